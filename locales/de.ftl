@@ -0,0 +1,216 @@
+# German
+
+app-name = Taskbar Hide
+
+## Tray
+
+tray-show = Taskleiste anzeigen
+tray-hide = Taskleiste ausblenden
+tray-profile = Profil
+tray-quit = Beenden
+tray-simulation = Simulation: { $mode }
+tray-tooltip-mode = Taskbar Hide ({ $mode })
+
+## Notifications
+
+notify-hidden = Taskleiste ausgeblendet
+notify-hidden-on = Taskleiste ausgeblendet auf { $monitors }
+notify-shown = Taskleiste angezeigt
+notify-shown-on = Taskleiste angezeigt auf { $monitors }
+notify-explorer-restarted = Der Explorer wurde neu gestartet, der Zustand der Taskleiste wurde erneut angewendet
+notify-config-failed = Die Konfiguration konnte nicht geladen werden, es gelten die Standardwerte: { $error }
+flapping-report = Die Taskleiste auf { $monitor } ist { $rehides } Mal in { $seconds } s wieder erschienen; erneutes Ausblenden pausiert für { $backoff } s
+flapping-report-culprit = Die Taskleiste auf { $monitor } ist { $rehides } Mal in { $seconds } s wieder erschienen, vermutlich durch { $process }; erneutes Ausblenden pausiert für { $backoff } s
+
+## Command line
+
+cli-unknown-command = Unbekannter Befehl: { $command }
+cli-not-running = Fehler: THide läuft nicht!
+cli-already-running = THide läuft bereits.
+cli-starting = THide wird gestartet...
+cli-showing = Taskleiste wird angezeigt...
+cli-hiding = Taskleiste wird ausgeblendet...
+cli-stopping = THide wird beendet...
+cli-showing-element = { $element } wird angezeigt...
+cli-hiding-element = { $element } wird ausgeblendet...
+cli-snoozing = Taskleiste wird für { $minutes } Minuten angezeigt...
+cli-unknown-language = Unbekannte Sprache { $language }, verfügbar: { $available }
+cli-would-post = Würde { $message } an das Fenster { $window } senden
+cli-would-send = Würde { $kind } „{ $data }" an das Fenster { $window } senden
+cli-snooze-usage = Aufruf: thide snooze <Minuten>
+cli-element-usage = Aufruf: thide { $action } --element <{ $elements }>
+cli-list-candidates-none = Keine passenden Fenster gefunden.
+state-hidden = ausgeblendet
+state-visible = sichtbar
+
+status-not-running = THide läuft nicht.
+status-running = THide läuft (PID { $pid })
+status-mode = {"  "}Modus:    { $mode } (keine Änderungen am Desktop)
+status-profile = {"  "}Profil:   { $profile }
+status-taskbar = {"  "}Taskleiste: { $state }
+status-monitor = {"            "}{ $monitor } { $state }
+status-snoozed = {"  "}Pausiert: noch { $minutes } min { $seconds } s
+status-warning = {"  "}Warnung:  { $message }
+
+script-usage = Aufruf: thide script <record|test> ...
+script-record-usage = Aufruf: thide script record <snapshot.json>
+script-test-usage = Aufruf: thide script test <script.rhai> [snapshot.json]
+script-snapshot-saved = Snapshot gespeichert in { $path }
+script-no-decision = Das Skript hat nichts entschieden, der Zustand im Infobereich gilt für alle Monitore.
+script-hide = ausblenden
+script-show = anzeigen
+script-error = Skriptfehler: { $error }
+
+config-usage = Aufruf: thide config <init|check|show|edit|migrate|schema|path> ...
+config-appdata-unset = Fehler: %APPDATA% ist nicht gesetzt
+config-programdata-unset = Fehler: %PROGRAMDATA% ist nicht gesetzt
+config-exists = { $path } existiert bereits, mit --force wird die Datei überschrieben
+config-written = Konfiguration geschrieben nach { $path }
+config-missing = Keine Konfigurationsdatei unter { $path }, es gelten die Standardwerte
+config-not-found = { $path } existiert nicht
+config-schema-written = Schema geschrieben nach { $path }
+config-outdated = Warnung: für Konfigurationsversion { $version } geschrieben, `thide config migrate` aktualisiert die Datei
+config-warning = Warnung: { $message }
+config-valid = { $path } ist gültig
+config-valid-warnings = { $path } ist gültig, mit { $count } Warnung(en)
+config-migrate-missing = Keine Konfigurationsdatei unter { $path }, nichts zu migrieren
+config-migrate-current = { $path } hat bereits die Konfigurationsversion { $version }
+config-migrate-would = Würde { $path } von Konfigurationsversion { $from } auf { $to } aktualisieren
+config-migrated = { $path } von Konfigurationsversion { $from } auf { $to } aktualisiert, das Original liegt in { $backup }
+
+replay-usage = Aufruf: thide replay <trace.jsonl>
+replay-summary = { $inputs } Eingaben abgespielt, aufgezeichnet im Modus { $mode }
+replay-match = Alle Aktionen stimmen mit der Aufzeichnung überein.
+replay-divergence = Eingabe #{ $step } bei { $ms } ms ({ $input }):
+replay-reordered = {"  "}gleiche Aktionen in anderer Reihenfolge oder Anzahl
+replay-diverged = { $diverged } von { $inputs } Eingaben erzeugten andere Aktionen
+
+autostart-enabled = ✓ Autostart erfolgreich aktiviert!
+autostart-enabled-hint = {"  "}THide startet automatisch, wenn Sie sich anmelden.
+autostart-enable-failed = Autostart konnte nicht aktiviert werden: { $error }
+autostart-disabled = ✓ Autostart erfolgreich deaktiviert!
+autostart-not-enabled = Autostart war nicht aktiviert.
+autostart-disable-failed = Autostart konnte nicht deaktiviert werden: { $error }
+
+profile-list = Profile:
+profile-unknown = Unbekanntes Profil: { $name }
+profile-available = Verfügbare Profile: { $profiles }
+profile-switching = Wechsle zu Profil { $name }...
+
+usage =
+    THide - Taskleiste ausblenden
+
+    AUFRUF:
+        thide [OPTIONEN] [BEFEHL]
+
+    BEFEHLE:
+        start              THide mit Infobereich-Symbol starten
+        daemon             THide im Vordergrund ohne Infobereich-Symbol ausführen
+        show               Taskleiste anzeigen (wenn THide läuft)
+        hide               Taskleiste ausblenden (wenn THide läuft)
+        hide --element E   Shell-Element ausblenden: desktop-icons, start-button,
+                           notification-area oder widgets (show --element stellt es wieder her)
+        stop               THide beenden und Taskleiste wiederherstellen
+        enable-autostart   Autostart bei der Anmeldung aktivieren
+        disable-autostart  Autostart bei der Anmeldung deaktivieren
+        profile [NAME]     Zu einem Profil wechseln oder Profile auflisten
+        snooze MINUTEN     Taskleiste eine Weile anzeigen und danach wieder ausblenden
+        status             Zustand der laufenden Instanz und Warnungen anzeigen
+        script record <snapshot.json>
+                           Aktuellen Desktop-Zustand zum Testen von Skripten speichern
+        script test <script.rhai> [snapshot.json]
+                           Skript mit einem Snapshot auswerten
+        list-candidates    Fenster auflisten, die die Taskleisten-Regeln erfassen oder ablehnen
+        doctor [--json]    Taskleisten-Fenster, Instanz, Autostart und Konfiguration prüfen
+        replay <trace.jsonl>
+                           Aufgezeichneten Trace abspielen und abweichende Aktionen melden
+        config init [--force]
+                           Kommentierte Standard-Konfiguration anlegen
+        config check [DATEI]
+                           Konfigurationsdatei prüfen
+        config show [--effective]
+                           Konfigurationsdatei ausgeben, oder die wirksamen Werte und ihre Herkunft
+        config edit        Konfiguration in $EDITOR oder Notepad öffnen, danach prüfen
+        config migrate [--dry-run]
+                           Konfiguration auf die aktuelle Version bringen, mit Sicherungskopie
+        config schema [DATEI]
+                           JSON-Schema der Konfiguration ausgeben oder speichern
+        config path [--system]
+                           Pfad der eigenen oder der systemweiten Konfigurationsdatei ausgeben
+        help               Diese Hilfe anzeigen
+
+    OPTIONEN:
+        --replace          Laufende Instanz beenden und übernehmen
+        --profile NAME     Mit einem Profil starten (wird weitergeleitet, wenn THide läuft)
+        --instance NAME    Eine isolierte, benannte Instanz ausführen oder steuern
+        --dry-run          Änderungen am Desktop nur protokollieren statt ausführen
+        --simulate DATEI   Mit einem simulierten Desktop aus einem JSON-Szenario ausführen
+        --record DATEI     Desktop-Abfragen, Aktionen und Eingaben in einem Trace aufzeichnen
+        --lang SPRACHE     Sprache von Menü und Meldungen, z. B. en oder de
+        --set KEY=WERT     Konfigurationsoption überschreiben, z. B. --set hide.strategy=dim
+
+## Doctor
+
+doctor-header = THide-Diagnose { $version }
+doctor-header-simulated = THide-Diagnose { $version } (simulierter Desktop)
+doctor-totals = { $passed } bestanden, { $warnings } Warnung(en), { $failed } fehlgeschlagen
+doctor-title-taskbar-windows = Taskleistenfenster
+doctor-title-taskbar-visibility = Sichtbarkeit
+doctor-title-appbar = AppBar-Zustand
+doctor-title-monitors = Monitore
+doctor-title-instance = Laufende Instanz
+doctor-title-autostart = Autostart
+doctor-title-config = Konfigurationsdatei
+doctor-no-taskbars = Kein Fenster passt zu den Taskleistenregeln, nichts kann ausgeblendet werden
+doctor-taskbars = { $count } Taskleistenfenster auf { $monitors } Monitor(en) gefunden
+doctor-managed = Von der laufenden Instanz verwaltet
+doctor-all-visible = Alle Taskleisten sind sichtbar
+doctor-hidden-taskbar = { $window } auf { $monitor }
+doctor-hidden-taskbars = { $count } Taskleiste(n) ausgeblendet, obwohl THide nicht läuft, `thide start` und danach `thide stop` stellt sie wieder her
+doctor-appbar-state = Zustand { $state } ({ $flags })
+doctor-appbar-auto-hide = automatisch ausblenden
+doctor-appbar-always-on-top = immer im Vordergrund
+doctor-appbar-normal = normal
+doctor-taskbar-position = Primäre Taskleiste am Rand { $edge }, ({ $left }, { $top }) - ({ $right }, { $bottom })
+doctor-taskbar-position-unknown = Position der primären Taskleiste unbekannt
+doctor-edge-left = links
+doctor-edge-top = oben
+doctor-edge-right = rechts
+doctor-edge-bottom = unten
+doctor-monitor = { $width }x{ $height } mit { $dpi } DPI, Arbeitsbereich ({ $left }, { $top }) - ({ $right }, { $bottom })
+doctor-monitor-count = { $count } Monitor(e)
+doctor-no-monitors = Keine Monitore gefunden
+doctor-work-area-outside = Arbeitsbereich außerhalb des Monitors auf { $monitors }
+doctor-not-running = THide läuft nicht
+doctor-running = Läuft (PID { $pid }, Profil { $profile }, Modus { $mode })
+doctor-unresponsive = Das IPC-Fenster existiert, aber die Instanz antwortet nicht, sie hängt vielleicht oder ist eine ältere Version
+doctor-stale-lock = Die Instanzsperre ist belegt, aber es gibt kein IPC-Fenster, beenden Sie thide.exe im Task-Manager oder führen Sie `thide --replace` aus
+doctor-autostart-disabled = Deaktiviert
+doctor-autostart-enabled = Aktiviert
+doctor-autostart-missing = Autostart verweist auf eine Datei, die nicht mehr existiert, führen Sie `thide enable-autostart` aus
+doctor-autostart-other = Autostart startet eine andere thide.exe als diese
+doctor-appdata-unset = %APPDATA% ist nicht gesetzt
+doctor-config-invalid = Ungültige Konfiguration: { $error }
+doctor-config-defaults = Keine Konfigurationsdatei, es gelten die Standardwerte
+doctor-config-valid = Gültig
+doctor-config-problems = { $count } Problem(e) gefunden
+
+## Config validation and migration
+
+validate-suggestion = Hinweis: meinten Sie `{ $name }`?
+validate-empty-rule = Taskleistenregel #{ $rule } hat keine Kriterien und wird ignoriert
+validate-hotkey = Tastenkürzel { $keys } in { $scope }: { $error }
+validate-hotkey-profile = Profil { $name }
+validate-script-missing = Skript { $path } existiert nicht
+validate-unknown-language = Für die Sprache { $language } gibt es keine Übersetzung, verfügbar sind { $available }
+migrate-removed = `{ $key }` entfernt, frühere Versionen haben den Schlüssel ignoriert
+migrate-removed-suggestion = `{ $key }` entfernt, frühere Versionen haben den Schlüssel ignoriert, meinten Sie `{ $suggestion }`?
+
+## Taskbar rules
+
+rule-matched = Regel #{ $rule }
+rule-excluded = ausgeschlossen durch Regel #{ $rule }
+rule-class-mismatch = Klasse ist { $class }, nicht { $expected }
+rule-process-unknown = Prozess ist unbekannt, nicht { $expected }
+rule-process-mismatch = Prozess ist { $process }, nicht { $expected }
+rule-title-mismatch = Titel „{ $title }“ passt nicht zu „{ $expected }“
@@ -0,0 +1,216 @@
+# English, the fallback for keys missing in other catalogs
+
+app-name = Taskbar Hide
+
+## Tray
+
+tray-show = Show Taskbar
+tray-hide = Hide Taskbar
+tray-profile = Profile
+tray-quit = Quit
+tray-simulation = Simulation: { $mode }
+tray-tooltip-mode = Taskbar Hide ({ $mode })
+
+## Notifications
+
+notify-hidden = Taskbar hidden
+notify-hidden-on = Taskbar hidden on { $monitors }
+notify-shown = Taskbar shown
+notify-shown-on = Taskbar shown on { $monitors }
+notify-explorer-restarted = Explorer restarted, the taskbar state was applied again
+notify-config-failed = Failed to load the config, using defaults: { $error }
+flapping-report = Taskbar on { $monitor } reappeared { $rehides } times in { $seconds }s; re-hiding paused for { $backoff }s
+flapping-report-culprit = Taskbar on { $monitor } reappeared { $rehides } times in { $seconds }s, likely caused by { $process }; re-hiding paused for { $backoff }s
+
+## Command line
+
+cli-unknown-command = Unknown command: { $command }
+cli-not-running = Error: THide is not running!
+cli-already-running = THide is already running.
+cli-starting = Starting THide...
+cli-showing = Showing taskbar...
+cli-hiding = Hiding taskbar...
+cli-stopping = Stopping THide...
+cli-showing-element = Showing { $element }...
+cli-hiding-element = Hiding { $element }...
+cli-snoozing = Showing taskbar for { $minutes } minutes...
+cli-unknown-language = Unknown language { $language }, available: { $available }
+cli-would-post = Would post { $message } to the { $window } window
+cli-would-send = Would send { $kind } "{ $data }" to the { $window } window
+cli-snooze-usage = Usage: thide snooze <minutes>
+cli-element-usage = Usage: thide { $action } --element <{ $elements }>
+cli-list-candidates-none = No candidate windows found.
+state-hidden = hidden
+state-visible = visible
+
+status-not-running = THide is not running.
+status-running = THide is running (pid { $pid })
+status-mode = {"  "}Mode:     { $mode } (no changes reach the desktop)
+status-profile = {"  "}Profile:  { $profile }
+status-taskbar = {"  "}Taskbar:  { $state }
+status-monitor = {"            "}{ $monitor } { $state }
+status-snoozed = {"  "}Snoozed:  { $minutes }m { $seconds }s left
+status-warning = {"  "}Warning:  { $message }
+
+script-usage = Usage: thide script <record|test> ...
+script-record-usage = Usage: thide script record <snapshot.json>
+script-test-usage = Usage: thide script test <script.rhai> [snapshot.json]
+script-snapshot-saved = Snapshot saved to { $path }
+script-no-decision = Script made no decision, the tray state applies to all monitors.
+script-hide = hide
+script-show = show
+script-error = Script error: { $error }
+
+config-usage = Usage: thide config <init|check|show|edit|migrate|schema|path> ...
+config-appdata-unset = Error: %APPDATA% is not set
+config-programdata-unset = Error: %PROGRAMDATA% is not set
+config-exists = { $path } already exists, add --force to overwrite it
+config-written = Config written to { $path }
+config-missing = No config file at { $path }, using the defaults
+config-not-found = { $path } does not exist
+config-schema-written = Schema written to { $path }
+config-outdated = warning: written for config version { $version }, run `thide config migrate` to upgrade it
+config-warning = warning: { $message }
+config-valid = { $path } is valid
+config-valid-warnings = { $path } is valid, with { $count } warning(s)
+config-migrate-missing = No config file at { $path }, nothing to migrate
+config-migrate-current = { $path } is already at config version { $version }
+config-migrate-would = Would upgrade { $path } from config version { $from } to { $to }
+config-migrated = Upgraded { $path } from config version { $from } to { $to }, the original is in { $backup }
+
+replay-usage = Usage: thide replay <trace.jsonl>
+replay-summary = Replayed { $inputs } inputs recorded in { $mode } mode
+replay-match = All actions match the recording.
+replay-divergence = Input #{ $step } at { $ms } ms ({ $input }):
+replay-reordered = {"  "}same actions in a different order or number
+replay-diverged = { $diverged } of { $inputs } inputs produced different actions
+
+autostart-enabled = ✓ Autostart enabled successfully!
+autostart-enabled-hint = {"  "}THide will start automatically when you log in.
+autostart-enable-failed = Failed to enable autostart: { $error }
+autostart-disabled = ✓ Autostart disabled successfully!
+autostart-not-enabled = Autostart was not enabled.
+autostart-disable-failed = Failed to disable autostart: { $error }
+
+profile-list = Profiles:
+profile-unknown = Unknown profile: { $name }
+profile-available = Available profiles: { $profiles }
+profile-switching = Switching to profile { $name }...
+
+usage =
+    THide - Taskbar Hide Utility
+
+    USAGE:
+        thide [OPTIONS] [COMMAND]
+
+    COMMANDS:
+        start              Start THide in GUI mode
+        daemon             Run THide in the foreground without a tray icon
+        show               Show the taskbar (if THide is running)
+        hide               Hide the taskbar (if THide is running)
+        hide --element E   Hide a shell element: desktop-icons, start-button,
+                           notification-area or widgets (show --element restores it)
+        stop               Stop THide and restore taskbar
+        enable-autostart   Enable autostart on login
+        disable-autostart  Disable autostart on login
+        profile [NAME]     Switch to a profile, or list profiles
+        snooze MINUTES     Show the taskbar for a while, then hide it again
+        status             Show the state of the running instance and warnings
+        script record <snapshot.json>
+                           Save the current desktop state for script testing
+        script test <script.rhai> [snapshot.json]
+                           Evaluate a script against a snapshot
+        list-candidates    List windows the taskbar rules match or reject
+        doctor [--json]    Check the taskbar windows, instance, autostart and config
+        replay <trace.jsonl>
+                           Replay a recorded trace and report diverging actions
+        config init [--force]
+                           Write a commented default config file
+        config check [FILE]
+                           Validate the config file
+        config show [--effective]
+                           Print the config file, or the values in effect and where they come from
+        config edit        Open the config file in $EDITOR or Notepad, then check it
+        config migrate [--dry-run]
+                           Upgrade the config file to the current version, keeping a backup
+        config schema [FILE]
+                           Print or save the JSON Schema of the config file
+        config path [--system]
+                           Print where the user's or the system-wide config file is
+        help               Show this help message
+
+    OPTIONS:
+        --replace          Shut down the running instance and take over
+        --profile NAME     Start with a profile (forwarded if already running)
+        --instance NAME    Run or control an isolated, named instance
+        --dry-run          Log what would change on the desktop without changing it
+        --simulate FILE    Run against a simulated desktop from a JSON scenario
+        --record FILE      Record desktop reads, actions and inputs into a trace
+        --lang LANGUAGE    Language of the tray menu and messages, e.g. en or de
+        --set KEY=VALUE    Override a config option, e.g. --set hide.strategy=dim
+
+## Doctor
+
+doctor-header = THide doctor { $version }
+doctor-header-simulated = THide doctor { $version } (simulated desktop)
+doctor-totals = { $passed } passed, { $warnings } warning(s), { $failed } failed
+doctor-title-taskbar-windows = Taskbar windows
+doctor-title-taskbar-visibility = Taskbar visibility
+doctor-title-appbar = AppBar state
+doctor-title-monitors = Monitors
+doctor-title-instance = Running instance
+doctor-title-autostart = Autostart
+doctor-title-config = Config file
+doctor-no-taskbars = No window matched the taskbar rules, nothing can be hidden
+doctor-taskbars = { $count } taskbar window(s) matched on { $monitors } monitor(s)
+doctor-managed = Managed by the running instance
+doctor-all-visible = All taskbars are visible
+doctor-hidden-taskbar = { $window } on { $monitor }
+doctor-hidden-taskbars = { $count } taskbar(s) hidden while THide is not running, `thide start` then `thide stop` restores them
+doctor-appbar-state = State { $state } ({ $flags })
+doctor-appbar-auto-hide = auto-hide
+doctor-appbar-always-on-top = always on top
+doctor-appbar-normal = normal
+doctor-taskbar-position = Primary taskbar at the { $edge } edge, ({ $left }, { $top }) - ({ $right }, { $bottom })
+doctor-taskbar-position-unknown = Primary taskbar position unknown
+doctor-edge-left = left
+doctor-edge-top = top
+doctor-edge-right = right
+doctor-edge-bottom = bottom
+doctor-monitor = { $width }x{ $height } at { $dpi } DPI, work area ({ $left }, { $top }) - ({ $right }, { $bottom })
+doctor-monitor-count = { $count } monitor(s)
+doctor-no-monitors = No monitors found
+doctor-work-area-outside = Work area outside the monitor on { $monitors }
+doctor-not-running = THide is not running
+doctor-running = Running (pid { $pid }, profile { $profile }, { $mode } mode)
+doctor-unresponsive = The IPC window exists but the instance did not answer, it may be hung or an older version
+doctor-stale-lock = The instance lock is held but no IPC window exists, end thide.exe in Task Manager or run `thide --replace`
+doctor-autostart-disabled = Disabled
+doctor-autostart-enabled = Enabled
+doctor-autostart-missing = Autostart points to a file that no longer exists, run `thide enable-autostart`
+doctor-autostart-other = Autostart starts a different thide.exe than this one
+doctor-appdata-unset = %APPDATA% is not set
+doctor-config-invalid = Invalid config: { $error }
+doctor-config-defaults = No config file, using the defaults
+doctor-config-valid = Valid
+doctor-config-problems = { $count } problem(s) found
+
+## Config validation and migration
+
+validate-suggestion = help: did you mean `{ $name }`?
+validate-empty-rule = Taskbar rule #{ $rule } has no criteria and is ignored
+validate-hotkey = Hotkey { $keys } in { $scope }: { $error }
+validate-hotkey-profile = profile { $name }
+validate-script-missing = Script { $path } does not exist
+validate-unknown-language = Language { $language } has no translation, available are { $available }
+migrate-removed = Removed `{ $key }`, which earlier versions ignored
+migrate-removed-suggestion = Removed `{ $key }`, which earlier versions ignored, did you mean `{ $suggestion }`?
+
+## Taskbar rules
+
+rule-matched = rule #{ $rule }
+rule-excluded = excluded by rule #{ $rule }
+rule-class-mismatch = class is { $class }, not { $expected }
+rule-process-unknown = process is unknown, not { $expected }
+rule-process-mismatch = process is { $process }, not { $expected }
+rule-title-mismatch = title "{ $title }" does not match "{ $expected }"
@@ -0,0 +1,460 @@
+use crate::backend::{Action, Desktop, WindowId};
+use crate::config::AnimationConfig;
+use crate::easing::{blend, Curve, Timeline};
+use crate::geometry::{docked_edge, Edge, Rect};
+use crate::strategy::{Applied, HideStrategy, Undo};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// About 60 frames per second
+const FRAME_INTERVAL_MS: u64 = 16;
+
+/// How taskbars move when hidden or revealed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AnimationKind {
+    /// Hide and reveal instantly
+    #[default]
+    None,
+    /// Fade the taskbar out and in
+    Fade,
+    /// Slide over the screen edge the taskbar is docked to
+    Slide,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Hide,
+    Reveal,
+}
+
+impl Direction {
+    /// Visibility at the end of the animation, 0 hidden and 1 fully shown
+    fn target(self) -> f64 {
+        match self {
+            Direction::Hide => 0.0,
+            Direction::Reveal => 1.0,
+        }
+    }
+}
+
+/// What an animation changes on a window, restored once it ends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Original {
+    /// Alpha before fading, None if the window wasn't layered
+    Alpha(Option<u8>),
+    Rect {
+        rect: Rect,
+        edge: Edge,
+    },
+}
+
+impl Original {
+    fn capture(kind: AnimationKind, window: WindowId, desktop: &dyn Desktop) -> Option<Self> {
+        match kind {
+            AnimationKind::None => None,
+            AnimationKind::Fade => Some(Original::Alpha(desktop.window_alpha(window))),
+            AnimationKind::Slide => Self::rect(desktop.window_rect(window)?, window, desktop),
+        }
+    }
+
+    fn rect(rect: Rect, window: WindowId, desktop: &dyn Desktop) -> Option<Self> {
+        let name = desktop.window_monitor(window)?;
+        let monitor = desktop.monitors().into_iter().find(|m| m.name == name)?;
+        Some(Original::Rect {
+            rect,
+            edge: docked_edge(rect, monitor.rect),
+        })
+    }
+}
+
+/// A taskbar window fading or sliding out of or into view
+///
+/// A hide animation runs before the hide strategy is applied, a reveal animation after it was
+/// undone. Either can be turned around halfway and continues from where its last frame was.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animation {
+    pub direction: Direction,
+    original: Original,
+    timeline: Timeline,
+    duration: Duration,
+}
+
+impl Animation {
+    /// Start hiding a visible window, None if there is nothing to animate
+    pub fn hide(
+        window: WindowId,
+        applied: &Applied,
+        config: &AnimationConfig,
+        desktop: &dyn Desktop,
+    ) -> Option<Self> {
+        // Dimmed taskbars fade on their own
+        if applied.strategy == HideStrategy::Dim || desktop.is_visible(window) != Some(true) {
+            return None;
+        }
+
+        let original = Original::capture(config.kind, window, desktop)?;
+        Some(Self::new(Direction::Hide, original, 1.0, config))
+    }
+
+    /// Start revealing a hidden window, along with the actions undoing its hide strategy
+    ///
+    /// The window is put in its fully hidden animation state first, so undoing the strategy
+    /// doesn't show it at once.
+    pub fn reveal(
+        window: WindowId,
+        applied: &Applied,
+        config: &AnimationConfig,
+        desktop: &dyn Desktop,
+    ) -> Option<(Self, Vec<Action>)> {
+        if applied.strategy == HideStrategy::Dim {
+            return None;
+        }
+
+        // What the strategy changed comes from its undo record, the desktop shows the hidden state
+        let original = match (config.kind, &applied.undo) {
+            (AnimationKind::Fade, Undo::Alpha(alpha)) => Original::Alpha(*alpha),
+            (AnimationKind::Slide, Undo::Rect(rect)) => Original::rect(*rect, window, desktop)?,
+            (kind, _) => Original::capture(kind, window, desktop)?,
+        };
+        let animation = Self::new(Direction::Reveal, original, 0.0, config);
+
+        let mut actions = animation.frame_actions(window, 0.0);
+        actions.extend(
+            applied
+                .undo_actions(window)
+                .into_iter()
+                .filter(|action| !animation.owns(action)),
+        );
+        Some((animation, actions))
+    }
+
+    fn new(direction: Direction, original: Original, from: f64, config: &AnimationConfig) -> Self {
+        let duration = Duration::from_millis(config.duration_ms);
+
+        Self {
+            direction,
+            original,
+            timeline: Timeline::new(from, direction.target(), duration, config.curve),
+            duration,
+        }
+    }
+
+    /// Head the other way, continuing from the last frame
+    pub fn turn(&mut self, direction: Direction) {
+        if self.direction != direction {
+            self.direction = direction;
+            self.timeline.retarget(direction.target(), self.duration);
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.timeline.is_finished()
+    }
+
+    /// Actions drawing the frame at `now`
+    pub fn advance(&mut self, window: WindowId, now: Instant) -> Vec<Action> {
+        let visibility = self.timeline.advance(now);
+        self.frame_actions(window, visibility)
+    }
+
+    fn frame_actions(&self, window: WindowId, visibility: f64) -> Vec<Action> {
+        match self.original {
+            Original::Alpha(alpha) => vec![Action::SetWindowAlpha {
+                window,
+                alpha: Some(blend(
+                    0,
+                    alpha.unwrap_or(u8::MAX),
+                    Curve::Linear,
+                    visibility,
+                )),
+            }],
+            Original::Rect { rect, edge } => {
+                let distance = (f64::from(rect.extent(edge)) * (1.0 - visibility)).round();
+                vec![Action::MoveWindow {
+                    window,
+                    rect: rect.slid(edge, distance as i32),
+                }]
+            }
+        }
+    }
+
+    /// Actions restoring exactly what the animation changed
+    pub fn restore_actions(&self, window: WindowId) -> Vec<Action> {
+        match self.original {
+            Original::Alpha(alpha) => vec![Action::SetWindowAlpha { window, alpha }],
+            Original::Rect { rect, .. } => vec![Action::MoveWindow { window, rect }],
+        }
+    }
+
+    /// Whether an action changes what the animation changes
+    pub fn owns(&self, action: &Action) -> bool {
+        matches!(
+            (self.original, action),
+            (Original::Alpha(_), Action::SetWindowAlpha { .. })
+                | (Original::Rect { .. }, Action::MoveWindow { .. })
+        )
+    }
+
+    /// Record the state from before the animation in a strategy captured halfway through it
+    pub fn keep_original(&self, mut applied: Applied) -> Applied {
+        match (self.original, &mut applied.undo) {
+            (Original::Alpha(original), Undo::Alpha(alpha)) => *alpha = original,
+            (Original::Rect { rect: original, .. }, Undo::Rect(rect)) => *rect = original,
+            _ => {}
+        }
+        applied
+    }
+}
+
+/// Requests frames from the loop driving the app while animations run
+pub struct FrameScheduler {
+    frame: Arc<dyn Fn() -> bool + Send + Sync>,
+    running: Option<Arc<AtomicBool>>,
+}
+
+impl FrameScheduler {
+    /// `frame` delivers a frame request, false once the loop is gone
+    pub fn new(frame: Arc<dyn Fn() -> bool + Send + Sync>) -> Self {
+        Self {
+            frame,
+            running: None,
+        }
+    }
+
+    /// Start requesting frames, if not already doing so
+    pub fn start(&mut self) {
+        if self.running.is_some() {
+            return;
+        }
+
+        let running = Arc::new(AtomicBool::new(true));
+        let frame = Arc::clone(&self.frame);
+        std::thread::spawn({
+            let running = Arc::clone(&running);
+            move || {
+                while running.load(Ordering::Relaxed) && frame() {
+                    std::thread::sleep(Duration::from_millis(FRAME_INTERVAL_MS));
+                }
+            }
+        });
+        self.running = Some(running);
+    }
+
+    /// Stop requesting frames, a request already on its way is harmless
+    pub fn stop(&mut self) {
+        if let Some(running) = self.running.take() {
+            running.store(false, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Drop for FrameScheduler {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::Overlay;
+    use crate::config::DimConfig;
+    use crate::simulation::Scenario;
+    use crate::testing::{desktop, perform};
+
+    const TASKBAR: WindowId = 1;
+    const BOTTOM: Rect = Rect {
+        left: 0,
+        top: 1032,
+        right: 1920,
+        bottom: 1080,
+    };
+
+    fn config(kind: AnimationKind) -> AnimationConfig {
+        AnimationConfig {
+            kind,
+            duration_ms: 200,
+            curve: Curve::Linear,
+        }
+    }
+
+    fn capture(strategy: HideStrategy, desktop: &dyn Desktop) -> Applied {
+        strategy
+            .capture(TASKBAR, desktop, &DimConfig::default())
+            .unwrap()
+    }
+
+    /// Draw a frame every 50ms of a fake clock until the animation ends, returning the frame count
+    fn run(animation: &mut Animation, desktop: &Overlay<Scenario>, start: Instant) -> usize {
+        let mut frames = 0;
+        while !animation.is_finished() {
+            let now = start + Duration::from_millis(50 * frames as u64);
+            perform(desktop, animation.advance(TASKBAR, now));
+            frames += 1;
+            assert!(frames < 100, "the animation never ends");
+        }
+        frames
+    }
+
+    #[test]
+    fn fades_out_and_restores_the_original_alpha() {
+        let desktop = desktop();
+        let applied = capture(HideStrategy::Hide, &desktop);
+        let mut animation =
+            Animation::hide(TASKBAR, &applied, &config(AnimationKind::Fade), &desktop).unwrap();
+
+        let start = Instant::now();
+        perform(&desktop, animation.advance(TASKBAR, start));
+        assert_eq!(desktop.window_alpha(TASKBAR), Some(255));
+        perform(
+            &desktop,
+            animation.advance(TASKBAR, start + Duration::from_millis(100)),
+        );
+        assert_eq!(desktop.window_alpha(TASKBAR), Some(128));
+        perform(
+            &desktop,
+            animation.advance(TASKBAR, start + Duration::from_millis(200)),
+        );
+        assert_eq!(desktop.window_alpha(TASKBAR), Some(0));
+        assert!(animation.is_finished());
+
+        perform(&desktop, applied.hide_actions(TASKBAR, &desktop));
+        perform(&desktop, animation.restore_actions(TASKBAR));
+        assert_eq!(desktop.is_visible(TASKBAR), Some(false));
+        assert_eq!(desktop.window_alpha(TASKBAR), None);
+    }
+
+    #[test]
+    fn slides_over_the_docked_edge() {
+        let desktop = desktop();
+        let applied = capture(HideStrategy::Hide, &desktop);
+        let mut animation =
+            Animation::hide(TASKBAR, &applied, &config(AnimationKind::Slide), &desktop).unwrap();
+
+        assert_eq!(run(&mut animation, &desktop, Instant::now()), 5);
+        assert_eq!(
+            desktop.window_rect(TASKBAR),
+            Some(BOTTOM.slid(Edge::Bottom, 48))
+        );
+
+        perform(&desktop, animation.restore_actions(TASKBAR));
+        assert_eq!(desktop.window_rect(TASKBAR), Some(BOTTOM));
+    }
+
+    #[test]
+    fn reveals_start_fully_hidden() {
+        let desktop = desktop();
+        let applied = capture(HideStrategy::Transparent, &desktop);
+        perform(&desktop, applied.hide_actions(TASKBAR, &desktop));
+
+        let (mut animation, actions) =
+            Animation::reveal(TASKBAR, &applied, &config(AnimationKind::Fade), &desktop).unwrap();
+        // The strategy's own alpha undo would show the taskbar at once, the animation owns it
+        assert_eq!(
+            actions,
+            [Action::SetWindowAlpha {
+                window: TASKBAR,
+                alpha: Some(0),
+            }]
+        );
+        perform(&desktop, actions);
+
+        run(&mut animation, &desktop, Instant::now());
+        assert_eq!(desktop.window_alpha(TASKBAR), Some(255));
+        perform(&desktop, animation.restore_actions(TASKBAR));
+        assert_eq!(desktop.window_alpha(TASKBAR), None);
+    }
+
+    #[test]
+    fn reveals_undo_the_strategy_after_the_first_frame() {
+        let desktop = desktop();
+        let applied = capture(HideStrategy::OffScreen, &desktop);
+        perform(&desktop, applied.hide_actions(TASKBAR, &desktop));
+
+        let (mut animation, actions) =
+            Animation::reveal(TASKBAR, &applied, &config(AnimationKind::Fade), &desktop).unwrap();
+        assert_eq!(
+            actions,
+            [
+                Action::SetWindowAlpha {
+                    window: TASKBAR,
+                    alpha: Some(0),
+                },
+                Action::MoveWindow {
+                    window: TASKBAR,
+                    rect: BOTTOM,
+                },
+            ]
+        );
+        perform(&desktop, actions);
+        run(&mut animation, &desktop, Instant::now());
+        perform(&desktop, animation.restore_actions(TASKBAR));
+        assert_eq!(desktop.window_rect(TASKBAR), Some(BOTTOM));
+        assert_eq!(desktop.window_alpha(TASKBAR), None);
+    }
+
+    #[test]
+    fn turning_around_continues_from_the_last_frame() {
+        let desktop = desktop();
+        let applied = capture(HideStrategy::Hide, &desktop);
+        let mut animation =
+            Animation::hide(TASKBAR, &applied, &config(AnimationKind::Slide), &desktop).unwrap();
+
+        let start = Instant::now();
+        animation.advance(TASKBAR, start);
+        perform(
+            &desktop,
+            animation.advance(TASKBAR, start + Duration::from_millis(100)),
+        );
+        assert_eq!(
+            desktop.window_rect(TASKBAR),
+            Some(BOTTOM.slid(Edge::Bottom, 24))
+        );
+
+        animation.turn(Direction::Reveal);
+        assert_eq!(animation.direction, Direction::Reveal);
+        // Half the way back takes half the time
+        let frames = run(&mut animation, &desktop, start + Duration::from_millis(100));
+        assert_eq!(frames, 3);
+        assert_eq!(desktop.window_rect(TASKBAR), Some(BOTTOM));
+    }
+
+    #[test]
+    fn nothing_to_animate() {
+        let desktop = desktop();
+        let hide = capture(HideStrategy::Hide, &desktop);
+        let dim = capture(HideStrategy::Dim, &desktop);
+        let fade = config(AnimationKind::Fade);
+
+        assert_eq!(Animation::hide(TASKBAR, &dim, &fade, &desktop), None);
+        assert_eq!(Animation::reveal(TASKBAR, &dim, &fade, &desktop), None);
+        let none = config(AnimationKind::None);
+        assert_eq!(Animation::hide(TASKBAR, &hide, &none, &desktop), None);
+
+        perform(&desktop, hide.hide_actions(TASKBAR, &desktop));
+        assert_eq!(Animation::hide(TASKBAR, &hide, &fade, &desktop), None);
+    }
+
+    #[test]
+    fn strategies_captured_halfway_keep_the_original_state() {
+        let desktop = desktop();
+        let hide = capture(HideStrategy::Hide, &desktop);
+        let mut animation =
+            Animation::hide(TASKBAR, &hide, &config(AnimationKind::Fade), &desktop).unwrap();
+        let start = Instant::now();
+        animation.advance(TASKBAR, start);
+        perform(
+            &desktop,
+            animation.advance(TASKBAR, start + Duration::from_millis(100)),
+        );
+
+        let transparent = animation.keep_original(capture(HideStrategy::Transparent, &desktop));
+        assert_eq!(transparent.undo, Undo::Alpha(None));
+        let off_screen = animation.keep_original(capture(HideStrategy::OffScreen, &desktop));
+        assert_eq!(off_screen.undo, Undo::Rect(BOTTOM));
+    }
+}
@@ -0,0 +1,724 @@
+use crate::animation::FrameScheduler;
+use crate::appbar::ReservedAppBar;
+use crate::backend::{self, Backend, Mode, Overlay, Win32Backend};
+use crate::cli::{self, LaunchOptions};
+use crate::config::{self, Config};
+use crate::controller::{Command, DesiredState, Input, TaskbarController, TransitionKind};
+use crate::elements::ShellElement;
+use crate::flapping::FlapReport;
+use crate::hooks::HookRunner;
+use crate::hotkeys::{self, HotkeyAction};
+use crate::i18n;
+use crate::idle::{self, IdleAction, IdleOverride};
+use crate::instance::{self, ConflictAction, InstanceLock, InstanceNames};
+use crate::notify::{self, Notification, Notifier};
+use crate::profiles::{self, Profile};
+use crate::state::{self, RuntimeState, StartupMode};
+use crate::trace::{self, Recorder};
+use crate::{logging, script, simulation, status, targets};
+use std::collections::BTreeMap;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
+use windows::Win32::System::DataExchange::COPYDATASTRUCT;
+use windows::Win32::UI::HiDpi::{
+    SetProcessDpiAwarenessContext, DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW, PostQuitMessage,
+    RegisterClassW, TranslateMessage, HWND_MESSAGE, MSG, WM_COPYDATA, WM_HOTKEY, WNDCLASSW,
+    WS_OVERLAPPEDWINDOW,
+};
+
+// Constants
+const TICK_INTERVAL_MS: u64 = 100;
+const QUIT_HOOK_TIMEOUT_MS: u64 = 5000;
+const REPLACE_TIMEOUT_MS: u64 = 10_000;
+
+// IPC Message Types
+#[derive(Debug, Clone)]
+pub enum IPCMessage {
+    Show,
+    Hide,
+    Quit,
+    /// Time to look for taskbars and shell elements that reappeared
+    Tick,
+    /// Time to draw the next frame of running animations
+    Frame,
+    ScriptDecision(BTreeMap<String, bool>),
+    Profile(String),
+    Hotkey(HotkeyAction),
+    Launch(Vec<String>),
+    Element(ShellElement, bool),
+    Idle(IdleAction),
+    /// Show the taskbar for this many minutes, then hide it again
+    Snooze(u64),
+}
+
+/// Delivers a message to the loop driving the app, false once that loop is gone
+pub type EventSender = Arc<dyn Fn(IPCMessage) -> bool + Send + Sync>;
+
+// Global event sender for the IPC window procedure
+static GLOBAL_EVENT_SENDER: Mutex<Option<EventSender>> = Mutex::new(None);
+
+/// The config, the error it failed to load with if any, and the single-instance lock
+pub type Prepared = (Config, Option<String>, InstanceLock);
+
+/// Become DPI aware, load the config, take the single-instance lock and select the backend
+///
+/// Returns None when the launch was forwarded to the instance that is already running. A config
+/// that failed to load is replaced by the defaults, the error is returned for notifying the user.
+pub fn prepare(
+    launch: &LaunchOptions,
+    instance_name: Option<&str>,
+) -> Result<Option<Prepared>, Box<dyn std::error::Error>> {
+    logging::init();
+    log::info!("Language: {}", i18n::language());
+
+    // Window and monitor rects in physical pixels on every monitor, before any window exists
+    if let Err(e) =
+        unsafe { SetProcessDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2) }
+    {
+        log::warn!("Failed to become per-monitor DPI aware: {}", e);
+    }
+    let (config, config_error) = match config::load() {
+        Ok(config) => (config, None),
+        Err(e) => {
+            log::error!("Failed to load config, using defaults: {}", e);
+            (Config::default(), Some(e.to_string()))
+        }
+    };
+
+    // Ensure single instance
+    let names = InstanceNames::new(instance_name, config.instance.scope);
+    instance::init(names.clone());
+    let mut instance_lock = InstanceLock::acquire(&names)?;
+
+    if !instance_lock.is_owned() {
+        if launch.replace {
+            log::info!("Replacing the running instance");
+            let (_, _, msg_quit) = cli::get_message_ids();
+            cli::post_message(msg_quit);
+
+            if !instance_lock.wait_for_owner(Duration::from_millis(REPLACE_TIMEOUT_MS)) {
+                log::error!("The running instance did not exit, giving up");
+                std::process::exit(instance::EXIT_ALREADY_RUNNING);
+            }
+        } else {
+            if config.instance.on_conflict == ConflictAction::Forward {
+                let unapplied = launch.unapplied();
+                if !unapplied.is_empty() {
+                    log::warn!(
+                        "The running instance can't apply {}, use --replace to restart with them",
+                        unapplied.join(", ")
+                    );
+                }
+                let forwarded = launch.forwarded_args().join("\0");
+                if cli::send_copydata(cli::get_copydata_launch_id(), forwarded.as_bytes()) {
+                    log::info!("Forwarded launch to the running instance");
+                    return Ok(None);
+                }
+            }
+
+            log::warn!("Another instance is already running");
+            std::process::exit(instance::EXIT_ALREADY_RUNNING);
+        }
+    }
+
+    targets::init(config.taskbars.clone());
+
+    // Dry runs and simulations log their changes instead of touching the desktop
+    let mut desktop: Box<dyn Backend> = if let Some(path) = &launch.simulate {
+        let scenario = simulation::Scenario::load(path)
+            .inspect_err(|e| log::error!("Failed to load scenario {}: {}", path, e))?;
+        Box::new(Overlay::new(scenario, Mode::Simulated))
+    } else if launch.dry_run {
+        Box::new(Overlay::new(Win32Backend, Mode::DryRun))
+    } else {
+        Box::new(Win32Backend)
+    };
+
+    if let Some(path) = &launch.record {
+        trace::start(
+            path,
+            desktop.mode(),
+            &config.work_area,
+            &config.hide,
+            &config.animation,
+        )
+        .inspect_err(|e| log::error!("Failed to create trace {}: {}", path, e))?;
+        log::info!("Recording a trace to {}", path);
+        desktop = Box::new(Recorder::new(desktop));
+    }
+
+    backend::init(desktop);
+    Ok(Some((config, config_error, instance_lock)))
+}
+
+/// The taskbar controller with everything feeding it, shared by the tray and daemon modes
+///
+/// Every entry point goes through the controller. Dropping the app quits it if that didn't happen
+/// yet, which restores the taskbar, work areas and shell elements, then releases the instance lock.
+pub struct App {
+    controller: TaskbarController,
+    hooks: HookRunner,
+    profiles: BTreeMap<String, Profile>,
+    profile_name: String,
+    /// When a snoozed taskbar is hidden again
+    snooze: Option<Instant>,
+    /// Taskbars another program keeps re-showing, empty once that stopped
+    flapping: Vec<FlapReport>,
+    /// Whether the idle policy hid the taskbar, so a reveal restores the user's state
+    idle: IdleOverride,
+    /// Requests frames while the controller animates a taskbar
+    frames: FrameScheduler,
+    notifier: Notifier,
+    mode: Mode,
+    _reserved_appbar: Option<ReservedAppBar>,
+    _instance_lock: InstanceLock,
+}
+
+impl App {
+    /// Start the IPC window, script, idle and ticker threads, then apply the initial state
+    pub fn start(
+        config: Config,
+        config_error: Option<String>,
+        launch: &LaunchOptions,
+        instance_lock: InstanceLock,
+        send: EventSender,
+    ) -> Self {
+        let mode = backend::current().mode();
+        let profiles = profiles::resolve(&config);
+        let persisted = state::load();
+        let active_profile = launch
+            .profile
+            .clone()
+            .or_else(|| persisted.active_profile.clone())
+            .and_then(|name| profiles.get(&name).cloned())
+            .or_else(|| profiles.get(profiles::DEFAULT_PROFILE).cloned())
+            .unwrap_or_default();
+
+        // Reserve a strip for a custom bar until thide exits
+        let reserve = config.reserve.clone().filter(|strip| strip.thickness > 0);
+        let reserved_appbar = match reserve {
+            Some(strip) if mode != Mode::Live => {
+                log::info!(
+                    "[{}] Would reserve {} DIPs at the {:?} edge",
+                    mode.as_str(),
+                    strip.thickness,
+                    strip.edge
+                );
+                None
+            }
+            reserve => reserve.map(ReservedAppBar::register),
+        };
+
+        let mut initial_state = DesiredState::for_profile(&active_profile);
+        initial_state.elements = config.elements.hide.iter().copied().collect();
+        let mut snooze: Option<Instant> = None;
+
+        match config.startup.mode {
+            StartupMode::AlwaysHide => initial_state.hidden = true,
+            StartupMode::AlwaysShow => initial_state.hidden = false,
+            StartupMode::Remember => {
+                if let Some(hidden) = persisted.hidden {
+                    initial_state.hidden = hidden;
+                    if active_profile.rules {
+                        initial_state.monitors = persisted.monitors.clone();
+                    }
+                }
+
+                // A snooze that ran out while thide wasn't running would have hidden the taskbar
+                if let Some(remaining) = persisted.snooze_remaining() {
+                    initial_state.hidden = false;
+                    snooze = Some(Instant::now() + remaining);
+                } else if persisted.snooze_until.is_some() {
+                    initial_state.hidden = true;
+                    save_state(mode, "the ended snooze", |state| state.snooze_until = None);
+                }
+            }
+        }
+
+        hotkeys::request(active_profile.hotkeys.clone().unwrap_or_default());
+
+        // Setup IPC for CLI communication
+        create_ipc_window(Arc::clone(&send));
+
+        // Evaluate the user script, its decisions arrive as per-monitor overrides
+        if config.script.path.is_some() {
+            let send = Arc::clone(&send);
+            script::spawn_script_thread(config.script, move |decision| {
+                send(IPCMessage::ScriptDecision(decision))
+            });
+        }
+
+        // Hide after inactivity or long focus, reveal on the configured triggers
+        idle::spawn_idle_thread(config.idle.clone(), {
+            let send = Arc::clone(&send);
+            move |action| send(IPCMessage::Idle(action))
+        });
+
+        let frames = FrameScheduler::new({
+            let send = Arc::clone(&send);
+            Arc::new(move || send(IPCMessage::Frame))
+        });
+        spawn_ticker(send);
+
+        let mut app = Self {
+            controller: TaskbarController::new(
+                config.work_area.clone(),
+                config.hide.clone(),
+                config.animation.clone(),
+            ),
+            hooks: HookRunner::new(config.hooks),
+            profiles,
+            profile_name: active_profile.name.clone(),
+            snooze,
+            flapping: Vec::new(),
+            idle: IdleOverride::default(),
+            frames,
+            notifier: Notifier::new(config.notifications.clone()),
+            mode,
+            _reserved_appbar: reserved_appbar,
+            _instance_lock: instance_lock,
+        };
+        app.run(Input::Start(active_profile, initial_state));
+        if let Some(error) = config_error {
+            let notification = app.notifier.config_failed(&error, Instant::now());
+            app.notify(notification);
+        }
+        app.publish_status();
+        app
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Taskbars another program keeps re-showing
+    pub fn flapping(&self) -> &[FlapReport] {
+        &self.flapping
+    }
+
+    /// Names of all profiles, in menu order
+    pub fn profile_names(&self) -> Vec<String> {
+        self.profiles.keys().cloned().collect()
+    }
+
+    /// Name of the active profile
+    pub fn profile_name(&self) -> &str {
+        &self.profile_name
+    }
+
+    /// Look up a profile by name and build the command switching to it
+    pub fn profile_command(&self, name: &str) -> Option<Command> {
+        match self.profiles.get(name) {
+            Some(profile) => Some(Command::ApplyProfile(profile.clone())),
+            None => {
+                log::warn!("Unknown profile {}", name);
+                None
+            }
+        }
+    }
+
+    /// Translate a message from the IPC window or a background thread into a command
+    pub fn on_message(&mut self, message: IPCMessage) -> Option<Command> {
+        match message {
+            IPCMessage::Show => Some(Command::Show),
+            IPCMessage::Hide => Some(Command::Hide),
+            IPCMessage::Quit => Some(Command::Quit),
+            IPCMessage::Frame => {
+                self.run(Input::Frame(Instant::now()));
+                None
+            }
+            IPCMessage::Tick => {
+                let now = Instant::now();
+                self.run(Input::Tick(now));
+
+                let reports = self.controller.flap_reports(now);
+                if reports != self.flapping {
+                    for notification in self.notifier.flapping(&reports, &self.flapping, now) {
+                        self.notify(Some(notification));
+                    }
+                    self.flapping = reports;
+                    self.publish_status();
+                }
+                None
+            }
+            IPCMessage::ScriptDecision(decision) => Some(Command::SetMonitorOverrides(decision)),
+            IPCMessage::Profile(name) => self.profile_command(&name),
+            IPCMessage::Launch(args) => {
+                log::info!("Second launch forwarded: {:?}", args);
+                let launch = LaunchOptions::parse(&args);
+                let unapplied = launch.unapplied();
+                if !unapplied.is_empty() {
+                    log::warn!(
+                        "Ignoring {} from a second launch, they only apply on start",
+                        unapplied.join(", ")
+                    );
+                }
+                launch.profile.and_then(|name| self.profile_command(&name))
+            }
+            IPCMessage::Element(element, hidden) => Some(Command::SetElement(element, hidden)),
+            IPCMessage::Snooze(minutes) => {
+                let duration = Duration::from_secs(minutes * 60);
+                log::info!("Snoozing for {} minutes", minutes);
+                self.snooze = Some(Instant::now() + duration);
+                save_state(self.mode, "the snooze", |state| {
+                    state.snooze_until = Some(state::unix_time_after(duration))
+                });
+                Some(Command::Show)
+            }
+            IPCMessage::Idle(action) => {
+                // Run here rather than returned, execute() treats its commands as the user's
+                let hidden = self.controller.desired().hidden;
+                if let Some(command) = self.idle.command(action, hidden) {
+                    self.run(Input::Command(command));
+                    self.publish_status();
+                }
+                None
+            }
+            IPCMessage::Hotkey(action) => match action {
+                HotkeyAction::Show => Some(Command::Show),
+                HotkeyAction::Hide => Some(Command::Hide),
+                HotkeyAction::Toggle => Some(Command::Toggle),
+                HotkeyAction::Quit => Some(Command::Quit),
+                HotkeyAction::Profile(name) => self.profile_command(&name),
+            },
+        }
+    }
+
+    /// Hide again once the snooze is over
+    pub fn on_tick(&mut self, now: Instant) -> Option<Command> {
+        if self.snooze.is_some_and(|deadline| now >= deadline) {
+            self.snooze = None;
+            save_state(self.mode, "the ended snooze", |state| {
+                state.snooze_until = None
+            });
+            return Some(Command::Hide);
+        }
+
+        None
+    }
+
+    /// Next time `on_tick` has something to do
+    pub fn deadline(&self) -> Option<Instant> {
+        self.snooze
+    }
+
+    /// Run a command, returns false once the app quit
+    pub fn execute(&mut self, command: Command) -> bool {
+        let quit = command == Command::Quit;
+
+        if matches!(
+            command,
+            Command::Show | Command::Hide | Command::Toggle | Command::ApplyProfile(_)
+        ) {
+            self.idle.user_changed();
+        }
+
+        if let Command::ApplyProfile(profile) = &command {
+            log::info!("Switching to profile {}", profile.name);
+            self.profile_name = profile.name.clone();
+            hotkeys::request(profile.hotkeys.clone().unwrap_or_default());
+            let name = profile.name.clone();
+            save_state(self.mode, "the active profile", |state| {
+                state.active_profile = Some(name)
+            });
+        }
+
+        self.run(Input::Command(command));
+
+        if quit {
+            self.hooks
+                .wait_idle(Duration::from_millis(QUIT_HOOK_TIMEOUT_MS));
+            return false;
+        }
+
+        self.publish_status();
+        true
+    }
+
+    /// Feed an input to the controller, perform its actions and dispatch its transitions
+    fn run(&mut self, input: Input) {
+        let backend = backend::current();
+        trace::record_input(&input);
+        let output = self.controller.handle(input, backend);
+
+        for action in output.actions {
+            backend.perform(action);
+        }
+
+        if self.controller.is_animating() {
+            self.frames.start();
+        } else {
+            self.frames.stop();
+        }
+
+        for notification in self
+            .notifier
+            .transitions(&output.transitions, Instant::now())
+        {
+            self.notify(Some(notification));
+        }
+
+        for transition in &output.transitions {
+            if self.mode == Mode::Live {
+                self.hooks.dispatch(transition);
+            } else {
+                for command_line in self.hooks.commands(transition.kind) {
+                    log::info!(
+                        "[{}] Would run hook {} `{}`",
+                        self.mode.as_str(),
+                        transition.kind.as_str(),
+                        command_line
+                    );
+                }
+            }
+
+            // Persist every show/hide so the next start can restore it, but not idle hides
+            let persisted = matches!(transition.kind, TransitionKind::Hide | TransitionKind::Show);
+            if persisted && !self.idle.is_hiding() {
+                let desired = self.controller.desired().clone();
+                save_state(self.mode, "the taskbar state", |state| {
+                    state.hidden = Some(desired.hidden);
+                    state.monitors = desired.monitors;
+                });
+            }
+        }
+    }
+
+    /// Show a notification, only logging it in dry-run and simulation mode
+    fn notify(&self, notification: Option<Notification>) {
+        let Some(notification) = notification else {
+            return;
+        };
+
+        if self.mode == Mode::Live {
+            notify::show(&notification);
+        } else {
+            log::info!(
+                "[{}] Would notify: {}",
+                self.mode.as_str(),
+                notification.body
+            );
+        }
+    }
+
+    fn publish_status(&self) {
+        let desired = self.controller.desired().clone();
+        status::publish(status::Status {
+            pid: std::process::id(),
+            mode: self.mode.as_str().to_string(),
+            profile: self.profile_name.clone(),
+            hidden: desired.hidden,
+            monitors: desired.monitors,
+            snooze_remaining_secs: self
+                .snooze
+                .map(|deadline| deadline.saturating_duration_since(Instant::now()).as_secs()),
+            flapping: self.flapping.clone(),
+        });
+    }
+}
+
+impl Drop for App {
+    fn drop(&mut self) {
+        // The loop may end without a Quit, e.g. when the tray's event loop fails
+        if !self.controller.is_stopped() {
+            self.execute(Command::Quit);
+        }
+
+        if self.mode == Mode::Live {
+            notify::remove();
+        }
+    }
+}
+
+/// Change the persisted state, only logging it in dry-run and simulation mode
+///
+/// Otherwise a dry run would change what the next real start restores.
+fn save_state(mode: Mode, what: &str, change: impl FnOnce(&mut RuntimeState)) {
+    if mode == Mode::Live {
+        state::update(change);
+    } else {
+        log::info!("[{}] Would save {}", mode.as_str(), what);
+    }
+}
+
+/// Run without a tray icon or winit window until `thide stop` or a Quit hotkey
+pub fn run_daemon(
+    launch: &LaunchOptions,
+    instance_name: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some((config, config_error, instance_lock)) = prepare(launch, instance_name)? else {
+        return Ok(());
+    };
+    log::info!("Running headless");
+
+    let (sender, receiver) = mpsc::channel();
+    let send: EventSender = Arc::new(move |message| sender.send(message).is_ok());
+    let mut app = App::start(config, config_error, launch, instance_lock, send);
+
+    loop {
+        let message = match app.deadline() {
+            Some(deadline) => {
+                match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                    Ok(message) => Some(message),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+            None => match receiver.recv() {
+                Ok(message) => Some(message),
+                Err(_) => break,
+            },
+        };
+
+        let command = message.and_then(|message| app.on_message(message));
+        for command in command.into_iter().chain(app.on_tick(Instant::now())) {
+            if !app.execute(command) {
+                return Ok(());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Tick the controller until the loop driving the app is gone
+fn spawn_ticker(send: EventSender) {
+    std::thread::spawn(move || {
+        while send(IPCMessage::Tick) {
+            std::thread::sleep(Duration::from_millis(TICK_INTERVAL_MS));
+        }
+    });
+}
+
+/// Create a hidden IPC window for CLI communication
+fn create_ipc_window(send: EventSender) {
+    std::thread::spawn(move || unsafe {
+        let class_name: Vec<u16> = format!("{}\0", cli::get_ipc_window_class())
+            .encode_utf16()
+            .collect();
+
+        let wc = WNDCLASSW {
+            lpfnWndProc: Some(ipc_window_proc),
+            lpszClassName: windows::core::PCWSTR(class_name.as_ptr()),
+            ..Default::default()
+        };
+
+        RegisterClassW(&wc);
+
+        GLOBAL_EVENT_SENDER
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .replace(send);
+
+        let hwnd = CreateWindowExW(
+            Default::default(),
+            windows::core::PCWSTR(class_name.as_ptr()),
+            windows::core::PCWSTR::null(),
+            WS_OVERLAPPEDWINDOW,
+            0,
+            0,
+            0,
+            0,
+            HWND_MESSAGE,
+            None,
+            None,
+            None,
+        );
+
+        let Ok(hwnd) = hwnd else {
+            log::error!("Failed to create IPC window");
+            return;
+        };
+
+        // Hotkeys are delivered to the window that registered them
+        hotkeys::register_pending(hwnd);
+
+        let mut msg = MSG::default();
+        while GetMessageW(&mut msg, None, 0, 0).as_bool() {
+            let _ = TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+    });
+}
+
+/// Window procedure for IPC message handling
+unsafe extern "system" fn ipc_window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    let (msg_show, msg_hide, msg_quit) = cli::get_message_ids();
+
+    let ipc_message = if msg == msg_show {
+        Some(IPCMessage::Show)
+    } else if msg == msg_hide {
+        Some(IPCMessage::Hide)
+    } else if msg == msg_quit {
+        PostQuitMessage(0);
+        Some(IPCMessage::Quit)
+    } else if msg == WM_COPYDATA {
+        let data = &*(lparam.0 as *const COPYDATASTRUCT);
+        if data.dwData == cli::get_copydata_status_id() {
+            // The sender passes the window expecting the reply in wParam
+            status::reply(HWND(wparam.0 as *mut _), data.dwData);
+            return LRESULT(1);
+        }
+
+        let text = if data.lpData.is_null() {
+            String::new()
+        } else {
+            let bytes = std::slice::from_raw_parts(data.lpData as *const u8, data.cbData as usize);
+            String::from_utf8_lossy(bytes).into_owned()
+        };
+
+        if data.dwData == cli::get_copydata_profile_id() && !text.is_empty() {
+            Some(IPCMessage::Profile(text))
+        } else if data.dwData == cli::get_copydata_snooze_id() {
+            text.trim().parse().ok().map(IPCMessage::Snooze)
+        } else if data.dwData == cli::get_copydata_element_id() {
+            // Element requests look like "hide:desktop-icons"
+            let (action, name) = text.split_once(':').unwrap_or_default();
+            ShellElement::parse(name).map(|element| IPCMessage::Element(element, action == "hide"))
+        } else if data.dwData == cli::get_copydata_launch_id() {
+            // Launch arguments are separated by NUL characters
+            let args = text
+                .split('\0')
+                .filter(|arg| !arg.is_empty())
+                .map(str::to_string)
+                .collect();
+            Some(IPCMessage::Launch(args))
+        } else {
+            None
+        }
+    } else if msg == WM_HOTKEY {
+        hotkeys::action_for(wparam.0 as i32).map(IPCMessage::Hotkey)
+    } else if msg == hotkeys::WM_THIDE_HOTKEYS {
+        hotkeys::register_pending(hwnd);
+        return LRESULT(0);
+    } else if msg == notify::WM_THIDE_NOTIFY {
+        notify::on_callback(lparam);
+        return LRESULT(0);
+    } else {
+        None
+    };
+
+    if let Some(ipc_msg) = ipc_message {
+        let send = GLOBAL_EVENT_SENDER
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        if let Some(send) = send {
+            send(ipc_msg);
+        }
+        // WM_COPYDATA senders expect TRUE once the data was accepted
+        return LRESULT((msg == WM_COPYDATA) as isize);
+    }
+
+    DefWindowProcW(hwnd, msg, wparam, lparam)
+}
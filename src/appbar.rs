@@ -0,0 +1,250 @@
+use crate::geometry::{appbar_position, appbar_proposal, Edge, Rect, ReservedStrip};
+use crate::workarea::{monitors, MonitorGeometry};
+use std::cell::RefCell;
+use std::mem;
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
+use windows::Win32::System::Threading::GetCurrentThreadId;
+use windows::Win32::UI::Shell::{
+    SHAppBarMessage, ABE_BOTTOM, ABE_LEFT, ABE_RIGHT, ABE_TOP, ABM_NEW, ABM_QUERYPOS, ABM_REMOVE,
+    ABM_SETPOS, ABN_POSCHANGED, APPBARDATA,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DestroyWindow, DispatchMessageW, GetMessageW,
+    PostThreadMessageW, RegisterClassW, SetWindowPos, TranslateMessage, MSG, SWP_NOACTIVATE,
+    SWP_NOZORDER, WM_APP, WM_DISPLAYCHANGE, WM_DPICHANGED, WM_QUIT, WNDCLASSW, WS_EX_NOACTIVATE,
+    WS_EX_TOOLWINDOW, WS_POPUP,
+};
+
+const APPBAR_WINDOW_CLASS: &str = "THideReservedAppBar";
+/// Notification message the shell sends to the AppBar windows
+const WM_APPBAR_CALLBACK: u32 = WM_APP + 20;
+
+thread_local! {
+    // Strip and registered windows, only touched on the AppBar thread
+    static STRIP: RefCell<ReservedStrip> = RefCell::new(ReservedStrip::default());
+    static WINDOWS: RefCell<Vec<AppBarWindow>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A registered AppBar window and the rect it was last given
+struct AppBarWindow {
+    hwnd: HWND,
+    monitor: String,
+    rect: Option<Rect>,
+}
+
+fn abe(edge: Edge) -> u32 {
+    match edge {
+        Edge::Top => ABE_TOP,
+        Edge::Bottom => ABE_BOTTOM,
+        Edge::Left => ABE_LEFT,
+        Edge::Right => ABE_RIGHT,
+    }
+}
+
+fn appbar_data(hwnd: HWND) -> APPBARDATA {
+    let mut data: APPBARDATA = unsafe { mem::zeroed() };
+    data.cbSize = mem::size_of::<APPBARDATA>() as u32;
+    data.hWnd = hwnd;
+    data
+}
+
+/// Reserves a strip for a custom bar by registering an AppBar on each configured monitor
+pub struct ReservedAppBar {
+    thread_id: u32,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ReservedAppBar {
+    /// Register the AppBars, they are removed again on drop
+    pub fn register(strip: ReservedStrip) -> Self {
+        let (ready, started) = mpsc::channel();
+        let thread = std::thread::spawn(move || run(strip, ready));
+
+        Self {
+            thread_id: started.recv().unwrap_or(0),
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for ReservedAppBar {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = PostThreadMessageW(self.thread_id, WM_QUIT, WPARAM(0), LPARAM(0));
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Create and register the AppBar windows, then pump messages until WM_QUIT
+fn run(strip: ReservedStrip, ready: mpsc::Sender<u32>) {
+    unsafe {
+        let class_name: Vec<u16> = format!("{}\0", APPBAR_WINDOW_CLASS)
+            .encode_utf16()
+            .collect();
+
+        let wc = WNDCLASSW {
+            lpfnWndProc: Some(appbar_window_proc),
+            lpszClassName: PCWSTR(class_name.as_ptr()),
+            ..Default::default()
+        };
+        RegisterClassW(&wc);
+        STRIP.set(strip.clone());
+
+        for monitor in monitors() {
+            if !strip.applies_to(&monitor.name, monitor.index) {
+                continue;
+            }
+
+            let hwnd = CreateWindowExW(
+                WS_EX_TOOLWINDOW | WS_EX_NOACTIVATE,
+                PCWSTR(class_name.as_ptr()),
+                PCWSTR::null(),
+                WS_POPUP,
+                0,
+                0,
+                0,
+                0,
+                None,
+                None,
+                None,
+                None,
+            );
+
+            let Ok(hwnd) = hwnd else {
+                log::warn!("Failed to create the AppBar window for {}", monitor.name);
+                continue;
+            };
+
+            let mut data = appbar_data(hwnd);
+            data.uCallbackMessage = WM_APPBAR_CALLBACK;
+            if SHAppBarMessage(ABM_NEW, &mut data) == 0 {
+                log::warn!("Failed to register the AppBar for {}", monitor.name);
+                let _ = DestroyWindow(hwnd);
+                continue;
+            }
+
+            let rect = position(hwnd, &monitor, &strip, None);
+            log::info!(
+                "Reserved {} DIPs at the {:?} edge of {}",
+                strip.thickness,
+                strip.edge,
+                monitor.name
+            );
+            WINDOWS.with_borrow_mut(|windows| {
+                windows.push(AppBarWindow {
+                    hwnd,
+                    monitor: monitor.name,
+                    rect: Some(rect),
+                })
+            });
+        }
+
+        let _ = ready.send(GetCurrentThreadId());
+
+        let mut msg = MSG::default();
+        while GetMessageW(&mut msg, None, 0, 0).as_bool() {
+            let _ = TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+
+        for window in WINDOWS.take() {
+            let mut data = appbar_data(window.hwnd);
+            SHAppBarMessage(ABM_REMOVE, &mut data);
+            let _ = DestroyWindow(window.hwnd);
+        }
+    }
+}
+
+/// Negotiate the AppBar rect with the shell and move the window there
+///
+/// Nothing is set when the rect didn't change, every ABM_SETPOS notifies the other AppBars.
+fn position(
+    hwnd: HWND,
+    monitor: &MonitorGeometry,
+    strip: &ReservedStrip,
+    current: Option<Rect>,
+) -> Rect {
+    unsafe {
+        let mut data = appbar_data(hwnd);
+        data.uEdge = abe(strip.edge);
+        data.rc = appbar_proposal(monitor.rect, monitor.dpi, strip).into();
+
+        // The shell may shrink the proposal to make room for other AppBars
+        SHAppBarMessage(ABM_QUERYPOS, &mut data);
+        let rect = appbar_position(data.rc.into(), monitor.dpi, strip);
+        if current == Some(rect) {
+            return rect;
+        }
+
+        data.rc = rect.into();
+        SHAppBarMessage(ABM_SETPOS, &mut data);
+
+        let rect = Rect::from(data.rc);
+        let _ = SetWindowPos(
+            hwnd,
+            None,
+            rect.left,
+            rect.top,
+            rect.width(),
+            rect.height(),
+            SWP_NOACTIVATE | SWP_NOZORDER,
+        );
+
+        rect
+    }
+}
+
+/// Re-negotiate one AppBar, or all of them after a resolution or DPI change
+fn reposition(only: Option<HWND>) {
+    let monitors = monitors();
+    let strip = STRIP.with_borrow(|strip| strip.clone());
+
+    // Positioning can re-enter the window procedure, so no borrow is held meanwhile
+    let targets: Vec<(HWND, String, Option<Rect>)> = WINDOWS.with_borrow(|windows| {
+        windows
+            .iter()
+            .filter(|window| only.is_none_or(|hwnd| hwnd == window.hwnd))
+            .map(|window| (window.hwnd, window.monitor.clone(), window.rect))
+            .collect()
+    });
+
+    for (hwnd, name, current) in targets {
+        let Some(monitor) = monitors.iter().find(|m| m.name == name) else {
+            continue;
+        };
+
+        let rect = position(hwnd, monitor, &strip, current);
+        WINDOWS.with_borrow_mut(|windows| {
+            if let Some(window) = windows.iter_mut().find(|window| window.hwnd == hwnd) {
+                window.rect = Some(rect);
+            }
+        });
+    }
+}
+
+unsafe extern "system" fn appbar_window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    match msg {
+        WM_APPBAR_CALLBACK if wparam.0 as u32 == ABN_POSCHANGED => {
+            reposition(Some(hwnd));
+            LRESULT(0)
+        }
+        WM_DISPLAYCHANGE | WM_DPICHANGED => {
+            // Monitor geometry changed, the previous rects are meaningless
+            WINDOWS.with_borrow_mut(|windows| windows.iter_mut().for_each(|w| w.rect = None));
+            reposition(None);
+            LRESULT(0)
+        }
+        _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+    }
+}
@@ -0,0 +1,476 @@
+use crate::elements::ShellElement;
+use crate::geometry::Rect;
+use crate::script::Snapshot;
+#[cfg(windows)]
+use crate::targets;
+use crate::workarea::MonitorGeometry;
+#[cfg(windows)]
+use crate::{get_process_name, window_monitor, workarea};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+#[cfg(windows)]
+use std::mem;
+use std::sync::{Mutex, OnceLock, PoisonError};
+#[cfg(windows)]
+use windows::Win32::Foundation::{COLORREF, HWND, LPARAM, POINT, RECT};
+#[cfg(windows)]
+use windows::Win32::UI::Shell::{SHAppBarMessage, ABM_GETSTATE, ABM_SETSTATE, APPBARDATA};
+#[cfg(windows)]
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetCursorPos, GetForegroundWindow, GetLayeredWindowAttributes, GetWindowLongW, GetWindowRect,
+    IsWindow, IsWindowVisible, SetLayeredWindowAttributes, SetWindowLongW, SetWindowPos,
+    ShowWindow, SystemParametersInfoW, GWL_EXSTYLE, LAYERED_WINDOW_ATTRIBUTES_FLAGS, LWA_ALPHA,
+    SPIF_SENDCHANGE, SPI_SETWORKAREA, SWP_NOACTIVATE, SWP_NOZORDER, SW_HIDE, SW_SHOW,
+    WS_EX_LAYERED,
+};
+
+/// A top-level window, the HWND value on a live desktop
+pub type WindowId = isize;
+
+/// ABS_AUTOHIDE bit of the AppBar state
+pub const ABS_AUTOHIDE: u32 = 0x0000_0001;
+/// ABS_ALWAYSONTOP bit of the AppBar state
+pub const ABS_ALWAYSONTOP: u32 = 0x0000_0002;
+
+/// Whether changes reach the real desktop
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Live,
+    /// Real desktop, changes are only logged
+    DryRun,
+    /// Desktop loaded from a scenario file, changes are only logged
+    Simulated,
+}
+
+impl Mode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Mode::Live => "live",
+            Mode::DryRun => "dry-run",
+            Mode::Simulated => "simulated",
+        }
+    }
+}
+
+/// A change to the desktop
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Action {
+    ShowWindow {
+        window: WindowId,
+        visible: bool,
+    },
+    /// ABM_SETSTATE for the taskbar, e.g. ABS_AUTOHIDE
+    SetAppBarState {
+        state: u32,
+    },
+    /// SPI_SETWORKAREA for the monitor containing the area
+    SetWorkArea {
+        area: Rect,
+    },
+    /// SetWindowPos without changing the z-order or activation
+    MoveWindow {
+        window: WindowId,
+        rect: Rect,
+    },
+    /// Make the window layered with this alpha, None removes WS_EX_LAYERED
+    SetWindowAlpha {
+        window: WindowId,
+        alpha: Option<u8>,
+    },
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::ShowWindow { window, visible } => write!(
+                f,
+                "{} window {:#x}",
+                if *visible { "show" } else { "hide" },
+                window
+            ),
+            Action::SetAppBarState { state } => write!(f, "set the AppBar state to {:#x}", state),
+            Action::SetWorkArea { area } => write!(
+                f,
+                "set the work area to ({}, {}) - ({}, {})",
+                area.left, area.top, area.right, area.bottom
+            ),
+            Action::MoveWindow { window, rect } => write!(
+                f,
+                "move window {:#x} to ({}, {}) - ({}, {})",
+                window, rect.left, rect.top, rect.right, rect.bottom
+            ),
+            Action::SetWindowAlpha { window, alpha } => match alpha {
+                Some(alpha) => write!(f, "set the alpha of window {:#x} to {}", window, alpha),
+                None => write!(f, "make window {:#x} opaque again", window),
+            },
+        }
+    }
+}
+
+/// Everything thide reads from the desktop
+pub trait Desktop: Send + Sync {
+    /// Taskbar windows, ordered by the rule that matched them
+    fn taskbars(&self) -> Vec<WindowId>;
+
+    /// Windows that make up a shell element
+    fn element_windows(&self, element: ShellElement) -> Vec<WindowId>;
+
+    fn monitors(&self) -> Vec<MonitorGeometry>;
+
+    /// Device name of the monitor a window is on
+    fn window_monitor(&self, window: WindowId) -> Option<String>;
+
+    /// Whether a window is visible, None if it no longer exists
+    fn is_visible(&self, window: WindowId) -> Option<bool>;
+
+    /// Screen rect of a window, None if it no longer exists
+    fn window_rect(&self, window: WindowId) -> Option<Rect>;
+
+    /// Alpha of a layered window, None if the window isn't layered
+    fn window_alpha(&self, window: WindowId) -> Option<u8>;
+
+    /// Mouse cursor position in screen coordinates
+    fn cursor_position(&self) -> Option<(i32, i32)>;
+
+    /// Process owning the foreground window
+    fn foreground_process(&self) -> Option<String>;
+
+    /// Current ABM_GETSTATE of the taskbar
+    fn appbar_state(&self) -> u32;
+
+    /// Desktop state passed to the user script
+    fn snapshot(&self) -> Snapshot;
+}
+
+/// A desktop thide can also change
+pub trait Backend: Desktop {
+    fn mode(&self) -> Mode;
+
+    fn perform(&self, action: Action);
+}
+
+static BACKEND: OnceLock<Box<dyn Backend>> = OnceLock::new();
+
+/// Select the backend, must be called before the first `current()`
+pub fn init(backend: Box<dyn Backend>) {
+    log::info!("Using the {} backend", backend.mode().as_str());
+    let _ = BACKEND.set(backend);
+}
+
+/// The selected backend, the live desktop unless `init` chose another
+#[cfg(windows)]
+pub fn current() -> &'static dyn Backend {
+    BACKEND.get_or_init(|| Box::new(Win32Backend)).as_ref()
+}
+
+/// The selected backend, there is no live desktop without Win32
+#[cfg(not(windows))]
+pub fn current() -> &'static dyn Backend {
+    BACKEND
+        .get()
+        .expect("no backend selected, the live desktop needs Windows")
+        .as_ref()
+}
+
+#[cfg(windows)]
+fn hwnd(window: WindowId) -> HWND {
+    HWND(window as *mut _)
+}
+
+#[cfg(windows)]
+fn appbar_data() -> APPBARDATA {
+    let mut data: APPBARDATA = unsafe { mem::zeroed() };
+    data.cbSize = mem::size_of::<APPBARDATA>() as u32;
+    data
+}
+
+/// The real desktop
+#[cfg(windows)]
+pub struct Win32Backend;
+
+#[cfg(windows)]
+impl Desktop for Win32Backend {
+    fn taskbars(&self) -> Vec<WindowId> {
+        targets::find_taskbars()
+            .into_iter()
+            .map(|hwnd| hwnd.0 as WindowId)
+            .collect()
+    }
+
+    fn element_windows(&self, element: ShellElement) -> Vec<WindowId> {
+        element
+            .discover()
+            .into_iter()
+            .map(|hwnd| hwnd.0 as WindowId)
+            .collect()
+    }
+
+    fn monitors(&self) -> Vec<MonitorGeometry> {
+        workarea::monitors()
+    }
+
+    fn window_monitor(&self, window: WindowId) -> Option<String> {
+        window_monitor(hwnd(window))
+    }
+
+    fn is_visible(&self, window: WindowId) -> Option<bool> {
+        unsafe {
+            IsWindow(hwnd(window))
+                .as_bool()
+                .then(|| IsWindowVisible(hwnd(window)).as_bool())
+        }
+    }
+
+    fn window_rect(&self, window: WindowId) -> Option<Rect> {
+        let mut rect = RECT::default();
+        unsafe { GetWindowRect(hwnd(window), &mut rect) }.ok()?;
+        Some(rect.into())
+    }
+
+    fn window_alpha(&self, window: WindowId) -> Option<u8> {
+        unsafe {
+            let ex_style = GetWindowLongW(hwnd(window), GWL_EXSTYLE) as u32;
+            if ex_style & WS_EX_LAYERED.0 == 0 {
+                return None;
+            }
+
+            let (mut alpha, mut flags) = (0u8, LAYERED_WINDOW_ATTRIBUTES_FLAGS(0));
+            GetLayeredWindowAttributes(hwnd(window), None, Some(&mut alpha), Some(&mut flags))
+                .ok()?;
+            // Layered without LWA_ALPHA means fully opaque
+            Some(if flags.contains(LWA_ALPHA) {
+                alpha
+            } else {
+                255
+            })
+        }
+    }
+
+    fn cursor_position(&self) -> Option<(i32, i32)> {
+        let mut point = POINT::default();
+        unsafe { GetCursorPos(&mut point) }.ok()?;
+        Some((point.x, point.y))
+    }
+
+    fn foreground_process(&self) -> Option<String> {
+        get_process_name(unsafe { GetForegroundWindow() })
+    }
+
+    fn appbar_state(&self) -> u32 {
+        unsafe { SHAppBarMessage(ABM_GETSTATE, &mut appbar_data()) as u32 }
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot::capture()
+    }
+}
+
+#[cfg(windows)]
+impl Backend for Win32Backend {
+    fn mode(&self) -> Mode {
+        Mode::Live
+    }
+
+    fn perform(&self, action: Action) {
+        unsafe {
+            match action {
+                Action::ShowWindow { window, visible } => {
+                    let _ = ShowWindow(hwnd(window), if visible { SW_SHOW } else { SW_HIDE });
+                }
+                Action::SetAppBarState { state } => {
+                    let mut data = appbar_data();
+                    data.lParam = LPARAM(state as isize);
+                    SHAppBarMessage(ABM_SETSTATE, &mut data);
+                }
+                Action::SetWorkArea { area } => {
+                    let mut rect: RECT = area.into();
+                    let result = SystemParametersInfoW(
+                        SPI_SETWORKAREA,
+                        0,
+                        Some(&mut rect as *mut _ as *mut _),
+                        SPIF_SENDCHANGE,
+                    );
+
+                    if let Err(e) = result {
+                        log::warn!("Failed to set work area {:?}: {}", area, e);
+                    }
+                }
+                Action::MoveWindow { window, rect } => {
+                    let _ = SetWindowPos(
+                        hwnd(window),
+                        None,
+                        rect.left,
+                        rect.top,
+                        rect.width(),
+                        rect.height(),
+                        SWP_NOZORDER | SWP_NOACTIVATE,
+                    );
+                }
+                Action::SetWindowAlpha { window, alpha } => {
+                    let ex_style = GetWindowLongW(hwnd(window), GWL_EXSTYLE) as u32;
+                    match alpha {
+                        Some(alpha) => {
+                            SetWindowLongW(
+                                hwnd(window),
+                                GWL_EXSTYLE,
+                                (ex_style | WS_EX_LAYERED.0) as i32,
+                            );
+                            let _ = SetLayeredWindowAttributes(
+                                hwnd(window),
+                                COLORREF(0),
+                                alpha,
+                                LWA_ALPHA,
+                            );
+                        }
+                        None => {
+                            SetWindowLongW(
+                                hwnd(window),
+                                GWL_EXSTYLE,
+                                (ex_style & !WS_EX_LAYERED.0) as i32,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Changes recorded by an overlay
+#[derive(Debug, Default)]
+struct Changes {
+    visible: BTreeMap<WindowId, bool>,
+    rects: BTreeMap<WindowId, Rect>,
+    alphas: BTreeMap<WindowId, Option<u8>>,
+    appbar_state: Option<u32>,
+    /// Work areas keyed by monitor device name
+    work_areas: BTreeMap<String, Rect>,
+}
+
+/// Logs actions instead of performing them, later reads reflect what they would have changed
+///
+/// Over the live desktop this is the dry run, over a scenario it is the simulation.
+pub struct Overlay<B> {
+    inner: B,
+    mode: Mode,
+    changes: Mutex<Changes>,
+}
+
+impl<B: Backend> Overlay<B> {
+    pub fn new(inner: B, mode: Mode) -> Self {
+        Self {
+            inner,
+            mode,
+            changes: Mutex::new(Changes::default()),
+        }
+    }
+
+    fn changes(&self) -> std::sync::MutexGuard<'_, Changes> {
+        self.changes.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<B: Backend> Desktop for Overlay<B> {
+    fn taskbars(&self) -> Vec<WindowId> {
+        self.inner.taskbars()
+    }
+
+    fn element_windows(&self, element: ShellElement) -> Vec<WindowId> {
+        self.inner.element_windows(element)
+    }
+
+    fn monitors(&self) -> Vec<MonitorGeometry> {
+        let changes = self.changes();
+
+        self.inner
+            .monitors()
+            .into_iter()
+            .map(|mut monitor| {
+                if let Some(&area) = changes.work_areas.get(&monitor.name) {
+                    monitor.work = area;
+                }
+                monitor
+            })
+            .collect()
+    }
+
+    fn window_monitor(&self, window: WindowId) -> Option<String> {
+        self.inner.window_monitor(window)
+    }
+
+    fn is_visible(&self, window: WindowId) -> Option<bool> {
+        let visible = self.inner.is_visible(window)?;
+        Some(
+            self.changes()
+                .visible
+                .get(&window)
+                .copied()
+                .unwrap_or(visible),
+        )
+    }
+
+    fn window_rect(&self, window: WindowId) -> Option<Rect> {
+        let rect = self.inner.window_rect(window)?;
+        Some(self.changes().rects.get(&window).copied().unwrap_or(rect))
+    }
+
+    fn window_alpha(&self, window: WindowId) -> Option<u8> {
+        match self.changes().alphas.get(&window) {
+            Some(&alpha) => alpha,
+            None => self.inner.window_alpha(window),
+        }
+    }
+
+    fn cursor_position(&self) -> Option<(i32, i32)> {
+        self.inner.cursor_position()
+    }
+
+    fn foreground_process(&self) -> Option<String> {
+        self.inner.foreground_process()
+    }
+
+    fn appbar_state(&self) -> u32 {
+        let state = self.changes().appbar_state;
+        state.unwrap_or_else(|| self.inner.appbar_state())
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        self.inner.snapshot()
+    }
+}
+
+impl<B: Backend> Backend for Overlay<B> {
+    fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    fn perform(&self, action: Action) {
+        log::info!("[{}] Would {}", self.mode.as_str(), action);
+
+        match action {
+            Action::ShowWindow { window, visible } => {
+                self.changes().visible.insert(window, visible);
+            }
+            Action::SetAppBarState { state } => self.changes().appbar_state = Some(state),
+            Action::MoveWindow { window, rect } => {
+                self.changes().rects.insert(window, rect);
+            }
+            Action::SetWindowAlpha { window, alpha } => {
+                self.changes().alphas.insert(window, alpha);
+            }
+            Action::SetWorkArea { area } => {
+                // SPI_SETWORKAREA applies to the monitor containing the area
+                if let Some(monitor) = self
+                    .inner
+                    .monitors()
+                    .into_iter()
+                    .find(|monitor| monitor.rect.contains(area))
+                {
+                    self.changes().work_areas.insert(monitor.name, area);
+                }
+            }
+        }
+    }
+}
@@ -1,32 +1,162 @@
-use windows::Win32::Foundation::{LPARAM, WPARAM};
-use windows::Win32::UI::WindowsAndMessaging::{FindWindowW, PostMessageW, WM_APP};
+use crate::config;
+use crate::doctor;
+use crate::elements::ShellElement;
+use crate::i18n;
+use crate::migrate;
+use crate::schema;
+use crate::script::{ScriptRunner, Snapshot};
+use crate::status::Status;
+use crate::targets;
+use crate::trace::Trace;
+use crate::validate;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
+use windows::Win32::System::DataExchange::COPYDATASTRUCT;
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DestroyWindow, FindWindowW, PostMessageW, RegisterClassW,
+    SendMessageW, HWND_MESSAGE, WINDOW_EX_STYLE, WINDOW_STYLE, WM_APP, WM_COPYDATA, WNDCLASSW,
+};
 
 // Custom message IDs for IPC
 const WM_THIDE_SHOW: u32 = WM_APP + 1;
 const WM_THIDE_HIDE: u32 = WM_APP + 2;
 const WM_THIDE_QUIT: u32 = WM_APP + 3;
 
-const IPC_WINDOW_CLASS: &str = "THideIPCWindow";
+// WM_COPYDATA payload types
+const COPYDATA_PROFILE: usize = 1;
+const COPYDATA_LAUNCH: usize = 2;
+const COPYDATA_ELEMENT: usize = 3;
+const COPYDATA_SNOOZE: usize = 4;
+const COPYDATA_STATUS: usize = 5;
 
-pub fn handle_cli_command(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+const STATUS_REPLY_WINDOW_CLASS: &str = "THideStatusReply";
+
+/// Flags accepted in front of any command, or alone when launching the GUI
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LaunchOptions {
+    /// Shut down the running instance and take over
+    pub replace: bool,
+    /// Run or address a named, isolated instance
+    pub instance: Option<String>,
+    /// Profile to start with
+    pub profile: Option<String>,
+    /// Log what would change on the desktop instead of changing it
+    pub dry_run: bool,
+    /// Run against a simulated desktop loaded from this scenario file
+    pub simulate: Option<String>,
+    /// Record every desktop read, action and input into this trace file
+    pub record: Option<String>,
+    /// Language of the tray menu and messages
+    pub lang: Option<String>,
+    /// Config overrides, each KEY=VALUE
+    pub set: Vec<String>,
+    /// Remaining arguments, a CLI command when not empty
+    pub command: Vec<String>,
+}
+
+impl LaunchOptions {
+    pub fn parse(args: &[String]) -> Self {
+        let mut options = Self::default();
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--replace" if options.command.is_empty() => options.replace = true,
+                "--instance" if options.command.is_empty() => {
+                    options.instance = args.next().cloned()
+                }
+                "--profile" if options.command.is_empty() => options.profile = args.next().cloned(),
+                "--dry-run" if options.command.is_empty() => options.dry_run = true,
+                "--simulate" if options.command.is_empty() => {
+                    options.simulate = args.next().cloned()
+                }
+                "--record" if options.command.is_empty() => options.record = args.next().cloned(),
+                "--lang" if options.command.is_empty() => options.lang = args.next().cloned(),
+                "--set" if options.command.is_empty() => options.set.extend(args.next().cloned()),
+                _ => options.command.push(arg.clone()),
+            }
+        }
+
+        options
+    }
+
+    /// Arguments passed on to an instance that is already running
+    ///
+    /// Every flag but the instance selection, the running instance applies the profile and
+    /// logs the rest, see [`Self::unapplied`].
+    pub fn forwarded_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if self.dry_run {
+            args.push("--dry-run".to_string());
+        }
+        for (flag, value) in [
+            ("--profile", &self.profile),
+            ("--simulate", &self.simulate),
+            ("--record", &self.record),
+            ("--lang", &self.lang),
+        ] {
+            if let Some(value) = value {
+                args.extend([flag.to_string(), value.clone()]);
+            }
+        }
+        for set in &self.set {
+            args.extend(["--set".to_string(), set.clone()]);
+        }
+        args
+    }
+
+    /// Flags that only take effect when an instance starts, a running one can't apply them
+    pub fn unapplied(&self) -> Vec<&'static str> {
+        [
+            ("--dry-run", self.dry_run),
+            ("--simulate", self.simulate.is_some()),
+            ("--record", self.record.is_some()),
+            ("--lang", self.lang.is_some()),
+            ("--set", !self.set.is_empty()),
+        ]
+        .into_iter()
+        .filter(|(_, given)| *given)
+        .map(|(flag, _)| flag)
+        .collect()
+    }
+}
+
+pub fn handle_cli_command(launch: &LaunchOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let args = &launch.command;
     if args.is_empty() {
         print_usage();
         return Ok(());
     }
 
     match args[0].to_lowercase().as_str() {
-        "start" => start_gui(),
-        "show" => send_command(WM_THIDE_SHOW, "Showing taskbar..."),
-        "hide" => send_command(WM_THIDE_HIDE, "Hiding taskbar..."),
-        "stop" | "quit" => send_command(WM_THIDE_QUIT, "Stopping THide..."),
+        "start" => start_gui(launch),
+        "show" | "hide" if args.get(1).is_some_and(|arg| arg == "--element") => {
+            set_element(&args[0].to_lowercase(), args.get(2), launch.dry_run)
+        }
+        "show" => send_command(WM_THIDE_SHOW, &i18n::text("cli-showing"), launch.dry_run),
+        "hide" => send_command(WM_THIDE_HIDE, &i18n::text("cli-hiding"), launch.dry_run),
+        "stop" | "quit" => send_command(WM_THIDE_QUIT, &i18n::text("cli-stopping"), launch.dry_run),
         "enable-autostart" => enable_autostart(),
         "disable-autostart" => disable_autostart(),
+        "profile" => switch_profile(args.get(1), launch.dry_run),
+        "snooze" => snooze(args.get(1), launch.dry_run),
+        "status" => print_status(),
+        "script" => script_command(&args[1..]),
+        "list-candidates" => list_candidates(),
+        "replay" => replay(args.get(1)),
+        "doctor" => doctor(launch, &args[1..]),
+        "config" => config_command(&args[1..], launch.dry_run),
         "help" | "--help" | "-h" => {
             print_usage();
             Ok(())
         }
         _ => {
-            eprintln!("Unknown command: {}", args[0]);
+            eprintln!(
+                "{}",
+                i18n::text_with("cli-unknown-command", &[("command", &args[0])])
+            );
             print_usage();
             std::process::exit(1);
         }
@@ -34,63 +164,200 @@ pub fn handle_cli_command(args: &[String]) -> Result<(), Box<dyn std::error::Err
 }
 
 pub fn get_ipc_window_class() -> &'static str {
-    IPC_WINDOW_CLASS
+    &crate::instance::current().ipc_window_class
 }
 
 pub const fn get_message_ids() -> (u32, u32, u32) {
     (WM_THIDE_SHOW, WM_THIDE_HIDE, WM_THIDE_QUIT)
 }
 
-/// Send an IPC command to the running THide instance
-fn send_command(message: u32, success_msg: &str) -> Result<(), Box<dyn std::error::Error>> {
+pub const fn get_copydata_profile_id() -> usize {
+    COPYDATA_PROFILE
+}
+
+pub const fn get_copydata_launch_id() -> usize {
+    COPYDATA_LAUNCH
+}
+
+pub const fn get_copydata_element_id() -> usize {
+    COPYDATA_ELEMENT
+}
+
+pub const fn get_copydata_snooze_id() -> usize {
+    COPYDATA_SNOOZE
+}
+
+pub const fn get_copydata_status_id() -> usize {
+    COPYDATA_STATUS
+}
+
+/// Find the IPC window of the running THide instance
+pub fn find_ipc_window() -> Option<HWND> {
     unsafe {
-        let class_name: Vec<u16> = format!("{}\0", IPC_WINDOW_CLASS).encode_utf16().collect();
+        let class_name: Vec<u16> = format!("{}\0", get_ipc_window_class())
+            .encode_utf16()
+            .collect();
 
         match FindWindowW(
             windows::core::PCWSTR(class_name.as_ptr()),
             windows::core::PCWSTR::null(),
         ) {
-            Ok(hwnd) if !hwnd.0.is_null() => {
-                let _ = PostMessageW(hwnd, message, WPARAM(0), LPARAM(0));
-                println!("{}", success_msg);
-                Ok(())
-            }
-            _ => {
-                eprintln!("Error: THide is not running!");
-                std::process::exit(1);
-            }
+            Ok(hwnd) if !hwnd.0.is_null() => Some(hwnd),
+            _ => None,
         }
     }
 }
 
-/// Check if THide is currently running
-fn is_thide_running() -> bool {
+/// Post an IPC message to the running instance, false if it isn't running
+pub fn post_message(message: u32) -> bool {
+    match find_ipc_window() {
+        Some(hwnd) => unsafe { PostMessageW(hwnd, message, WPARAM(0), LPARAM(0)).is_ok() },
+        None => false,
+    }
+}
+
+/// Send a WM_COPYDATA payload to the running instance, false if it wasn't accepted
+pub fn send_copydata(kind: usize, data: &[u8]) -> bool {
+    let Some(hwnd) = find_ipc_window() else {
+        return false;
+    };
+
     unsafe {
-        let class_name: Vec<u16> = format!("{}\0", IPC_WINDOW_CLASS).encode_utf16().collect();
-
-        matches!(
-            FindWindowW(
-                windows::core::PCWSTR(class_name.as_ptr()),
-                windows::core::PCWSTR::null(),
-            ),
-            Ok(hwnd) if !hwnd.0.is_null()
+        let copy_data = COPYDATASTRUCT {
+            dwData: kind,
+            cbData: data.len() as u32,
+            lpData: data.as_ptr() as *mut _,
+        };
+
+        SendMessageW(
+            hwnd,
+            WM_COPYDATA,
+            WPARAM(0),
+            LPARAM(&copy_data as *const _ as isize),
         )
+        .0 != 0
+    }
+}
+
+/// Send an IPC command to the running THide instance
+fn send_command(
+    message: u32,
+    success_msg: &str,
+    dry_run: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if dry_run {
+        println!(
+            "{}",
+            i18n::text_with(
+                "cli-would-post",
+                &[
+                    ("message", message_name(message)),
+                    ("window", get_ipc_window_class())
+                ]
+            )
+        );
+        return Ok(());
+    }
+
+    if post_message(message) {
+        println!("{}", success_msg);
+        Ok(())
+    } else {
+        eprintln!("{}", i18n::text("cli-not-running"));
+        std::process::exit(1);
+    }
+}
+
+/// Send a data payload to the running THide instance via WM_COPYDATA
+fn send_data(
+    kind: usize,
+    data: &[u8],
+    success_msg: &str,
+    dry_run: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if dry_run {
+        println!(
+            "{}",
+            i18n::text_with(
+                "cli-would-send",
+                &[
+                    ("kind", copydata_name(kind)),
+                    ("data", &String::from_utf8_lossy(data)),
+                    ("window", get_ipc_window_class())
+                ]
+            )
+        );
+        return Ok(());
+    }
+
+    if send_copydata(kind, data) {
+        println!("{}", success_msg);
+        Ok(())
+    } else {
+        eprintln!("{}", i18n::text("cli-not-running"));
+        std::process::exit(1);
+    }
+}
+
+/// Name of an IPC message, for dry runs
+fn message_name(message: u32) -> &'static str {
+    match message {
+        WM_THIDE_SHOW => "WM_THIDE_SHOW",
+        WM_THIDE_HIDE => "WM_THIDE_HIDE",
+        WM_THIDE_QUIT => "WM_THIDE_QUIT",
+        _ => "WM_APP",
+    }
+}
+
+/// Name of a WM_COPYDATA payload type, for dry runs
+fn copydata_name(kind: usize) -> &'static str {
+    match kind {
+        COPYDATA_PROFILE => "COPYDATA_PROFILE",
+        COPYDATA_LAUNCH => "COPYDATA_LAUNCH",
+        COPYDATA_ELEMENT => "COPYDATA_ELEMENT",
+        COPYDATA_SNOOZE => "COPYDATA_SNOOZE",
+        COPYDATA_STATUS => "COPYDATA_STATUS",
+        _ => "WM_COPYDATA",
     }
 }
 
-/// Start THide in GUI mode
-fn start_gui() -> Result<(), Box<dyn std::error::Error>> {
+/// Check if THide is currently running
+fn is_thide_running() -> bool {
+    find_ipc_window().is_some()
+}
+
+/// Start THide in GUI mode, passing on the simulation flags
+fn start_gui(launch: &LaunchOptions) -> Result<(), Box<dyn std::error::Error>> {
     use std::process::Command;
 
     if is_thide_running() {
-        println!("THide is already running.");
+        println!("{}", i18n::text("cli-already-running"));
         return Ok(());
     }
 
     let exe_path = std::env::current_exe()?;
-    Command::new(exe_path).spawn()?;
+    let mut command = Command::new(exe_path);
+    if let Some(name) = &crate::instance::current().name {
+        command.args(["--instance", name]);
+    }
+    if launch.dry_run {
+        command.arg("--dry-run");
+    }
+    if let Some(scenario) = &launch.simulate {
+        command.args(["--simulate", scenario]);
+    }
+    if let Some(trace) = &launch.record {
+        command.args(["--record", trace]);
+    }
+    if let Some(lang) = &launch.lang {
+        command.args(["--lang", lang]);
+    }
+    for assignment in &launch.set {
+        command.args(["--set", assignment]);
+    }
+    command.spawn()?;
 
-    println!("Starting THide...");
+    println!("{}", i18n::text("cli-starting"));
     Ok(())
 }
 
@@ -116,16 +383,39 @@ fn enable_autostart() -> Result<(), Box<dyn std::error::Error>> {
         .output()?;
 
     if output.status.success() {
-        println!("✓ Autostart enabled successfully!");
-        println!("  THide will start automatically when you log in.");
+        println!("{}", i18n::text("autostart-enabled"));
+        println!("{}", i18n::text("autostart-enabled-hint"));
         Ok(())
     } else {
         let error = String::from_utf8_lossy(&output.stderr);
-        eprintln!("Failed to enable autostart: {}", error);
+        eprintln!(
+            "{}",
+            i18n::text_with("autostart-enable-failed", &[("error", &error)])
+        );
         std::process::exit(1);
     }
 }
 
+/// Command line of the autostart entry, None when autostart is disabled
+pub fn autostart_command() -> Option<String> {
+    let output = std::process::Command::new("reg")
+        .args([
+            "query",
+            "HKCU\\Software\\Microsoft\\Windows\\CurrentVersion\\Run",
+            "/v",
+            "THide",
+        ])
+        .output()
+        .ok()?;
+
+    // The value line looks like "    THide    REG_SZ    C:\path\thide.exe"
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.split_once("REG_SZ"))
+        .map(|(_, command)| command.trim().to_string())
+        .filter(|command| !command.is_empty())
+}
+
 /// Disable THide autostart on Windows login
 fn disable_autostart() -> Result<(), Box<dyn std::error::Error>> {
     use std::process::Command;
@@ -141,33 +431,740 @@ fn disable_autostart() -> Result<(), Box<dyn std::error::Error>> {
         .output()?;
 
     if output.status.success() {
-        println!("✓ Autostart disabled successfully!");
+        println!("{}", i18n::text("autostart-disabled"));
         Ok(())
     } else {
         let error = String::from_utf8_lossy(&output.stderr);
         if error.contains("unable to find") || error.contains("does not exist") {
-            println!("Autostart was not enabled.");
+            println!("{}", i18n::text("autostart-not-enabled"));
             Ok(())
         } else {
-            eprintln!("Failed to disable autostart: {}", error);
+            eprintln!(
+                "{}",
+                i18n::text_with("autostart-disable-failed", &[("error", &error)])
+            );
             std::process::exit(1)
         }
     }
 }
 
+/// Switch the running instance to a profile, or list the profiles
+fn switch_profile(name: Option<&String>, dry_run: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let config = crate::config::load()?;
+    let profiles = crate::profiles::resolve(&config);
+
+    let Some(name) = name else {
+        println!("{}", i18n::text("profile-list"));
+        for name in profiles.keys() {
+            println!("    {}", name);
+        }
+        return Ok(());
+    };
+
+    if !profiles.contains_key(name) {
+        let available = profiles.keys().cloned().collect::<Vec<_>>().join(", ");
+        eprintln!("{}", i18n::text_with("profile-unknown", &[("name", name)]));
+        eprintln!(
+            "{}",
+            i18n::text_with("profile-available", &[("profiles", &available)])
+        );
+        std::process::exit(1);
+    }
+
+    send_data(
+        COPYDATA_PROFILE,
+        name.as_bytes(),
+        &i18n::text_with("profile-switching", &[("name", name)]),
+        dry_run,
+    )
+}
+
+// Status JSON received from the running instance
+static STATUS_REPLY: Mutex<Option<String>> = Mutex::new(None);
+
+unsafe extern "system" fn status_reply_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    if msg == WM_COPYDATA {
+        let data = &*(lparam.0 as *const COPYDATASTRUCT);
+        if data.dwData == COPYDATA_STATUS && !data.lpData.is_null() {
+            let bytes = std::slice::from_raw_parts(data.lpData as *const u8, data.cbData as usize);
+            STATUS_REPLY
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .replace(String::from_utf8_lossy(bytes).into_owned());
+        }
+        return LRESULT(1);
+    }
+
+    DefWindowProcW(hwnd, msg, wparam, lparam)
+}
+
+/// Ask the running instance for its status, it replies to a temporary window
+pub fn request_status() -> Option<Status> {
+    let target = find_ipc_window()?;
+
+    unsafe {
+        let class_name: Vec<u16> = format!("{}\0", STATUS_REPLY_WINDOW_CLASS)
+            .encode_utf16()
+            .collect();
+        let wc = WNDCLASSW {
+            lpfnWndProc: Some(status_reply_proc),
+            lpszClassName: windows::core::PCWSTR(class_name.as_ptr()),
+            ..Default::default()
+        };
+        RegisterClassW(&wc);
+
+        let reply_window = CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            windows::core::PCWSTR(class_name.as_ptr()),
+            windows::core::PCWSTR::null(),
+            WINDOW_STYLE::default(),
+            0,
+            0,
+            0,
+            0,
+            HWND_MESSAGE,
+            None,
+            None,
+            None,
+        )
+        .ok()?;
+
+        // The reply is sent back while this call blocks
+        let copy_data = COPYDATASTRUCT {
+            dwData: COPYDATA_STATUS,
+            cbData: 0,
+            lpData: std::ptr::null_mut(),
+        };
+        SendMessageW(
+            target,
+            WM_COPYDATA,
+            WPARAM(reply_window.0 as usize),
+            LPARAM(&copy_data as *const _ as isize),
+        );
+        let _ = DestroyWindow(reply_window);
+    }
+
+    let reply = STATUS_REPLY
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .take()?;
+    serde_json::from_str(&reply).ok()
+}
+
+/// Print the state of the running instance
+fn print_status() -> Result<(), Box<dyn std::error::Error>> {
+    let Some(status) = request_status() else {
+        eprintln!("{}", i18n::text("status-not-running"));
+        std::process::exit(1);
+    };
+
+    println!(
+        "{}",
+        i18n::text_with("status-running", &[("pid", &status.pid.to_string())])
+    );
+    if !status.mode.is_empty() && status.mode != "live" {
+        println!(
+            "{}",
+            i18n::text_with("status-mode", &[("mode", &status.mode)])
+        );
+    }
+    println!(
+        "{}",
+        i18n::text_with("status-profile", &[("profile", &status.profile)])
+    );
+    println!(
+        "{}",
+        i18n::text_with("status-taskbar", &[("state", &state_text(status.hidden))])
+    );
+    for (monitor, hidden) in &status.monitors {
+        println!(
+            "{}",
+            i18n::text_with(
+                "status-monitor",
+                &[("monitor", monitor), ("state", &state_text(*hidden))]
+            )
+        );
+    }
+    if let Some(secs) = status.snooze_remaining_secs {
+        println!(
+            "{}",
+            i18n::text_with(
+                "status-snoozed",
+                &[
+                    ("minutes", &(secs / 60).to_string()),
+                    ("seconds", &(secs % 60).to_string())
+                ]
+            )
+        );
+    }
+    for report in &status.flapping {
+        println!(
+            "{}",
+            i18n::text_with("status-warning", &[("message", &report.message())])
+        );
+    }
+
+    Ok(())
+}
+
+/// "hidden" or "visible" in the selected language
+fn state_text(hidden: bool) -> String {
+    i18n::text(if hidden {
+        "state-hidden"
+    } else {
+        "state-visible"
+    })
+}
+
+/// Show the taskbar for a number of minutes, then hide it again
+fn snooze(minutes: Option<&String>, dry_run: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(minutes) = minutes
+        .and_then(|m| m.parse::<u64>().ok())
+        .filter(|&m| m > 0)
+    else {
+        eprintln!("{}", i18n::text("cli-snooze-usage"));
+        std::process::exit(1);
+    };
+
+    send_data(
+        COPYDATA_SNOOZE,
+        minutes.to_string().as_bytes(),
+        &i18n::text_with("cli-snoozing", &[("minutes", &minutes.to_string())]),
+        dry_run,
+    )
+}
+
+/// Ask the running instance to show or hide a shell element
+fn set_element(
+    action: &str,
+    name: Option<&String>,
+    dry_run: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let names: Vec<&str> = ShellElement::ALL.iter().map(|e| e.as_str()).collect();
+
+    let Some(element) = name.and_then(|name| ShellElement::parse(name)) else {
+        eprintln!(
+            "{}",
+            i18n::text_with(
+                "cli-element-usage",
+                &[("action", action), ("elements", &names.join("|"))]
+            )
+        );
+        std::process::exit(1);
+    };
+
+    let key = if action == "hide" {
+        "cli-hiding-element"
+    } else {
+        "cli-showing-element"
+    };
+    send_data(
+        COPYDATA_ELEMENT,
+        format!("{}:{}", action, element.as_str()).as_bytes(),
+        &i18n::text_with(key, &[("element", element.as_str())]),
+        dry_run,
+    )
+}
+
+/// Record a desktop snapshot or test a script against one
+fn script_command(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    match args.first().map(|arg| arg.to_lowercase()).as_deref() {
+        Some("record") => {
+            let Some(path) = args.get(1) else {
+                eprintln!("{}", i18n::text("script-record-usage"));
+                std::process::exit(1);
+            };
+
+            Snapshot::capture().save(path)?;
+            println!(
+                "{}",
+                i18n::text_with("script-snapshot-saved", &[("path", path)])
+            );
+            Ok(())
+        }
+        Some("test") => {
+            let Some(script_path) = args.get(1) else {
+                eprintln!("{}", i18n::text("script-test-usage"));
+                std::process::exit(1);
+            };
+
+            let snapshot = match args.get(2) {
+                Some(path) => Snapshot::load(path)?,
+                None => Snapshot::capture(),
+            };
+            let timeout_ms = crate::config::load()
+                .map(|config| config.script.timeout_ms)
+                .unwrap_or_else(|_| crate::config::ScriptConfig::default().timeout_ms);
+
+            let decision = ScriptRunner::load(script_path, Duration::from_millis(timeout_ms))
+                .and_then(|runner| runner.evaluate(&snapshot));
+
+            match decision {
+                Ok(decision) if decision.is_empty() => {
+                    println!("{}", i18n::text("script-no-decision"));
+                    Ok(())
+                }
+                Ok(decision) => {
+                    for monitor in &snapshot.monitors {
+                        let state = match decision.get(&monitor.name) {
+                            Some(true) => i18n::text("script-hide"),
+                            Some(false) => i18n::text("script-show"),
+                            None => "-".to_string(),
+                        };
+                        println!("  {}  {:<16} {}", monitor.index, monitor.name, state);
+                    }
+                    Ok(())
+                }
+                Err(e) => {
+                    eprintln!(
+                        "{}",
+                        i18n::text_with("script-error", &[("error", &e.to_string())])
+                    );
+                    std::process::exit(1);
+                }
+            }
+        }
+        _ => {
+            eprintln!("{}", i18n::text("script-usage"));
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Run the diagnostics, exits with code 1 if any of them failed
+fn doctor(launch: &LaunchOptions, args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let json = args.iter().any(|arg| arg == "--json");
+    let context = doctor::Context::gather(launch)?;
+
+    if doctor::report(&context, launch.simulate.is_some(), json)? == doctor::Severity::Fail {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Manage the config file, `dry_run` is the global --dry-run flag
+fn config_command(args: &[String], dry_run: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let path = || {
+        config::config_path().unwrap_or_else(|| {
+            eprintln!("{}", i18n::text("config-appdata-unset"));
+            std::process::exit(1);
+        })
+    };
+
+    match args.first().map(|arg| arg.to_lowercase()).as_deref() {
+        Some("init") => {
+            let path = path();
+            if path.exists() && !args.iter().any(|arg| arg == "--force") {
+                eprintln!(
+                    "{}",
+                    i18n::text_with("config-exists", &[("path", &path.display().to_string())])
+                );
+                std::process::exit(1);
+            }
+
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(&path, config::DEFAULT_FILE)?;
+            println!(
+                "{}",
+                i18n::text_with("config-written", &[("path", &path.display().to_string())])
+            );
+            Ok(())
+        }
+        Some("check") => check_config(&args.get(1).map(PathBuf::from).unwrap_or_else(path)),
+        Some("show") if args.iter().any(|arg| arg == "--effective") => show_effective(),
+        Some("show") => {
+            let path = path();
+            match std::fs::read_to_string(&path) {
+                Ok(contents) => print!("{}", contents),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    println!(
+                        "{}",
+                        i18n::text_with("config-missing", &[("path", &path.display().to_string())])
+                    )
+                }
+                Err(e) => return Err(e.into()),
+            }
+            Ok(())
+        }
+        Some("edit") => {
+            let path = path();
+            if !path.exists() {
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                std::fs::write(&path, config::DEFAULT_FILE)?;
+            }
+
+            let editor = std::env::var("EDITOR").unwrap_or_else(|_| "notepad.exe".to_string());
+            let mut words = editor.split_whitespace();
+            let program = words.next().unwrap_or("notepad.exe");
+            std::process::Command::new(program)
+                .args(words)
+                .arg(&path)
+                .status()?;
+            check_config(&path)
+        }
+        Some("migrate") => {
+            let dry_run = dry_run || args.iter().any(|arg| arg == "--dry-run");
+            migrate_config(&path(), dry_run)
+        }
+        Some("schema") => {
+            let json = schema::config_json()?;
+            match args.get(1) {
+                Some(file) => {
+                    std::fs::write(file, json)?;
+                    println!(
+                        "{}",
+                        i18n::text_with("config-schema-written", &[("path", file)])
+                    );
+                }
+                None => print!("{}", json),
+            }
+            Ok(())
+        }
+        Some("path") if args.iter().any(|arg| arg == "--system") => {
+            match config::system_config_path() {
+                Some(path) => println!("{}", path.display()),
+                None => {
+                    eprintln!("{}", i18n::text("config-programdata-unset"));
+                    std::process::exit(1);
+                }
+            }
+            Ok(())
+        }
+        Some("path") => {
+            println!("{}", path().display());
+            Ok(())
+        }
+        _ => {
+            eprintln!("{}", i18n::text("config-usage"));
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Validate a config file, exits with code 1 if it can't be loaded
+fn check_config(path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let shown = path.display().to_string();
+    let config = match config::read(path) {
+        Ok(Some(table)) => toml::Value::Table(table).try_into::<config::Config>()?,
+        Ok(None) => {
+            eprintln!(
+                "{}",
+                i18n::text_with("config-not-found", &[("path", &shown)])
+            );
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let migration = migrate::migrate(&std::fs::read_to_string(path)?)?;
+    if migration.is_upgrade() {
+        println!(
+            "{}",
+            i18n::text_with(
+                "config-outdated",
+                &[("version", &migration.from.to_string())]
+            )
+        );
+        for change in &migration.changes {
+            println!(
+                "{}",
+                i18n::text_with("config-warning", &[("message", &change.to_string())])
+            );
+        }
+    }
+
+    let problems = validate::problems(&config);
+    for problem in &problems {
+        println!(
+            "{}",
+            i18n::text_with("config-warning", &[("message", &problem.to_string())])
+        );
+    }
+    if problems.is_empty() {
+        println!("{}", i18n::text_with("config-valid", &[("path", &shown)]));
+    } else {
+        println!(
+            "{}",
+            i18n::text_with(
+                "config-valid-warnings",
+                &[("path", &shown), ("count", &problems.len().to_string())]
+            )
+        );
+    }
+    Ok(())
+}
+
+/// Upgrade the config file to the current version, keeping the original as a backup
+fn migrate_config(path: &Path, dry_run: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let shown = path.display().to_string();
+    let current = migrate::CURRENT_VERSION.to_string();
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            println!(
+                "{}",
+                i18n::text_with("config-migrate-missing", &[("path", &shown)])
+            );
+            return Ok(());
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    let migration = match migrate::migrate(&contents) {
+        Ok(migration) => migration,
+        Err(e) => {
+            eprintln!("{}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+    };
+    if !migration.is_upgrade() {
+        println!(
+            "{}",
+            i18n::text_with(
+                "config-migrate-current",
+                &[("path", &shown), ("version", &current)]
+            )
+        );
+        return Ok(());
+    }
+
+    for change in &migration.changes {
+        println!("{}", change);
+    }
+    if dry_run {
+        println!(
+            "{}",
+            i18n::text_with(
+                "config-migrate-would",
+                &[
+                    ("path", &shown),
+                    ("from", &migration.from.to_string()),
+                    ("to", &current)
+                ]
+            )
+        );
+        return Ok(());
+    }
+
+    let backup = path.with_extension(format!("toml.v{}.bak", migration.from));
+    std::fs::copy(path, &backup)?;
+    std::fs::write(path, migration.document.to_string())?;
+    println!(
+        "{}",
+        i18n::text_with(
+            "config-migrated",
+            &[
+                ("path", &shown),
+                ("from", &migration.from.to_string()),
+                ("to", &current),
+                ("backup", &backup.display().to_string())
+            ]
+        )
+    );
+    Ok(())
+}
+
+/// Print every value in effect and the layer it comes from
+fn show_effective() -> Result<(), Box<dyn std::error::Error>> {
+    let layered = match config::layered() {
+        Ok(layered) => layered,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let lines: Vec<_> = layered
+        .entries()
+        .into_iter()
+        .map(|(path, value, source)| (format!("{} = {}", path, value), source))
+        .collect();
+    let width = lines
+        .iter()
+        .map(|(line, _)| line.chars().count())
+        .max()
+        .unwrap_or(0)
+        .min(60);
+    for (line, source) in lines {
+        println!("{:<width$}  # {}", line, source, width = width);
+    }
+    Ok(())
+}
+
+/// Replay a trace against the current controller and print where its actions differ
+fn replay(path: Option<&String>) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(path) = path else {
+        eprintln!("{}", i18n::text("replay-usage"));
+        std::process::exit(1);
+    };
+
+    let trace = Trace::load(path)?;
+    let divergences = trace.replay();
+    let inputs = trace.steps.len().to_string();
+    println!(
+        "{}",
+        i18n::text_with(
+            "replay-summary",
+            &[
+                ("inputs", &inputs),
+                ("mode", &trace.header.mode.to_string())
+            ]
+        )
+    );
+
+    if divergences.is_empty() {
+        println!("{}", i18n::text("replay-match"));
+        return Ok(());
+    }
+
+    for divergence in &divergences {
+        println!(
+            "{}",
+            i18n::text_with(
+                "replay-divergence",
+                &[
+                    ("step", &(divergence.step + 1).to_string()),
+                    ("ms", &divergence.at_ms.to_string()),
+                    ("input", &divergence.input.label())
+                ]
+            )
+        );
+
+        let missing: Vec<_> = divergence
+            .expected
+            .iter()
+            .filter(|action| !divergence.actual.contains(action))
+            .collect();
+        let extra: Vec<_> = divergence
+            .actual
+            .iter()
+            .filter(|action| !divergence.expected.contains(action))
+            .collect();
+
+        for action in &missing {
+            println!("  - {}", action);
+        }
+        for action in &extra {
+            println!("  + {}", action);
+        }
+        if missing.is_empty() && extra.is_empty() {
+            println!("{}", i18n::text("replay-reordered"));
+        }
+    }
+
+    eprintln!(
+        "{}",
+        i18n::text_with(
+            "replay-diverged",
+            &[
+                ("diverged", &divergences.len().to_string()),
+                ("inputs", &inputs)
+            ]
+        )
+    );
+    std::process::exit(1);
+}
+
+/// Print every window the taskbar rules match or nearly match
+fn list_candidates() -> Result<(), Box<dyn std::error::Error>> {
+    let config = crate::config::load()?;
+    let matchers = targets::effective(config.taskbars);
+    let candidates = targets::list_candidates(&matchers);
+
+    if candidates.is_empty() {
+        println!("{}", i18n::text("cli-list-candidates-none"));
+        return Ok(());
+    }
+
+    for candidate in candidates {
+        println!(
+            "{:<8} {:#010x}  {:<24} {:<20} {:<8} \"{}\"  {}",
+            candidate.verdict.status(),
+            candidate.hwnd.0 as usize,
+            candidate.window.class,
+            candidate.window.process,
+            state_text(!candidate.visible),
+            candidate.window.title,
+            candidate.verdict.reason()
+        );
+    }
+
+    Ok(())
+}
+
 /// Display CLI usage information
 fn print_usage() {
-    println!("THide - Taskbar Hide Utility");
-    println!();
-    println!("USAGE:");
-    println!("    thide [COMMAND]");
-    println!();
-    println!("COMMANDS:");
-    println!("    start              Start THide in GUI mode");
-    println!("    show               Show the taskbar (if THide is running)");
-    println!("    hide               Hide the taskbar (if THide is running)");
-    println!("    stop               Stop THide and restore taskbar");
-    println!("    enable-autostart   Enable autostart on login");
-    println!("    disable-autostart  Disable autostart on login");
-    println!("    help               Show this help message");
+    println!("{}", i18n::text("usage"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layers::{self, Layered};
+    use crate::strategy::HideStrategy;
+
+    fn parse(args: &[&str]) -> LaunchOptions {
+        LaunchOptions::parse(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn forwarded_args_round_trip() {
+        let launch = parse(&[
+            "--instance",
+            "bar",
+            "--dry-run",
+            "--profile",
+            "focus",
+            "--record",
+            "trace.jsonl",
+            "--lang",
+            "de",
+            "--set",
+            "hide.strategy=dim",
+            "--set",
+            "notifications.enabled=false",
+        ]);
+
+        let forwarded = LaunchOptions::parse(&launch.forwarded_args());
+        assert_eq!(
+            forwarded,
+            LaunchOptions {
+                instance: None,
+                ..launch.clone()
+            }
+        );
+        assert_eq!(
+            forwarded.unapplied(),
+            ["--dry-run", "--record", "--lang", "--set"]
+        );
+
+        // The forwarded overrides still apply to the config
+        let mut layered = Layered::defaults();
+        for (layer, source) in layers::flags(None, &forwarded.set).unwrap() {
+            layered.merge(layer, source);
+        }
+        let config = layered.config().unwrap();
+        assert_eq!(config.hide.strategy, HideStrategy::Dim);
+        assert!(!config.notifications.enabled);
+    }
+
+    #[test]
+    fn a_profile_alone_is_applied() {
+        let launch = parse(&["--profile", "focus", "start"]);
+        assert_eq!(launch.forwarded_args(), ["--profile", "focus"]);
+        assert!(launch.unapplied().is_empty());
+        assert!(parse(&[]).forwarded_args().is_empty());
+    }
 }
@@ -0,0 +1,381 @@
+use crate::animation::AnimationKind;
+use crate::easing::Curve;
+use crate::elements::ShellElement;
+use crate::geometry::{monitor_matches, ReservedStrip};
+use crate::idle::RevealTrigger;
+use crate::instance::{ConflictAction, InstanceScope};
+use crate::layers::{self, Layered, Source};
+use crate::migrate;
+use crate::notify::NotificationEvent;
+use crate::profiles::Profile;
+use crate::schema;
+use crate::state::StartupMode;
+use crate::strategy::HideStrategy;
+use crate::targets::Matcher;
+use crate::validate;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+use toml::Table;
+
+const CONFIG_FILE_NAME: &str = "config.toml";
+
+static FLAGS: OnceLock<Result<Vec<(Table, Source)>, String>> = OnceLock::new();
+
+/// Commented config file written by `thide config init`
+pub const DEFAULT_FILE: &str = include_str!("../assets/config.toml");
+
+/// User configuration loaded from %APPDATA%\thide\config.toml
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Version of the config format the file was written for, see `thide config migrate`
+    pub version: Option<u32>,
+    pub startup: StartupConfig,
+    pub instance: InstanceConfig,
+    pub hooks: HooksConfig,
+    pub script: ScriptConfig,
+    pub idle: IdleConfig,
+    /// Rules selecting the taskbar windows, the explorer.exe taskbars when empty
+    pub taskbars: Vec<Matcher>,
+    pub elements: ElementsConfig,
+    pub work_area: WorkAreaConfig,
+    pub hide: HideConfig,
+    pub animation: AnimationConfig,
+    /// Strip reserved with an AppBar for a custom bar that doesn't register one itself
+    pub reserve: Option<ReservedStrip>,
+    pub notifications: NotificationsConfig,
+    /// Language of the tray menu and messages, e.g. "de", the user's language if not set
+    pub language: Option<String>,
+    /// Global hotkeys, e.g. "Ctrl+Alt+T" = "toggle"
+    #[schemars(schema_with = "schema::hotkeys")]
+    pub hotkeys: BTreeMap<String, String>,
+    /// User profiles, merged with the built-in ones
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// Taskbar state on start
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct StartupConfig {
+    pub mode: StartupMode,
+}
+
+/// Single-instance behavior
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct InstanceConfig {
+    pub scope: InstanceScope,
+    pub on_conflict: ConflictAction,
+}
+
+/// Shell elements hidden besides the taskbar
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct ElementsConfig {
+    /// Elements hidden on start, e.g. ["desktop-icons", "widgets"]
+    pub hide: Vec<ShellElement>,
+}
+
+/// Work area of monitors whose taskbar is hidden
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorkAreaConfig {
+    /// Give the taskbar's space to other windows while it is hidden
+    pub manage: bool,
+    /// Strips kept out of the work area, e.g. for a custom status bar
+    pub reserve: Vec<ReservedStrip>,
+}
+
+impl Default for WorkAreaConfig {
+    fn default() -> Self {
+        Self {
+            manage: true,
+            reserve: Vec::new(),
+        }
+    }
+}
+
+/// How hidden taskbars are hidden
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct HideConfig {
+    pub strategy: HideStrategy,
+    /// Per-monitor strategies keyed by monitor name or 1-based index
+    pub monitors: BTreeMap<String, HideStrategy>,
+    pub dim: DimConfig,
+}
+
+/// Taskbars hidden with the dim strategy
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct DimConfig {
+    /// Opacity of a dimmed taskbar, from 0.0 to 1.0
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub opacity: f64,
+    /// Fade to full opacity while the mouse is over the taskbar
+    pub reveal_on_hover: bool,
+    /// Milliseconds a fade to or from full opacity takes
+    pub fade_ms: u64,
+    pub curve: Curve,
+}
+
+impl DimConfig {
+    /// Alpha of a dimmed taskbar
+    pub fn alpha(&self) -> u8 {
+        (self.opacity.clamp(0.0, 1.0) * 255.0).round() as u8
+    }
+}
+
+impl Default for DimConfig {
+    fn default() -> Self {
+        Self {
+            opacity: 0.35,
+            reveal_on_hover: true,
+            fade_ms: 150,
+            curve: Curve::Ease,
+        }
+    }
+}
+
+impl HideConfig {
+    /// Strategy used for the taskbar on a monitor
+    pub fn strategy_for(&self, name: &str, index: usize) -> HideStrategy {
+        self.monitors
+            .iter()
+            .find(|(selector, _)| monitor_matches(selector, name, index))
+            .map(|(_, strategy)| *strategy)
+            .unwrap_or(self.strategy)
+    }
+}
+
+/// Transitions when the taskbar is hidden or revealed
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnimationConfig {
+    pub kind: AnimationKind,
+    /// Milliseconds a full hide or reveal takes
+    pub duration_ms: u64,
+    pub curve: Curve,
+}
+
+impl Default for AnimationConfig {
+    fn default() -> Self {
+        Self {
+            kind: AnimationKind::None,
+            duration_ms: 200,
+            curve: Curve::Ease,
+        }
+    }
+}
+
+/// Tray notifications for state changes and problems
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotificationsConfig {
+    /// Show notifications at all
+    pub enabled: bool,
+    /// Minimum seconds between two notifications of the same kind
+    pub min_interval_secs: u64,
+    /// Notify when the taskbar was hidden
+    pub hidden: bool,
+    /// Notify when the taskbar was shown
+    pub shown: bool,
+    /// Notify when Explorer restarted and the taskbar state was applied again
+    pub explorer_restarted: bool,
+    /// Notify when the config file couldn't be loaded and the defaults are used
+    pub config_failed: bool,
+    /// Notify when another program keeps re-showing the taskbar
+    pub flapping: bool,
+}
+
+impl NotificationsConfig {
+    pub fn min_interval(&self) -> Duration {
+        Duration::from_secs(self.min_interval_secs)
+    }
+
+    /// Whether notifications for the event are turned on
+    pub fn is_enabled(&self, event: NotificationEvent) -> bool {
+        match event {
+            NotificationEvent::Hidden => self.hidden,
+            NotificationEvent::Shown => self.shown,
+            NotificationEvent::ExplorerRestarted => self.explorer_restarted,
+            NotificationEvent::ConfigFailed => self.config_failed,
+            NotificationEvent::Flapping => self.flapping,
+        }
+    }
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        // Problems are reported, routine state changes only on request
+        Self {
+            enabled: true,
+            min_interval_secs: 30,
+            hidden: false,
+            shown: false,
+            explorer_restarted: true,
+            config_failed: true,
+            flapping: true,
+        }
+    }
+}
+
+/// Commands to run when the taskbar state changes
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct HooksConfig {
+    /// Commands run when thide starts
+    pub on_start: Vec<String>,
+    /// Commands run after the taskbar was hidden
+    pub on_hide: Vec<String>,
+    /// Commands run after the taskbar was shown
+    pub on_show: Vec<String>,
+    /// Commands run when thide quits
+    pub on_quit: Vec<String>,
+    /// Commands run after Explorer restarted
+    pub on_explorer_restart: Vec<String>,
+    /// Kill a hook command after this many milliseconds
+    pub timeout_ms: u64,
+    /// Maximum number of hook commands running at the same time
+    pub max_concurrent: usize,
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            on_start: Vec::new(),
+            on_hide: Vec::new(),
+            on_show: Vec::new(),
+            on_quit: Vec::new(),
+            on_explorer_restart: Vec::new(),
+            timeout_ms: 10_000,
+            max_concurrent: 4,
+        }
+    }
+}
+
+/// User script deciding the per-monitor taskbar state
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScriptConfig {
+    /// Path to a Rhai script, scripting is disabled when unset
+    pub path: Option<PathBuf>,
+    /// How often the script is evaluated
+    pub interval_ms: u64,
+    /// Abort a single evaluation after this many milliseconds
+    pub timeout_ms: u64,
+}
+
+impl Default for ScriptConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            interval_ms: 500,
+            timeout_ms: 100,
+        }
+    }
+}
+
+/// Automatic hide after inactivity or long focus
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct IdleConfig {
+    /// Hide after this many seconds without keyboard or mouse input, 0 disables
+    pub hide_after_idle_secs: u64,
+    /// Hide after the same window kept focus this many seconds, 0 disables
+    pub hide_after_focus_secs: u64,
+    /// Events that reveal the taskbar again
+    pub reveal_on: Vec<RevealTrigger>,
+}
+
+impl Default for IdleConfig {
+    fn default() -> Self {
+        Self {
+            hide_after_idle_secs: 0,
+            hide_after_focus_secs: 0,
+            reveal_on: vec![RevealTrigger::Input, RevealTrigger::FocusChange],
+        }
+    }
+}
+
+/// Get the config file path (%APPDATA%\thide\config.toml)
+pub fn config_path() -> Option<PathBuf> {
+    std::env::var_os("APPDATA").map(|dir| PathBuf::from(dir).join("thide").join(CONFIG_FILE_NAME))
+}
+
+/// Get the system-wide config file path (%PROGRAMDATA%\thide\config.toml)
+pub fn system_config_path() -> Option<PathBuf> {
+    std::env::var_os("PROGRAMDATA")
+        .map(|dir| PathBuf::from(dir).join("thide").join(CONFIG_FILE_NAME))
+}
+
+/// Get the directory for logs and runtime state (%LOCALAPPDATA%\thide)
+pub fn data_dir() -> Option<PathBuf> {
+    std::env::var_os("LOCALAPPDATA").map(|dir| PathBuf::from(dir).join("thide"))
+}
+
+/// Load the configuration from all its layers
+pub fn load() -> Result<Config, Box<dyn std::error::Error>> {
+    layered()?.config()
+}
+
+/// Set the command line flags overriding the config, before it is loaded
+pub fn init_flags(flags: Result<Vec<(Table, Source)>, String>) {
+    let _ = FLAGS.set(flags);
+}
+
+/// The layers making up the configuration, missing config files are skipped
+pub fn layered() -> Result<Layered, Box<dyn std::error::Error>> {
+    let mut layered = Layered::defaults();
+
+    for path in [system_config_path(), config_path()].into_iter().flatten() {
+        if let Some(table) = read(&path)? {
+            layered.merge(table, Source::File(path));
+        }
+    }
+
+    let flags = FLAGS.get().cloned().unwrap_or(Ok(Vec::new()))?;
+    for (table, source) in layers::env(std::env::vars()).into_iter().chain(flags) {
+        layered.merge(table, source.clone());
+        // Checked one by one, so an error names the variable or flag
+        layered.config().map_err(|e| format!("{}: {}", source, e))?;
+    }
+
+    Ok(layered)
+}
+
+/// Parse a config file, None if it doesn't exist
+///
+/// Files are read as they are, an older version is only upgraded by `thide config migrate`.
+pub fn read(path: &Path) -> Result<Option<Table>, Box<dyn std::error::Error>> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+
+    let version =
+        migrate::file_version(&contents).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let outdated = (version < migrate::CURRENT_VERSION).then(|| {
+        format!(
+            "written for config version {}, `thide config migrate` upgrades it",
+            version
+        )
+    });
+
+    // Deserializing the file on its own points at the line of an unknown key or bad value
+    if let Err(e) = toml::from_str::<Config>(&contents) {
+        let hint = outdated.map(|o| format!(" ({})", o)).unwrap_or_default();
+        return Err(format!("{}: {}{}", path.display(), validate::describe(&e), hint).into());
+    }
+    if let Some(outdated) = outdated
+        .filter(|_| migrate::migrate(&contents).is_ok_and(|migration| migration.is_upgrade()))
+    {
+        log::warn!("{}: {}", path.display(), outdated);
+    }
+    Ok(Some(toml::from_str(&contents)?))
+}
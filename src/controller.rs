@@ -0,0 +1,1111 @@
+use crate::animation::{Animation, AnimationKind, Direction};
+use crate::backend::{Action, Desktop, WindowId, ABS_AUTOHIDE};
+use crate::config::{AnimationConfig, HideConfig, WorkAreaConfig};
+use crate::easing::Fade;
+use crate::elements::ShellElement;
+use crate::flapping::{FlapDetector, FlapReport};
+use crate::geometry::{reclaimed_work_area, Rect};
+use crate::profiles::Profile;
+use crate::strategy::{Applied, HideStrategy};
+use crate::workarea::MonitorGeometry;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::mem;
+use std::time::{Duration, Instant};
+
+/// Commands accepted by the controller from the tray menu, IPC, hotkeys and background threads
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Command {
+    Show,
+    Hide,
+    Toggle,
+    Quit,
+    /// Switch to a profile, replacing the desired state in one step
+    ApplyProfile(Profile),
+    /// Per-monitor hidden state decided by the user script, keyed by device name
+    SetMonitorOverrides(BTreeMap<String, bool>),
+    /// Hide (true) or show (false) an additional shell element
+    SetElement(ShellElement, bool),
+}
+
+/// Kind of state transition emitted by the controller
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransitionKind {
+    Start,
+    Hide,
+    Show,
+    Quit,
+    ExplorerRestart,
+}
+
+impl TransitionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransitionKind::Start => "start",
+            TransitionKind::Hide => "hide",
+            TransitionKind::Show => "show",
+            TransitionKind::Quit => "quit",
+            TransitionKind::ExplorerRestart => "explorer_restart",
+        }
+    }
+}
+
+/// A taskbar state change, dispatched to the hooks by the caller
+#[derive(Debug, Clone)]
+pub struct Transition {
+    pub kind: TransitionKind,
+    pub hidden: bool,
+    pub was_hidden: bool,
+    /// Device names of the monitors whose taskbar was affected
+    pub monitors: Vec<String>,
+}
+
+/// Desired taskbar visibility
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DesiredState {
+    /// State for monitors without an override
+    pub hidden: bool,
+    /// Strategy of the active profile, the [hide] strategies when None
+    pub strategy: Option<HideStrategy>,
+    /// Per-monitor overrides keyed by device name
+    pub monitors: BTreeMap<String, bool>,
+    /// Shell elements hidden besides the taskbar
+    pub elements: BTreeSet<ShellElement>,
+}
+
+impl DesiredState {
+    /// Initial state of a profile, before any script decision
+    pub fn for_profile(profile: &Profile) -> Self {
+        Self {
+            hidden: profile.hidden,
+            strategy: profile.strategy,
+            ..Self::default()
+        }
+    }
+
+    /// Whether the taskbar on the given monitor should be hidden
+    pub fn is_hidden_on(&self, monitor: &str) -> bool {
+        self.monitors.get(monitor).copied().unwrap_or(self.hidden)
+    }
+
+    /// Whether any of the given monitors should have its taskbar hidden
+    pub fn any_hidden(&self, monitors: &[String]) -> bool {
+        if monitors.is_empty() {
+            return self.hidden;
+        }
+
+        monitors.iter().any(|monitor| self.is_hidden_on(monitor))
+    }
+}
+
+/// Everything the controller reacts to
+#[derive(Debug, Clone)]
+pub enum Input {
+    /// Save the original desktop state and apply the initial state of a profile
+    Start(Profile, DesiredState),
+    Command(Command),
+    /// Periodic look at the desktop, re-hides what reappeared
+    Tick(Instant),
+    /// Time to draw the next frame of running animations
+    Frame(Instant),
+}
+
+/// Changes to perform on the desktop and transitions to dispatch, in order
+#[derive(Debug, Default)]
+pub struct Output {
+    pub actions: Vec<Action>,
+    pub transitions: Vec<Transition>,
+}
+
+/// Owns the desired taskbar state and turns every input into desktop actions
+///
+/// The controller only reads the desktop, performing the actions is up to the caller. Once it
+/// handled Quit, it has written back the original AppBar state, work areas and shell elements
+/// and ignores any further input.
+pub struct TaskbarController {
+    desired: DesiredState,
+    work_area: WorkAreaConfig,
+    hide: HideConfig,
+    animation: AnimationConfig,
+    /// ABM_GETSTATE before thide changed it, None until started
+    original_appbar: Option<u32>,
+    /// Work areas before thide changed them, keyed by device name
+    saved_work_areas: BTreeMap<String, Rect>,
+    /// Windows hidden by thide and the element they belong to, restored on show
+    hidden_elements: Vec<(WindowId, ShellElement)>,
+    /// Taskbar windows changed by a hide strategy and how to undo it
+    applied: BTreeMap<WindowId, Applied>,
+    /// Hover fades of dimmed taskbars
+    fades: BTreeMap<WindowId, Fade>,
+    /// Taskbar windows being hidden or revealed
+    animations: BTreeMap<WindowId, Animation>,
+    /// Last decision of the user script, applied while the profile enables rules
+    script_overrides: BTreeMap<String, bool>,
+    rules: bool,
+    flapping: FlapDetector,
+    /// First taskbar window, a new one means explorer.exe was restarted
+    primary_taskbar: Option<WindowId>,
+    stopped: bool,
+}
+
+impl TaskbarController {
+    pub fn new(work_area: WorkAreaConfig, hide: HideConfig, animation: AnimationConfig) -> Self {
+        Self {
+            desired: DesiredState::default(),
+            work_area,
+            hide,
+            animation,
+            original_appbar: None,
+            saved_work_areas: BTreeMap::new(),
+            hidden_elements: Vec::new(),
+            applied: BTreeMap::new(),
+            fades: BTreeMap::new(),
+            animations: BTreeMap::new(),
+            script_overrides: BTreeMap::new(),
+            rules: true,
+            flapping: FlapDetector::default(),
+            primary_taskbar: None,
+            stopped: false,
+        }
+    }
+
+    /// Current desired state
+    pub fn desired(&self) -> &DesiredState {
+        &self.desired
+    }
+
+    /// Whether Quit was handled
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// Whether animations are running and need frames
+    pub fn is_animating(&self) -> bool {
+        !self.animations.is_empty()
+    }
+
+    /// Taskbars that flapped recently
+    pub fn flap_reports(&self, now: Instant) -> Vec<FlapReport> {
+        self.flapping.reports(now)
+    }
+
+    /// Handle an input, reading the desktop to decide which actions are needed
+    pub fn handle(&mut self, input: Input, desktop: &dyn Desktop) -> Output {
+        let mut output = Output::default();
+
+        if self.stopped {
+            return output;
+        }
+
+        match input {
+            Input::Start(profile, state) => {
+                if self.original_appbar.is_none() {
+                    self.start(&profile, state, desktop, &mut output);
+                }
+            }
+            // Nothing to restore yet, so nothing may be changed either
+            _ if self.original_appbar.is_none() => {
+                log::debug!("Ignoring {:?} before start", input);
+            }
+            Input::Command(command) => self.command(command, desktop, &mut output),
+            Input::Tick(now) => self.tick(now, desktop, &mut output),
+            Input::Frame(now) => self.frame(now, desktop, &mut output),
+        }
+
+        output
+    }
+
+    fn start(
+        &mut self,
+        profile: &Profile,
+        state: DesiredState,
+        desktop: &dyn Desktop,
+        output: &mut Output,
+    ) {
+        self.original_appbar = Some(desktop.appbar_state());
+        self.primary_taskbar = desktop.taskbars().first().copied();
+        self.rules = profile.rules;
+        self.desired = state;
+
+        let monitors = taskbar_monitors(desktop);
+        self.apply(desktop, output);
+        let hidden = self.desired.any_hidden(&monitors);
+        emit(output, TransitionKind::Start, hidden, false, monitors);
+    }
+
+    fn command(&mut self, command: Command, desktop: &dyn Desktop, output: &mut Output) {
+        match command {
+            Command::Show => self.update(|state| state.hidden = false, desktop, output),
+            Command::Hide => self.update(|state| state.hidden = true, desktop, output),
+            Command::Toggle => self.update(|state| state.hidden = !state.hidden, desktop, output),
+            Command::ApplyProfile(profile) => {
+                self.rules = profile.rules;
+                let state = self.profile_state(&profile);
+                self.update(|current| *current = state, desktop, output);
+            }
+            Command::SetMonitorOverrides(overrides) => {
+                self.script_overrides = overrides;
+                if self.rules {
+                    let overrides = self.script_overrides.clone();
+                    self.update(|state| state.monitors = overrides, desktop, output);
+                }
+            }
+            Command::SetElement(element, hidden) => {
+                log::info!(
+                    "{} {}",
+                    if hidden { "Hiding" } else { "Showing" },
+                    element.as_str()
+                );
+                self.update(
+                    |state| {
+                        if hidden {
+                            state.elements.insert(element);
+                        } else {
+                            state.elements.remove(&element);
+                        }
+                    },
+                    desktop,
+                    output,
+                );
+            }
+            Command::Quit => {
+                let monitors = taskbar_monitors(desktop);
+                let was_hidden = self.desired.any_hidden(&monitors);
+                self.stopped = true;
+                self.desired = DesiredState::default();
+                self.apply(desktop, output);
+
+                // Reveals still running are finished at once
+                for (window, animation) in mem::take(&mut self.animations) {
+                    if desktop.is_visible(window).is_some() {
+                        output.actions.extend(animation.restore_actions(window));
+                    }
+                }
+
+                // Monitors that were unplugged meanwhile still get their work area back
+                for (_, previous) in mem::take(&mut self.saved_work_areas) {
+                    output.actions.push(Action::SetWorkArea { area: previous });
+                }
+                emit(output, TransitionKind::Quit, false, was_hidden, monitors);
+            }
+        }
+    }
+
+    /// Re-hide taskbars and shell elements that reappeared
+    ///
+    /// Taskbars that another program keeps re-showing are left alone while backing off.
+    fn tick(&mut self, now: Instant, desktop: &dyn Desktop, output: &mut Output) {
+        let primary = desktop.taskbars().first().copied();
+        if primary.is_some() && primary != self.primary_taskbar {
+            let restarted = self.primary_taskbar.is_some();
+            self.primary_taskbar = primary;
+
+            if restarted {
+                // A new explorer instance starts with its own taskbar state
+                let monitors = taskbar_monitors(desktop);
+                self.apply(desktop, output);
+                let hidden = self.desired.any_hidden(&monitors);
+                emit(
+                    output,
+                    TransitionKind::ExplorerRestart,
+                    hidden,
+                    hidden,
+                    monitors,
+                );
+                return;
+            }
+        }
+
+        let geometries = desktop.monitors();
+        for window in desktop.taskbars() {
+            let monitor = desktop.window_monitor(window).unwrap_or_default();
+            let Some(strategy) = self.strategy_on(&monitor, &geometries) else {
+                continue;
+            };
+
+            let holds = self.applied.get(&window).is_some_and(|applied| {
+                applied.strategy == strategy && applied.holds(window, desktop)
+            });
+            if holds
+                || self.animations.contains_key(&window)
+                || !self.flapping.may_rehide(&monitor, now)
+            {
+                continue;
+            }
+
+            self.hide_taskbar(window, strategy, false, desktop, output);
+
+            // Whatever is in the foreground is the most likely reason it reappeared
+            let culprit = desktop.foreground_process();
+            if let Some(backoff) = self
+                .flapping
+                .record_rehide(&monitor, culprit.as_deref(), now)
+            {
+                log::warn!(
+                    "Taskbar on {} keeps reappearing (foreground: {}), pausing re-hide for {:?}",
+                    monitor,
+                    culprit.as_deref().unwrap_or("unknown"),
+                    backoff
+                );
+            }
+        }
+
+        self.fade_dimmed(now, desktop, output);
+
+        for element in self.desired.elements.clone() {
+            self.hide_element(element, desktop, output);
+        }
+    }
+
+    /// Draw the next frame of every animation, applying the hide strategy once hidden
+    fn frame(&mut self, now: Instant, desktop: &dyn Desktop, output: &mut Output) {
+        for (window, mut animation) in mem::take(&mut self.animations) {
+            // The window may be gone after an explorer restart
+            if desktop.is_visible(window).is_none() {
+                continue;
+            }
+
+            output.actions.extend(animation.advance(window, now));
+            if !animation.is_finished() {
+                self.animations.insert(window, animation);
+                continue;
+            }
+
+            let applied = self.applied.get(&window);
+            let hide = match (animation.direction, applied) {
+                (Direction::Hide, Some(applied)) => applied.hide_actions(window, desktop),
+                _ => Vec::new(),
+            };
+            let owned = hide.iter().any(|action| animation.owns(action));
+            output.actions.extend(hide);
+            if !owned {
+                output.actions.extend(animation.restore_actions(window));
+            }
+        }
+    }
+
+    /// Fade dimmed taskbars to full opacity while hovered and back once the mouse leaves
+    fn fade_dimmed(&mut self, now: Instant, desktop: &dyn Desktop, output: &mut Output) {
+        let dimmed: Vec<WindowId> = self
+            .applied
+            .iter()
+            .filter(|(_, applied)| applied.strategy == HideStrategy::Dim)
+            .map(|(&window, _)| window)
+            .collect();
+        self.fades.retain(|window, _| dimmed.contains(window));
+        if dimmed.is_empty() {
+            return;
+        }
+
+        let dim = &self.hide.dim;
+        let cursor = dim
+            .reveal_on_hover
+            .then(|| desktop.cursor_position())
+            .flatten();
+
+        for window in dimmed {
+            let Some(current) = desktop.window_alpha(window) else {
+                continue;
+            };
+            let hovered = cursor.is_some_and(|(x, y)| {
+                desktop
+                    .window_rect(window)
+                    .is_some_and(|rect| rect.contains_point(x, y))
+            });
+            let target = if hovered { u8::MAX } else { dim.alpha() };
+
+            let fade = self
+                .fades
+                .entry(window)
+                .or_insert_with(|| Fade::new(current, target, now));
+            if fade.to != target {
+                *fade = Fade::new(current, target, now);
+            }
+
+            let alpha = fade.alpha_at(now, Duration::from_millis(dim.fade_ms), dim.curve);
+            if alpha != current {
+                output.actions.push(Action::SetWindowAlpha {
+                    window,
+                    alpha: Some(alpha),
+                });
+            }
+        }
+    }
+
+    /// Change the desired state and emit hide/show for the monitors it affected
+    fn update(
+        &mut self,
+        change: impl FnOnce(&mut DesiredState),
+        desktop: &dyn Desktop,
+        output: &mut Output,
+    ) {
+        let monitors = taskbar_monitors(desktop);
+        let before = self.desired.clone();
+        change(&mut self.desired);
+
+        if self.desired == before {
+            return;
+        }
+
+        self.apply(desktop, output);
+
+        let after = &self.desired;
+        let (hidden, shown): (Vec<String>, Vec<String>) = monitors
+            .into_iter()
+            .filter(|monitor| before.is_hidden_on(monitor) != after.is_hidden_on(monitor))
+            .partition(|monitor| after.is_hidden_on(monitor));
+
+        if !hidden.is_empty() {
+            emit(output, TransitionKind::Hide, true, false, hidden);
+        }
+        if !shown.is_empty() {
+            emit(output, TransitionKind::Show, false, true, shown);
+        }
+    }
+
+    /// Desired state for a profile, including script overrides if it enables rules
+    fn profile_state(&self, profile: &Profile) -> DesiredState {
+        DesiredState {
+            elements: self.desired.elements.clone(),
+            monitors: if profile.rules {
+                self.script_overrides.clone()
+            } else {
+                BTreeMap::new()
+            },
+            ..DesiredState::for_profile(profile)
+        }
+    }
+
+    /// Bring the desktop in line with the desired state
+    fn apply(&mut self, desktop: &dyn Desktop, output: &mut Output) {
+        let original = self.original_appbar.unwrap_or_default();
+        let monitors = taskbar_monitors(desktop);
+        let geometries = desktop.monitors();
+
+        // The AppBar state is global, keep auto-hide while any taskbar is hidden. The original
+        // state is always written back on quit, even if the desktop already reports it.
+        let appbar = if self.auto_hides(&monitors, &geometries) {
+            original | ABS_AUTOHIDE
+        } else {
+            original
+        };
+        if self.stopped || desktop.appbar_state() != appbar {
+            output
+                .actions
+                .push(Action::SetAppBarState { state: appbar });
+        }
+
+        let taskbars = desktop.taskbars();
+
+        // Windows that are no longer taskbars are put back, the ones that are gone forgotten
+        let stale: Vec<WindowId> = self
+            .applied
+            .keys()
+            .filter(|window| !taskbars.contains(window))
+            .copied()
+            .collect();
+        for window in stale {
+            self.restore_taskbar(window, false, desktop, output);
+        }
+
+        for window in taskbars {
+            let monitor = desktop.window_monitor(window).unwrap_or_default();
+            let target = self.strategy_on(&monitor, &geometries);
+            let current = self.applied.get(&window);
+
+            match (target, current) {
+                (Some(strategy), Some(applied)) if applied.strategy == strategy => {
+                    if !self.animations.contains_key(&window) && !applied.holds(window, desktop) {
+                        output.actions.extend(applied.hide_actions(window, desktop));
+                    }
+                }
+                (Some(strategy), _) => {
+                    self.hide_taskbar(window, strategy, self.animates(), desktop, output)
+                }
+                (None, Some(_)) => self.restore_taskbar(window, self.animates(), desktop, output),
+                // Not hidden by thide, e.g. left over from a crash, but it should be visible
+                (None, None) => {
+                    if desktop.is_visible(window) == Some(false) {
+                        output.actions.push(Action::ShowWindow {
+                            window,
+                            visible: true,
+                        });
+                    }
+                }
+            }
+        }
+
+        self.apply_work_areas(desktop, output);
+
+        for element in ShellElement::ALL {
+            if self.desired.elements.contains(&element) {
+                self.hide_element(element, desktop, output);
+            } else {
+                self.show_element(element, desktop, output);
+            }
+        }
+    }
+
+    /// Strategy hiding the taskbar window on a monitor, None if the window is left alone
+    fn strategy_on(&self, monitor: &str, geometries: &[MonitorGeometry]) -> Option<HideStrategy> {
+        if !self.desired.is_hidden_on(monitor) {
+            return None;
+        }
+
+        let index = geometries
+            .iter()
+            .find(|geometry| geometry.name == monitor)
+            .map_or(0, |geometry| geometry.index);
+        let strategy = self
+            .desired
+            .strategy
+            .unwrap_or_else(|| self.hide.strategy_for(monitor, index));
+        Some(strategy).filter(HideStrategy::changes_window)
+    }
+
+    /// Whether auto-hide is needed, dimmed taskbars must stay where they are
+    fn auto_hides(&self, monitors: &[String], geometries: &[MonitorGeometry]) -> bool {
+        if monitors.is_empty() {
+            return self.desired.hidden;
+        }
+
+        monitors.iter().any(|monitor| {
+            self.desired.is_hidden_on(monitor)
+                && self.strategy_on(monitor, geometries) != Some(HideStrategy::Dim)
+        })
+    }
+
+    /// Whether hiding and revealing taskbars is animated
+    fn animates(&self) -> bool {
+        self.animation.kind != AnimationKind::None && !self.stopped
+    }
+
+    /// Hide a taskbar window with a strategy, undoing a different one applied before
+    fn hide_taskbar(
+        &mut self,
+        window: WindowId,
+        strategy: HideStrategy,
+        animate: bool,
+        desktop: &dyn Desktop,
+        output: &mut Output,
+    ) {
+        // While a hide animation runs, the strategy isn't applied yet
+        let animating = self.animations.contains_key(&window);
+        let previous = self
+            .applied
+            .remove(&window)
+            .filter(|applied| applied.strategy != strategy);
+        if let Some(previous) = previous.as_ref().filter(|_| !animating) {
+            output.actions.extend(previous.undo_actions(window));
+        }
+
+        let Some(mut applied) = strategy.capture(window, desktop, &self.hide.dim) else {
+            return;
+        };
+        log::debug!(
+            "Hiding taskbar {:#x} with the {} strategy",
+            window,
+            strategy.as_str()
+        );
+        if let Some(previous) = &previous {
+            applied = applied.inherit(previous);
+        }
+
+        // Turn a reveal around, or start hiding if nothing was undone in this step
+        if let Some(animation) = self.animations.get_mut(&window) {
+            applied = animation.keep_original(applied);
+            animation.turn(Direction::Hide);
+            self.applied.insert(window, applied);
+            return;
+        }
+        if animate && previous.is_none() {
+            if let Some(animation) = Animation::hide(window, &applied, &self.animation, desktop) {
+                self.animations.insert(window, animation);
+                self.applied.insert(window, applied);
+                return;
+            }
+        }
+
+        output.actions.extend(applied.hide_actions(window, desktop));
+        self.applied.insert(window, applied);
+    }
+
+    /// Undo whatever strategy hid a taskbar window
+    fn restore_taskbar(
+        &mut self,
+        window: WindowId,
+        animate: bool,
+        desktop: &dyn Desktop,
+        output: &mut Output,
+    ) {
+        let applied = self.applied.remove(&window);
+
+        // The window may be gone after an explorer restart
+        if desktop.is_visible(window).is_none() {
+            self.animations.remove(&window);
+            return;
+        }
+
+        // A hide animation ends before the strategy is applied, so only the animation is undone
+        if let Some(mut animation) = self.animations.remove(&window) {
+            if animate {
+                animation.turn(Direction::Reveal);
+                self.animations.insert(window, animation);
+            } else {
+                output.actions.extend(animation.restore_actions(window));
+            }
+            return;
+        }
+
+        let Some(applied) = applied else {
+            return;
+        };
+        if animate {
+            if let Some((animation, actions)) =
+                Animation::reveal(window, &applied, &self.animation, desktop)
+            {
+                output.actions.extend(actions);
+                self.animations.insert(window, animation);
+                return;
+            }
+        }
+        output.actions.extend(applied.undo_actions(window));
+    }
+
+    /// Reclaim the work area where the taskbar window is hidden, restore it elsewhere
+    fn apply_work_areas(&mut self, desktop: &dyn Desktop, output: &mut Output) {
+        if !self.work_area.manage {
+            return;
+        }
+
+        let geometries = desktop.monitors();
+        for monitor in &geometries {
+            if self
+                .strategy_on(&monitor.name, &geometries)
+                .is_some_and(|strategy| strategy.frees_space())
+            {
+                let area = reclaimed_work_area(
+                    monitor.rect,
+                    monitor.dpi,
+                    &monitor.name,
+                    monitor.index,
+                    &self.work_area.reserve,
+                );
+
+                self.saved_work_areas
+                    .entry(monitor.name.clone())
+                    .or_insert(monitor.work);
+                if monitor.work != area {
+                    output.actions.push(Action::SetWorkArea { area });
+                }
+            } else if let Some(previous) = self.saved_work_areas.remove(&monitor.name) {
+                output.actions.push(Action::SetWorkArea { area: previous });
+            }
+        }
+    }
+
+    /// Hide the visible windows of an element, remembering them for restoration
+    fn hide_element(&mut self, element: ShellElement, desktop: &dyn Desktop, output: &mut Output) {
+        for window in desktop.element_windows(element) {
+            if desktop.is_visible(window).unwrap_or(false) {
+                output.actions.push(Action::ShowWindow {
+                    window,
+                    visible: false,
+                });
+                if !self.hidden_elements.iter().any(|(h, _)| *h == window) {
+                    self.hidden_elements.push((window, element));
+                }
+            }
+        }
+    }
+
+    /// Show the windows of an element that thide hid, others are left alone
+    fn show_element(&mut self, element: ShellElement, desktop: &dyn Desktop, output: &mut Output) {
+        self.hidden_elements.retain(|&(window, e)| {
+            if e != element {
+                return true;
+            }
+
+            // The window may be gone after an explorer restart
+            if desktop.is_visible(window).is_some() {
+                output.actions.push(Action::ShowWindow {
+                    window,
+                    visible: true,
+                });
+            }
+            false
+        });
+    }
+}
+
+/// Device names of the monitors hosting a taskbar
+fn taskbar_monitors(desktop: &dyn Desktop) -> Vec<String> {
+    desktop
+        .taskbars()
+        .into_iter()
+        .filter_map(|taskbar| desktop.window_monitor(taskbar))
+        .collect()
+}
+
+fn emit(
+    output: &mut Output,
+    kind: TransitionKind,
+    hidden: bool,
+    was_hidden: bool,
+    monitors: Vec<String>,
+) {
+    log::info!(
+        "Transition {} (hidden: {} -> {}) on [{}]",
+        kind.as_str(),
+        was_hidden,
+        hidden,
+        monitors.join(", ")
+    );
+
+    output.transitions.push(Transition {
+        kind,
+        hidden,
+        was_hidden,
+        monitors,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{Mode, Overlay};
+    use crate::easing::Curve;
+    use crate::simulation::{Scenario, ScenarioMonitor, ScenarioWindow};
+    use crate::testing::Session;
+    use proptest::prelude::*;
+    use proptest::sample::select;
+
+    const PRIMARY: &str = "\\\\.\\DISPLAY1";
+    const SECONDARY: &str = "\\\\.\\DISPLAY2";
+    const TASKBARS: [WindowId; 2] = [0x10, 0x20];
+    const ICONS: WindowId = 0x30;
+
+    fn scenario() -> Scenario {
+        let taskbar = |hwnd, class: &str, monitor: &str| ScenarioWindow {
+            hwnd,
+            class: class.to_string(),
+            process: "explorer.exe".to_string(),
+            monitor: monitor.to_string(),
+            ..ScenarioWindow::default()
+        };
+
+        Scenario {
+            monitors: vec![
+                ScenarioMonitor {
+                    name: PRIMARY.to_string(),
+                    rect: Rect::new(0, 0, 1920, 1080),
+                    work: Some(Rect::new(0, 0, 1920, 1032)),
+                    primary: true,
+                    ..ScenarioMonitor::default()
+                },
+                ScenarioMonitor {
+                    name: SECONDARY.to_string(),
+                    rect: Rect::new(1920, 0, 3840, 1080),
+                    work: Some(Rect::new(1920, 0, 3840, 1032)),
+                    ..ScenarioMonitor::default()
+                },
+            ],
+            windows: vec![
+                taskbar(TASKBARS[0], "Shell_TrayWnd", PRIMARY),
+                taskbar(TASKBARS[1], "Shell_SecondaryTrayWnd", SECONDARY),
+                ScenarioWindow {
+                    element: Some(ShellElement::DesktopIcons),
+                    ..taskbar(ICONS, "SHELLDLL_DefView", PRIMARY)
+                },
+            ],
+            ..Scenario::default()
+        }
+    }
+
+    /// What the desktop looks like for the assertions, comparable before and after
+    #[derive(Debug, PartialEq)]
+    struct Observed {
+        windows: Vec<(Option<bool>, Option<Rect>, Option<u8>)>,
+        work_areas: Vec<Rect>,
+        appbar_state: u32,
+    }
+
+    fn observe(desktop: &dyn Desktop) -> Observed {
+        Observed {
+            windows: TASKBARS
+                .iter()
+                .chain([&ICONS])
+                .map(|&window| {
+                    (
+                        desktop.is_visible(window),
+                        desktop.window_rect(window),
+                        desktop.window_alpha(window),
+                    )
+                })
+                .collect(),
+            work_areas: desktop.monitors().iter().map(|m| m.work).collect(),
+            appbar_state: desktop.appbar_state(),
+        }
+    }
+
+    /// The requests the controller should end up reflecting, updated like the desired state
+    #[derive(Debug, Clone, Default)]
+    struct Model {
+        hidden: bool,
+        monitors: BTreeMap<String, bool>,
+        elements: BTreeSet<ShellElement>,
+    }
+
+    impl Model {
+        fn request(&mut self, command: &Command) {
+            match command {
+                Command::Show => self.hidden = false,
+                Command::Hide => self.hidden = true,
+                Command::Toggle => self.hidden = !self.hidden,
+                Command::SetMonitorOverrides(overrides) => self.monitors = overrides.clone(),
+                Command::SetElement(element, true) => {
+                    self.elements.insert(*element);
+                }
+                Command::SetElement(element, false) => {
+                    self.elements.remove(element);
+                }
+                Command::Quit | Command::ApplyProfile(_) => {}
+            }
+        }
+
+        fn is_hidden_on(&self, monitor: &str) -> bool {
+            self.monitors.get(monitor).copied().unwrap_or(self.hidden)
+        }
+    }
+
+    fn session(strategy: HideStrategy) -> Session {
+        animated(strategy, AnimationConfig::default())
+    }
+
+    fn animated(strategy: HideStrategy, animation: AnimationConfig) -> Session {
+        let hide = HideConfig {
+            strategy,
+            ..HideConfig::default()
+        };
+        let mut session = Session::new(Overlay::new(scenario(), Mode::Simulated), hide, animation);
+        session.handle(session.start_input());
+        session
+    }
+
+    fn strategy() -> impl Strategy<Value = HideStrategy> {
+        select(vec![
+            HideStrategy::Hide,
+            HideStrategy::AutoHide,
+            HideStrategy::OffScreen,
+            HideStrategy::Transparent,
+            HideStrategy::Strip,
+            HideStrategy::Dim,
+        ])
+    }
+
+    fn command() -> impl Strategy<Value = Command> {
+        let monitor = select(vec![PRIMARY, SECONDARY]).prop_map(str::to_string);
+        prop_oneof![
+            Just(Command::Show),
+            Just(Command::Hide),
+            Just(Command::Toggle),
+            (select(ShellElement::ALL.to_vec()), any::<bool>())
+                .prop_map(|(element, hidden)| Command::SetElement(element, hidden)),
+            proptest::collection::btree_map(monitor, any::<bool>(), 0..=2)
+                .prop_map(Command::SetMonitorOverrides),
+        ]
+    }
+
+    /// Commands, each optionally followed by a tick
+    fn events() -> impl Strategy<Value = Vec<(Command, bool)>> {
+        proptest::collection::vec((command(), any::<bool>()), 0..24)
+    }
+
+    proptest! {
+        #[test]
+        fn final_state_matches_the_last_requests(strategy in strategy(), events in events()) {
+            let mut session = session(strategy);
+            let mut model = Model {
+                hidden: true,
+                ..Model::default()
+            };
+
+            for (command, tick) in events {
+                model.request(&command);
+                session.command(command);
+                if tick {
+                    session.tick();
+                }
+            }
+
+            let desired = session.controller.desired();
+            prop_assert_eq!(desired.hidden, model.hidden);
+            prop_assert_eq!(&desired.monitors, &model.monitors);
+            prop_assert_eq!(&desired.elements, &model.elements);
+
+            let desktop = &session.desktop;
+            let monitors = [PRIMARY, SECONDARY];
+            for (&window, monitor) in TASKBARS.iter().zip(monitors) {
+                let hidden = model.is_hidden_on(monitor);
+                let visible = desktop.is_visible(window);
+                let expected = strategy != HideStrategy::Hide || !hidden;
+                prop_assert_eq!(visible, Some(expected), "taskbar on {}", monitor);
+            }
+
+            let auto_hide = monitors.iter().any(|m| model.is_hidden_on(m))
+                && strategy != HideStrategy::Dim;
+            prop_assert_eq!(desktop.appbar_state() & ABS_AUTOHIDE != 0, auto_hide);
+
+            let icons_hidden = model.elements.contains(&ShellElement::DesktopIcons);
+            prop_assert_eq!(desktop.is_visible(ICONS), Some(!icons_hidden));
+        }
+
+        #[test]
+        fn repeated_requests_change_nothing(strategy in strategy(), events in events()) {
+            let mut session = session(strategy);
+            for (command, tick) in events {
+                let repeatable = command != Command::Toggle;
+                session.command(command.clone());
+                if tick {
+                    session.tick();
+                }
+
+                if repeatable {
+                    let before = observe(&session.desktop);
+                    let output = session.command(command);
+                    prop_assert!(output.actions.is_empty(), "{:?}", output.actions);
+                    prop_assert!(output.transitions.is_empty());
+                    prop_assert_eq!(observe(&session.desktop), before);
+                }
+            }
+
+            // A settled desktop needs no re-hiding
+            let output = session.tick();
+            prop_assert!(output.actions.is_empty(), "{:?}", output.actions);
+        }
+
+        #[test]
+        fn quit_restores_the_original_desktop(strategy in strategy(), events in events()) {
+            let original = observe(&Overlay::new(scenario(), Mode::Simulated));
+            let mut session = session(strategy);
+            for (command, tick) in events {
+                session.command(command);
+                if tick {
+                    session.tick();
+                }
+            }
+
+            let output = session.command(Command::Quit);
+            prop_assert!(session.controller.is_stopped());
+            prop_assert_eq!(observe(&session.desktop), original);
+            prop_assert!(output
+                .transitions
+                .iter()
+                .any(|transition| transition.kind == TransitionKind::Quit));
+
+            // Nothing is changed once stopped
+            prop_assert!(session.command(Command::Hide).actions.is_empty());
+            prop_assert!(session.tick().actions.is_empty());
+        }
+    }
+
+    #[test]
+    fn hide_and_show_emit_one_transition_each() {
+        let mut session = session(HideStrategy::Hide);
+
+        let shown = session.command(Command::Show);
+        let kinds: Vec<_> = shown.transitions.iter().map(|t| t.kind).collect();
+        assert_eq!(kinds, [TransitionKind::Show]);
+        assert_eq!(shown.transitions[0].monitors, [PRIMARY, SECONDARY]);
+
+        let hidden = session.command(Command::Hide);
+        let kinds: Vec<_> = hidden.transitions.iter().map(|t| t.kind).collect();
+        assert_eq!(kinds, [TransitionKind::Hide]);
+        assert!(session.command(Command::Hide).transitions.is_empty());
+    }
+
+    fn animation(kind: AnimationKind) -> AnimationConfig {
+        AnimationConfig {
+            kind,
+            duration_ms: 200,
+            curve: Curve::Linear,
+        }
+    }
+
+    #[test]
+    fn animations_end_in_the_requested_state() {
+        let original = observe(&Overlay::new(scenario(), Mode::Simulated));
+
+        for kind in [AnimationKind::Fade, AnimationKind::Slide] {
+            let mut session = animated(HideStrategy::Hide, animation(kind));
+            assert!(session.controller.is_animating());
+            assert_eq!(session.frames(), 5);
+            for window in TASKBARS {
+                assert_eq!(session.desktop.is_visible(window), Some(false), "{kind:?}");
+            }
+
+            session.command(Command::Show);
+            session.frames();
+            assert_eq!(observe(&session.desktop), original, "{kind:?}");
+        }
+    }
+
+    #[test]
+    fn reversed_animations_return_to_the_original_state() {
+        let original = observe(&Overlay::new(scenario(), Mode::Simulated));
+        let mut session = animated(HideStrategy::OffScreen, animation(AnimationKind::Slide));
+        session.frames();
+
+        session.command(Command::Show);
+        session.handle(Input::Frame(session.now));
+        session.now += Duration::from_millis(50);
+        session.handle(Input::Frame(session.now));
+        session.command(Command::Hide);
+        session.now += Duration::from_millis(50);
+        session.handle(Input::Frame(session.now));
+        session.command(Command::Show);
+        session.frames();
+        assert_eq!(observe(&session.desktop), original);
+
+        // Quitting halfway through an animation restores the desktop too
+        session.command(Command::Hide);
+        session.now += Duration::from_millis(50);
+        session.handle(Input::Frame(session.now));
+        session.command(Command::Quit);
+        assert!(!session.controller.is_animating());
+        assert_eq!(observe(&session.desktop), original);
+    }
+
+    #[test]
+    fn profile_strategies_replace_the_hide_config() {
+        let mut session = session(HideStrategy::Hide);
+        let focus = Profile {
+            strategy: Some(HideStrategy::AutoHide),
+            ..Profile::default()
+        };
+        session.command(Command::ApplyProfile(focus));
+
+        // Auto-hide leaves the windows alone, the ones hidden by the [hide] strategy are shown again
+        for window in TASKBARS {
+            assert_eq!(session.desktop.is_visible(window), Some(true));
+        }
+        assert_eq!(session.desktop.appbar_state() & ABS_AUTOHIDE, ABS_AUTOHIDE);
+
+        let off_screen = Profile {
+            strategy: Some(HideStrategy::OffScreen),
+            ..Profile::default()
+        };
+        session.command(Command::ApplyProfile(off_screen));
+        let rect = session.desktop.window_rect(TASKBARS[0]).unwrap();
+        assert!(rect.right < 0, "{:?}", rect);
+
+        session.command(Command::ApplyProfile(Profile::default()));
+        assert_eq!(session.desktop.is_visible(TASKBARS[0]), Some(false));
+        assert_eq!(
+            session.desktop.window_rect(TASKBARS[0]),
+            Some(Rect::new(0, 1032, 1920, 1080))
+        );
+    }
+}
@@ -0,0 +1,842 @@
+#[cfg(windows)]
+use crate::backend::Win32Backend;
+use crate::backend::{Desktop, WindowId, ABS_ALWAYSONTOP, ABS_AUTOHIDE};
+#[cfg(windows)]
+use crate::cli::{self, LaunchOptions};
+#[cfg(windows)]
+use crate::config;
+use crate::config::Config;
+use crate::geometry::{Edge, Rect};
+use crate::i18n;
+#[cfg(windows)]
+use crate::instance::{self, InstanceLock, InstanceNames};
+use crate::simulation::Scenario;
+use crate::status::Status;
+use crate::targets::{self, Verdict, WindowIdentity};
+use crate::validate;
+use crate::workarea::MonitorGeometry;
+use serde::Serialize;
+#[cfg(windows)]
+use std::mem;
+use std::path::{Path, PathBuf};
+#[cfg(windows)]
+use windows::Win32::UI::Shell::{
+    SHAppBarMessage, ABE_LEFT, ABE_RIGHT, ABE_TOP, ABM_GETTASKBARPOS, APPBARDATA,
+};
+
+/// Result of a diagnostic, ordered from best to worst
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Pass => "pass",
+            Severity::Warn => "warn",
+            Severity::Fail => "fail",
+        }
+    }
+}
+
+/// What a diagnostic found
+#[derive(Debug, Clone, Serialize)]
+pub struct Outcome {
+    pub severity: Severity,
+    pub summary: String,
+    pub details: Vec<String>,
+}
+
+impl Outcome {
+    pub fn pass(summary: impl Into<String>) -> Self {
+        Self::new(Severity::Pass, summary)
+    }
+
+    pub fn warn(summary: impl Into<String>) -> Self {
+        Self::new(Severity::Warn, summary)
+    }
+
+    pub fn fail(summary: impl Into<String>) -> Self {
+        Self::new(Severity::Fail, summary)
+    }
+
+    fn new(severity: Severity, summary: impl Into<String>) -> Self {
+        Self {
+            severity,
+            summary: summary.into(),
+            details: Vec::new(),
+        }
+    }
+
+    pub fn with_details(mut self, details: Vec<String>) -> Self {
+        self.details = details;
+        self
+    }
+}
+
+/// A named check over the gathered facts
+pub struct Diagnostic {
+    pub name: &'static str,
+    /// Message key of the title in the text report
+    pub title: &'static str,
+    pub run: fn(&Context) -> Outcome,
+}
+
+/// Every diagnostic `thide doctor` runs, in report order
+pub const DIAGNOSTICS: &[Diagnostic] = &[
+    Diagnostic {
+        name: "taskbar-windows",
+        title: "doctor-title-taskbar-windows",
+        run: taskbar_windows,
+    },
+    Diagnostic {
+        name: "taskbar-visibility",
+        title: "doctor-title-taskbar-visibility",
+        run: taskbar_visibility,
+    },
+    Diagnostic {
+        name: "appbar",
+        title: "doctor-title-appbar",
+        run: appbar,
+    },
+    Diagnostic {
+        name: "monitors",
+        title: "doctor-title-monitors",
+        run: monitors,
+    },
+    Diagnostic {
+        name: "instance",
+        title: "doctor-title-instance",
+        run: instance,
+    },
+    Diagnostic {
+        name: "autostart",
+        title: "doctor-title-autostart",
+        run: autostart,
+    },
+    Diagnostic {
+        name: "config",
+        title: "doctor-title-config",
+        run: config,
+    },
+];
+
+/// A window the taskbar rules matched or rejected
+#[derive(Debug, Clone, Serialize)]
+pub struct WindowReport {
+    pub hwnd: WindowId,
+    pub class: String,
+    pub process: String,
+    pub title: String,
+    pub visible: bool,
+    /// Device name of the monitor the window is on
+    pub monitor: String,
+    /// match, exclude or reject
+    pub status: &'static str,
+    pub reason: String,
+}
+
+impl WindowReport {
+    fn new(
+        hwnd: WindowId,
+        window: WindowIdentity,
+        visible: bool,
+        monitor: String,
+        verdict: &Verdict,
+    ) -> Self {
+        Self {
+            hwnd,
+            class: window.class,
+            process: window.process,
+            title: window.title,
+            visible,
+            monitor,
+            status: verdict.status(),
+            reason: verdict.reason(),
+        }
+    }
+
+    fn is_taskbar(&self) -> bool {
+        self.status == "match"
+    }
+}
+
+/// State of the single-instance lock and IPC window
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct InstanceReport {
+    /// Whether another process holds the instance mutex
+    pub mutex_held: bool,
+    pub ipc_window: bool,
+    /// Status answered over IPC, None if nothing answered
+    pub status: Option<Status>,
+}
+
+/// Everything the diagnostics look at, gathered once up front
+pub struct Context {
+    pub desktop: Box<dyn Desktop>,
+    pub windows: Vec<WindowReport>,
+    /// Edge and rect of the primary taskbar, None if unknown
+    pub taskbar_position: Option<(Edge, Rect)>,
+    pub instance: InstanceReport,
+    /// Command line of the autostart entry
+    pub autostart: Option<String>,
+    pub current_exe: Option<PathBuf>,
+    pub config_path: Option<PathBuf>,
+    /// The parsed config, or why it could not be loaded
+    pub config: Result<Config, String>,
+}
+
+impl Context {
+    /// Collect the facts from this machine, or the desktop part from a scenario
+    #[cfg(windows)]
+    pub fn gather(launch: &LaunchOptions) -> Result<Self, Box<dyn std::error::Error>> {
+        let config = config::load().map_err(|e| e.to_string());
+        if let Ok(config) = &config {
+            targets::init(config.taskbars.clone());
+        }
+
+        let (desktop, windows, taskbar_position): (Box<dyn Desktop>, _, _) = match &launch.simulate
+        {
+            Some(path) => {
+                let scenario = Scenario::load(path)?;
+                let windows = scenario_windows(&scenario);
+                (Box::new(scenario), windows, None)
+            }
+            None => (Box::new(Win32Backend), live_windows(), taskbar_position()),
+        };
+
+        Ok(Self {
+            desktop,
+            windows,
+            taskbar_position,
+            instance: instance_report(config.as_ref().ok()),
+            autostart: cli::autostart_command(),
+            current_exe: std::env::current_exe().ok(),
+            config_path: config::config_path(),
+            config,
+        })
+    }
+}
+
+#[cfg(windows)]
+fn live_windows() -> Vec<WindowReport> {
+    targets::configured_candidates()
+        .into_iter()
+        .map(|candidate| {
+            WindowReport::new(
+                candidate.hwnd.0 as WindowId,
+                candidate.window,
+                candidate.visible,
+                crate::window_monitor(candidate.hwnd).unwrap_or_default(),
+                &candidate.verdict,
+            )
+        })
+        .collect()
+}
+
+fn scenario_windows(scenario: &Scenario) -> Vec<WindowReport> {
+    scenario
+        .windows
+        .iter()
+        .filter_map(|window| {
+            let identity = WindowIdentity {
+                class: window.class.clone(),
+                process: window.process.clone(),
+                title: window.title.clone(),
+            };
+            let verdict = targets::evaluate(targets::matchers(), &identity)?;
+
+            Some(WindowReport::new(
+                window.hwnd,
+                identity,
+                window.visible,
+                window.monitor.clone(),
+                &verdict,
+            ))
+        })
+        .collect()
+}
+
+/// ABM_GETTASKBARPOS of the primary taskbar
+#[cfg(windows)]
+fn taskbar_position() -> Option<(Edge, Rect)> {
+    unsafe {
+        let mut data: APPBARDATA = mem::zeroed();
+        data.cbSize = mem::size_of::<APPBARDATA>() as u32;
+        if SHAppBarMessage(ABM_GETTASKBARPOS, &mut data) == 0 {
+            return None;
+        }
+
+        let edge = match data.uEdge {
+            ABE_LEFT => Edge::Left,
+            ABE_TOP => Edge::Top,
+            ABE_RIGHT => Edge::Right,
+            _ => Edge::Bottom,
+        };
+        Some((edge, data.rc.into()))
+    }
+}
+
+#[cfg(windows)]
+fn instance_report(config: Option<&Config>) -> InstanceReport {
+    let names = InstanceNames::new(
+        instance::current().name.as_deref(),
+        config
+            .map(|config| config.instance.scope)
+            .unwrap_or_default(),
+    );
+
+    // Briefly taking the lock is the only way to tell whether someone else holds it
+    let mutex_held = InstanceLock::acquire(&names).is_ok_and(|lock| !lock.is_owned());
+    let ipc_window = cli::find_ipc_window().is_some();
+
+    InstanceReport {
+        mutex_held,
+        ipc_window,
+        status: ipc_window.then(cli::request_status).flatten(),
+    }
+}
+
+/// Run every diagnostic against the context
+pub fn run(context: &Context) -> Vec<(&'static Diagnostic, Outcome)> {
+    DIAGNOSTICS
+        .iter()
+        .map(|diagnostic| (diagnostic, (diagnostic.run)(context)))
+        .collect()
+}
+
+fn taskbar_windows(context: &Context) -> Outcome {
+    let details = context
+        .windows
+        .iter()
+        .map(|window| {
+            format!(
+                "{:<8} {:#010x}  {:<24} {:<16} {:<8} {:<14} \"{}\"  {}",
+                window.status,
+                window.hwnd as usize,
+                window.class,
+                window.process,
+                i18n::text(if window.visible {
+                    "state-visible"
+                } else {
+                    "state-hidden"
+                }),
+                window.monitor,
+                window.title,
+                window.reason
+            )
+        })
+        .collect();
+
+    let taskbars: Vec<&WindowReport> = context
+        .windows
+        .iter()
+        .filter(|window| window.is_taskbar())
+        .collect();
+    let mut monitors: Vec<&str> = taskbars.iter().map(|w| w.monitor.as_str()).collect();
+    monitors.sort_unstable();
+    monitors.dedup();
+
+    let outcome = match taskbars.len() {
+        0 => Outcome::fail(i18n::text("doctor-no-taskbars")),
+        count => Outcome::pass(i18n::text_with(
+            "doctor-taskbars",
+            &[
+                ("count", &count.to_string()),
+                ("monitors", &monitors.len().to_string()),
+            ],
+        )),
+    };
+    outcome.with_details(details)
+}
+
+fn taskbar_visibility(context: &Context) -> Outcome {
+    if context.instance.mutex_held || context.instance.ipc_window {
+        return Outcome::pass(i18n::text("doctor-managed"));
+    }
+
+    // Hidden taskbars without a running instance are left over from a crash
+    let hidden: Vec<String> = context
+        .windows
+        .iter()
+        .filter(|window| window.is_taskbar())
+        .filter(|window| context.desktop.is_visible(window.hwnd) == Some(false))
+        .map(|window| {
+            i18n::text_with(
+                "doctor-hidden-taskbar",
+                &[
+                    ("window", &format!("{:#x}", window.hwnd as usize)),
+                    ("monitor", &window.monitor),
+                ],
+            )
+        })
+        .collect();
+
+    if hidden.is_empty() {
+        Outcome::pass(i18n::text("doctor-all-visible"))
+    } else {
+        Outcome::warn(i18n::text_with(
+            "doctor-hidden-taskbars",
+            &[("count", &hidden.len().to_string())],
+        ))
+        .with_details(hidden)
+    }
+}
+
+fn appbar(context: &Context) -> Outcome {
+    let state = context.desktop.appbar_state();
+    let mut flags = Vec::new();
+    if state & ABS_AUTOHIDE != 0 {
+        flags.push(i18n::text("doctor-appbar-auto-hide"));
+    }
+    if state & ABS_ALWAYSONTOP != 0 {
+        flags.push(i18n::text("doctor-appbar-always-on-top"));
+    }
+    if flags.is_empty() {
+        flags.push(i18n::text("doctor-appbar-normal"));
+    }
+
+    let details = match context.taskbar_position {
+        Some((edge, rect)) => {
+            let edge = match edge {
+                Edge::Left => "doctor-edge-left",
+                Edge::Top => "doctor-edge-top",
+                Edge::Right => "doctor-edge-right",
+                Edge::Bottom => "doctor-edge-bottom",
+            };
+            vec![i18n::text_with(
+                "doctor-taskbar-position",
+                &[
+                    ("edge", &i18n::text(edge)),
+                    ("left", &rect.left.to_string()),
+                    ("top", &rect.top.to_string()),
+                    ("right", &rect.right.to_string()),
+                    ("bottom", &rect.bottom.to_string()),
+                ],
+            )]
+        }
+        None => vec![i18n::text("doctor-taskbar-position-unknown")],
+    };
+
+    let summary = i18n::text_with(
+        "doctor-appbar-state",
+        &[
+            ("state", &format!("{:#x}", state)),
+            ("flags", &flags.join(", ")),
+        ],
+    );
+    Outcome::pass(summary).with_details(details)
+}
+
+fn monitors(context: &Context) -> Outcome {
+    let monitors: Vec<MonitorGeometry> = context.desktop.monitors();
+    let details = monitors
+        .iter()
+        .map(|monitor| {
+            let geometry = i18n::text_with(
+                "doctor-monitor",
+                &[
+                    ("width", &monitor.rect.width().to_string()),
+                    ("height", &monitor.rect.height().to_string()),
+                    ("dpi", &monitor.dpi.to_string()),
+                    ("left", &monitor.work.left.to_string()),
+                    ("top", &monitor.work.top.to_string()),
+                    ("right", &monitor.work.right.to_string()),
+                    ("bottom", &monitor.work.bottom.to_string()),
+                ],
+            );
+            format!("{}  {:<14} {}", monitor.index, monitor.name, geometry)
+        })
+        .collect();
+
+    let broken: Vec<&str> = monitors
+        .iter()
+        .filter(|monitor| monitor.work.width() <= 0 || !monitor.rect.contains(monitor.work))
+        .map(|monitor| monitor.name.as_str())
+        .collect();
+
+    let outcome = if monitors.is_empty() {
+        Outcome::fail(i18n::text("doctor-no-monitors"))
+    } else if !broken.is_empty() {
+        Outcome::warn(i18n::text_with(
+            "doctor-work-area-outside",
+            &[("monitors", &broken.join(", "))],
+        ))
+    } else {
+        Outcome::pass(i18n::text_with(
+            "doctor-monitor-count",
+            &[("count", &monitors.len().to_string())],
+        ))
+    };
+    outcome.with_details(details)
+}
+
+fn instance(context: &Context) -> Outcome {
+    let report = &context.instance;
+
+    match (report.mutex_held, report.ipc_window, &report.status) {
+        (false, false, _) => Outcome::pass(i18n::text("doctor-not-running")),
+        (_, true, Some(status)) => {
+            let outcome = i18n::text_with(
+                "doctor-running",
+                &[
+                    ("pid", &status.pid.to_string()),
+                    ("profile", &status.profile),
+                    ("mode", &status.mode),
+                ],
+            );
+            let warnings: Vec<String> = status.flapping.iter().map(|r| r.message()).collect();
+            if warnings.is_empty() {
+                Outcome::pass(outcome)
+            } else {
+                Outcome::warn(outcome).with_details(warnings)
+            }
+        }
+        (_, true, None) => Outcome::warn(i18n::text("doctor-unresponsive")),
+        (true, false, _) => Outcome::fail(i18n::text("doctor-stale-lock")),
+    }
+}
+
+/// Strip the quotes and arguments of a command line, leaving the executable
+fn command_executable(command: &str) -> &str {
+    match command.strip_prefix('"') {
+        Some(rest) => rest.split('"').next().unwrap_or(rest),
+        None => command.split(" --").next().unwrap_or(command).trim(),
+    }
+}
+
+fn autostart(context: &Context) -> Outcome {
+    let Some(command) = &context.autostart else {
+        return Outcome::pass(i18n::text("doctor-autostart-disabled"));
+    };
+
+    let executable = Path::new(command_executable(command));
+    let details = vec![command.clone()];
+
+    if context.current_exe.as_deref().is_some_and(|exe| {
+        exe.to_string_lossy()
+            .eq_ignore_ascii_case(&executable.to_string_lossy())
+    }) {
+        Outcome::pass(i18n::text("doctor-autostart-enabled")).with_details(details)
+    } else if !executable.exists() {
+        Outcome::fail(i18n::text("doctor-autostart-missing")).with_details(details)
+    } else {
+        Outcome::warn(i18n::text("doctor-autostart-other")).with_details(details)
+    }
+}
+
+fn config(context: &Context) -> Outcome {
+    let path = context
+        .config_path
+        .as_ref()
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|| i18n::text("doctor-appdata-unset"));
+
+    let config = match &context.config {
+        Ok(config) => config,
+        Err(e) => {
+            return Outcome::fail(i18n::text_with("doctor-config-invalid", &[("error", e)]))
+                .with_details(vec![path])
+        }
+    };
+
+    if !context.config_path.as_deref().is_some_and(Path::exists) {
+        return Outcome::pass(i18n::text("doctor-config-defaults")).with_details(vec![path]);
+    }
+
+    let problems = validate::problems(config);
+    if problems.is_empty() {
+        Outcome::pass(i18n::text("doctor-config-valid")).with_details(vec![path])
+    } else {
+        Outcome::warn(i18n::text_with(
+            "doctor-config-problems",
+            &[("count", &problems.len().to_string())],
+        ))
+        .with_details(std::iter::once(path).chain(problems).collect())
+    }
+}
+
+/// Machine-readable report for bug reports
+#[derive(Serialize)]
+struct Bundle<'a> {
+    version: &'static str,
+    mode: &'static str,
+    checks: Vec<JsonCheck<'a>>,
+    windows: &'a [WindowReport],
+    monitors: Vec<MonitorGeometry>,
+    appbar_state: u32,
+    instance: &'a InstanceReport,
+}
+
+#[derive(Serialize)]
+struct JsonCheck<'a> {
+    name: &'static str,
+    #[serde(flatten)]
+    outcome: &'a Outcome,
+}
+
+/// Print the report as text or JSON, returns the worst severity
+pub fn report(
+    context: &Context,
+    simulated: bool,
+    json: bool,
+) -> Result<Severity, Box<dyn std::error::Error>> {
+    let results = run(context);
+    let worst = results
+        .iter()
+        .map(|(_, outcome)| outcome.severity)
+        .max()
+        .unwrap_or(Severity::Pass);
+
+    if json {
+        let bundle = Bundle {
+            version: env!("CARGO_PKG_VERSION"),
+            mode: if simulated { "simulated" } else { "live" },
+            checks: results
+                .iter()
+                .map(|(diagnostic, outcome)| JsonCheck {
+                    name: diagnostic.name,
+                    outcome,
+                })
+                .collect(),
+            windows: &context.windows,
+            monitors: context.desktop.monitors(),
+            appbar_state: context.desktop.appbar_state(),
+            instance: &context.instance,
+        };
+        println!("{}", serde_json::to_string_pretty(&bundle)?);
+        return Ok(worst);
+    }
+
+    let header = if simulated {
+        "doctor-header-simulated"
+    } else {
+        "doctor-header"
+    };
+    println!(
+        "{}",
+        i18n::text_with(header, &[("version", env!("CARGO_PKG_VERSION"))])
+    );
+    println!();
+    for (diagnostic, outcome) in &results {
+        println!(
+            "[{}] {:<20} {}",
+            outcome.severity.as_str(),
+            i18n::text(diagnostic.title),
+            outcome.summary
+        );
+        for detail in &outcome.details {
+            println!("       {}", detail);
+        }
+    }
+
+    let count = |severity| {
+        results
+            .iter()
+            .filter(|(_, outcome)| outcome.severity == severity)
+            .count()
+    };
+    println!();
+    println!(
+        "{}",
+        i18n::text_with(
+            "doctor-totals",
+            &[
+                ("passed", &count(Severity::Pass).to_string()),
+                ("warnings", &count(Severity::Warn).to_string()),
+                ("failed", &count(Severity::Fail).to_string()),
+            ],
+        )
+    );
+
+    Ok(worst)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flapping::FlapReport;
+    use crate::targets::Matcher;
+    use crate::testing::{scenario, SCENARIO};
+
+    fn context(scenario: Scenario) -> Context {
+        Context {
+            windows: scenario_windows(&scenario),
+            desktop: Box::new(scenario),
+            taskbar_position: None,
+            instance: InstanceReport::default(),
+            autostart: None,
+            current_exe: None,
+            config_path: None,
+            config: Ok(Config::default()),
+        }
+    }
+
+    fn outcome(context: &Context, name: &str) -> Outcome {
+        let diagnostic = DIAGNOSTICS.iter().find(|d| d.name == name).unwrap();
+        (diagnostic.run)(context)
+    }
+
+    #[test]
+    fn healthy_desktop_passes_every_check() {
+        let context = context(scenario());
+        for (diagnostic, outcome) in run(&context) {
+            assert_eq!(outcome.severity, Severity::Pass, "{}", diagnostic.name);
+        }
+
+        let windows = outcome(&context, "taskbar-windows");
+        assert_eq!(
+            windows.summary,
+            "2 taskbar window(s) matched on 2 monitor(s)"
+        );
+        let matched = windows.details.iter().filter(|d| d.starts_with("match"));
+        assert_eq!(matched.count(), 2);
+    }
+
+    #[test]
+    fn missing_taskbars_fail() {
+        let mut scenario = scenario();
+        scenario
+            .windows
+            .retain(|window| window.process != "explorer.exe");
+        let outcome = outcome(&context(scenario), "taskbar-windows");
+        assert_eq!(outcome.severity, Severity::Fail);
+    }
+
+    #[test]
+    fn hidden_taskbars_warn_only_without_an_instance() {
+        let mut scenario = scenario();
+        scenario.windows[1].visible = false;
+        let mut context = context(scenario);
+
+        let stranded = outcome(&context, "taskbar-visibility");
+        assert_eq!(stranded.severity, Severity::Warn);
+        assert_eq!(stranded.details, ["0x2 on \\\\.\\DISPLAY2"]);
+
+        context.instance.mutex_held = true;
+        let managed = outcome(&context, "taskbar-visibility");
+        assert_eq!(managed.severity, Severity::Pass);
+    }
+
+    #[test]
+    fn appbar_flags_are_named() {
+        let mut scenario = scenario();
+        scenario.appbar_state = ABS_AUTOHIDE | ABS_ALWAYSONTOP;
+        let mut context = context(scenario);
+        context.taskbar_position = Some((Edge::Bottom, Rect::new(0, 1032, 1920, 1080)));
+
+        let outcome = outcome(&context, "appbar");
+        assert_eq!(outcome.summary, "State 0x3 (auto-hide, always on top)");
+        assert_eq!(
+            outcome.details,
+            ["Primary taskbar at the bottom edge, (0, 1032) - (1920, 1080)"]
+        );
+    }
+
+    #[test]
+    fn broken_work_areas_and_missing_monitors() {
+        let mut scenario = scenario();
+        scenario.monitors[1].work = Some(Rect::new(0, 0, 100, 100));
+        let outcome = outcome(&context(scenario.clone()), "monitors");
+        assert_eq!(outcome.severity, Severity::Warn);
+        assert!(outcome.summary.ends_with("\\\\.\\DISPLAY2"));
+
+        scenario.monitors.clear();
+        scenario.windows.clear();
+        let outcome = self::outcome(&context(scenario), "monitors");
+        assert_eq!(outcome.severity, Severity::Fail);
+    }
+
+    #[test]
+    fn instance_states() {
+        let mut context = context(scenario());
+        let check = |context: &Context| outcome(context, "instance").severity;
+        assert_eq!(check(&context), Severity::Pass);
+
+        context.instance.mutex_held = true;
+        assert_eq!(check(&context), Severity::Fail);
+
+        context.instance.ipc_window = true;
+        assert_eq!(check(&context), Severity::Warn);
+
+        context.instance.status = Some(Status {
+            pid: 42,
+            mode: "live".to_string(),
+            profile: "default".to_string(),
+            ..Status::default()
+        });
+        let running = outcome(&context, "instance");
+        assert_eq!(running.severity, Severity::Pass);
+        assert_eq!(
+            running.summary,
+            "Running (pid 42, profile default, live mode)"
+        );
+
+        if let Some(status) = &mut context.instance.status {
+            status.flapping.push(FlapReport {
+                monitor: "\\\\.\\DISPLAY1".to_string(),
+                rehides: 20,
+                window_secs: 10,
+                culprit: Some("fighter.exe".to_string()),
+                backoff_secs: 4,
+            });
+        }
+        let flapping = outcome(&context, "instance");
+        assert_eq!(flapping.severity, Severity::Warn);
+        assert_eq!(flapping.details.len(), 1);
+    }
+
+    #[test]
+    fn autostart_points_to_this_executable() {
+        let mut context = context(scenario());
+        let check = |context: &Context| outcome(context, "autostart").severity;
+        assert_eq!(check(&context), Severity::Pass);
+
+        context.current_exe = Some(PathBuf::from("C:\\Tools\\thide.exe"));
+        context.autostart = Some("\"C:\\Tools\\THIDE.exe\" --profile focus".to_string());
+        assert_eq!(check(&context), Severity::Pass);
+
+        context.autostart = Some("C:\\Gone\\thide.exe --profile focus".to_string());
+        assert_eq!(check(&context), Severity::Fail);
+
+        // An existing file, but not the running executable
+        context.autostart = Some(format!("\"{}\"", SCENARIO));
+        assert_eq!(check(&context), Severity::Warn);
+    }
+
+    #[test]
+    fn command_lines_are_reduced_to_the_executable() {
+        assert_eq!(
+            command_executable("\"C:\\Program Files\\THide\\thide.exe\" --profile x"),
+            "C:\\Program Files\\THide\\thide.exe"
+        );
+        assert_eq!(
+            command_executable("C:\\thide\\thide.exe --profile x"),
+            "C:\\thide\\thide.exe"
+        );
+        assert_eq!(command_executable("C:\\thide.exe"), "C:\\thide.exe");
+    }
+
+    #[test]
+    fn config_problems_are_listed() {
+        let mut context = context(scenario());
+        context.config = Err("unknown field `hdie`".to_string());
+        assert_eq!(outcome(&context, "config").severity, Severity::Fail);
+
+        context.config_path = Some(PathBuf::from(SCENARIO));
+        context.config = Ok(Config {
+            taskbars: vec![Matcher::default()],
+            ..Config::default()
+        });
+        let problems = outcome(&context, "config");
+        assert_eq!(problems.severity, Severity::Warn);
+        assert_eq!(problems.details.len(), 2);
+
+        context.config = Ok(Config::default());
+        assert_eq!(outcome(&context, "config").summary, "Valid");
+    }
+}
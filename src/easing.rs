@@ -0,0 +1,229 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Shape of a transition over time
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Curve {
+    /// Constant speed
+    Linear,
+    /// Slow start and end, cubic ease-in-out
+    #[default]
+    Ease,
+}
+
+impl Curve {
+    /// Progress of the transition at `t`, both between 0 and 1
+    pub fn progress(self, t: f64) -> f64 {
+        let t = if t.is_nan() { 1.0 } else { t.clamp(0.0, 1.0) };
+
+        match self {
+            Curve::Linear => t,
+            Curve::Ease if t < 0.5 => 4.0 * t * t * t,
+            Curve::Ease => 1.0 - (2.0 - 2.0 * t).powi(3) / 2.0,
+        }
+    }
+}
+
+/// Alpha between `from` and `to` at `t` along the curve
+pub fn blend(from: u8, to: u8, curve: Curve, t: f64) -> u8 {
+    let from = f64::from(from);
+    (from + (f64::from(to) - from) * curve.progress(t)).round() as u8
+}
+
+/// An opacity transition started at a point in time
+///
+/// Time is passed in by the caller, so a fade is deterministic for a given timeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fade {
+    pub from: u8,
+    pub to: u8,
+    started: Instant,
+}
+
+impl Fade {
+    pub fn new(from: u8, to: u8, now: Instant) -> Self {
+        Self {
+            from,
+            to,
+            started: now,
+        }
+    }
+
+    /// Alpha at `now`, `to` once the duration has passed
+    pub fn alpha_at(&self, now: Instant, duration: Duration, curve: Curve) -> u8 {
+        if duration.is_zero() {
+            return self.to;
+        }
+
+        let elapsed = now.saturating_duration_since(self.started);
+        blend(
+            self.from,
+            self.to,
+            curve,
+            elapsed.as_secs_f64() / duration.as_secs_f64(),
+        )
+    }
+}
+
+/// A value moving between two points, started by its first frame
+///
+/// Like `Fade`, time is passed in by the caller.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timeline {
+    from: f64,
+    to: f64,
+    duration: Duration,
+    curve: Curve,
+    started: Option<Instant>,
+    value: f64,
+}
+
+impl Timeline {
+    /// `duration` is what a move from 0 to 1 takes, shorter moves take proportionally less
+    pub fn new(from: f64, to: f64, duration: Duration, curve: Curve) -> Self {
+        Self {
+            from,
+            to,
+            duration: duration.mul_f64((to - from).abs().min(1.0)),
+            curve,
+            started: None,
+            value: from,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.value == self.to
+    }
+
+    /// Value at `now`, the first call starts the timeline
+    pub fn advance(&mut self, now: Instant) -> f64 {
+        let started = *self.started.get_or_insert(now);
+        let elapsed = now.saturating_duration_since(started);
+
+        self.value = if elapsed >= self.duration {
+            self.to
+        } else {
+            let t = elapsed.as_secs_f64() / self.duration.as_secs_f64();
+            self.from + (self.to - self.from) * self.curve.progress(t)
+        };
+        self.value
+    }
+
+    /// Head for another value from wherever the last frame was, e.g. when a command is reversed
+    pub fn retarget(&mut self, to: f64, duration: Duration) {
+        *self = Self::new(self.value, to, duration, self.curve);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CURVES: [Curve; 2] = [Curve::Linear, Curve::Ease];
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn curves_run_from_zero_to_one() {
+        for curve in CURVES {
+            assert_eq!(curve.progress(0.0), 0.0);
+            assert_eq!(curve.progress(0.5), 0.5);
+            assert_eq!(curve.progress(1.0), 1.0);
+
+            let samples: Vec<f64> = (0..=100)
+                .map(|i| curve.progress(i as f64 / 100.0))
+                .collect();
+            assert!(
+                samples.windows(2).all(|pair| pair[0] <= pair[1]),
+                "{curve:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn ease_is_symmetric_and_slow_at_the_ends() {
+        for i in 0..=50 {
+            let t = i as f64 / 100.0;
+            let sum = Curve::Ease.progress(t) + Curve::Ease.progress(1.0 - t);
+            assert!((sum - 1.0).abs() < 1e-12, "{t}");
+        }
+        assert!(Curve::Ease.progress(0.1) < Curve::Linear.progress(0.1));
+        assert!(Curve::Ease.progress(0.9) > Curve::Linear.progress(0.9));
+    }
+
+    #[test]
+    fn out_of_range_progress_is_clamped() {
+        for curve in CURVES {
+            assert_eq!(curve.progress(-1.0), 0.0);
+            assert_eq!(curve.progress(2.0), 1.0);
+            assert_eq!(curve.progress(f64::INFINITY), 1.0);
+            assert_eq!(curve.progress(f64::NAN), 1.0);
+        }
+    }
+
+    #[test]
+    fn blend_in_both_directions() {
+        assert_eq!(blend(0, 255, Curve::Linear, 0.5), 128);
+        assert_eq!(blend(255, 0, Curve::Linear, 0.5), 128);
+        assert_eq!(blend(89, 255, Curve::Ease, 0.0), 89);
+        assert_eq!(blend(89, 255, Curve::Ease, 1.0), 255);
+        assert_eq!(blend(89, 255, Curve::Ease, 0.25), 99);
+    }
+
+    #[test]
+    fn fades_follow_the_clock() {
+        let start = Instant::now();
+        let fade = Fade::new(255, 0, start);
+
+        assert_eq!(fade.alpha_at(start, ms(200), Curve::Linear), 255);
+        assert_eq!(fade.alpha_at(start + ms(100), ms(200), Curve::Linear), 128);
+        assert_eq!(fade.alpha_at(start + ms(200), ms(200), Curve::Linear), 0);
+        assert_eq!(fade.alpha_at(start + ms(500), ms(200), Curve::Ease), 0);
+
+        // A clock reading from before the start doesn't move the fade backwards
+        let later = Fade::new(0, 255, start + ms(50));
+        assert_eq!(later.alpha_at(start, ms(200), Curve::Linear), 0);
+
+        assert_eq!(fade.alpha_at(start, Duration::ZERO, Curve::Ease), 0);
+    }
+
+    #[test]
+    fn timelines_start_on_their_first_frame() {
+        let start = Instant::now();
+        let mut timeline = Timeline::new(0.0, 1.0, ms(200), Curve::Linear);
+        assert!(!timeline.is_finished());
+
+        // However late the first frame comes, it starts at the beginning
+        assert_eq!(timeline.advance(start + ms(1_000)), 0.0);
+        assert_eq!(timeline.advance(start + ms(1_100)), 0.5);
+        assert_eq!(timeline.advance(start + ms(1_200)), 1.0);
+        assert!(timeline.is_finished());
+    }
+
+    #[test]
+    fn shorter_moves_take_proportionally_less() {
+        let start = Instant::now();
+        let mut timeline = Timeline::new(0.75, 1.0, ms(200), Curve::Linear);
+        timeline.advance(start);
+        assert_eq!(timeline.advance(start + ms(25)), 0.875);
+        assert_eq!(timeline.advance(start + ms(50)), 1.0);
+    }
+
+    #[test]
+    fn retargeting_continues_from_the_last_frame() {
+        let start = Instant::now();
+        let mut timeline = Timeline::new(0.0, 1.0, ms(200), Curve::Linear);
+        timeline.advance(start);
+        assert_eq!(timeline.advance(start + ms(50)), 0.25);
+
+        timeline.retarget(0.0, ms(200));
+        assert_eq!(timeline.advance(start + ms(60)), 0.25);
+        assert_eq!(timeline.advance(start + ms(85)), 0.125);
+        assert_eq!(timeline.advance(start + ms(110)), 0.0);
+        assert!(timeline.is_finished());
+    }
+}
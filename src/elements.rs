@@ -0,0 +1,123 @@
+#[cfg(windows)]
+use crate::get_process_name;
+#[cfg(windows)]
+use crate::targets;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+#[cfg(windows)]
+use windows::core::PCWSTR;
+#[cfg(windows)]
+use windows::Win32::Foundation::HWND;
+#[cfg(windows)]
+use windows::Win32::UI::WindowsAndMessaging::{FindWindowExW, FindWindowW, IsWindowVisible};
+
+/// A part of the shell that can be hidden besides the taskbar itself
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum ShellElement {
+    /// The desktop icon view (SHELLDLL_DefView under Progman or WorkerW)
+    DesktopIcons,
+    /// The Start button of each taskbar (Windows 10 and classic taskbars)
+    StartButton,
+    /// The notification area of the primary taskbar (TrayNotifyWnd)
+    NotificationArea,
+    /// The widgets board (Widgets.exe windows)
+    Widgets,
+}
+
+impl ShellElement {
+    pub const ALL: [ShellElement; 4] = [
+        ShellElement::DesktopIcons,
+        ShellElement::StartButton,
+        ShellElement::NotificationArea,
+        ShellElement::Widgets,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ShellElement::DesktopIcons => "desktop-icons",
+            ShellElement::StartButton => "start-button",
+            ShellElement::NotificationArea => "notification-area",
+            ShellElement::Widgets => "widgets",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|element| element.as_str().eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// Find the windows that make up this element
+    #[cfg(windows)]
+    pub fn discover(&self) -> Vec<HWND> {
+        match self {
+            ShellElement::DesktopIcons => desktop_icon_view().into_iter().collect(),
+            ShellElement::StartButton => taskbar_children("Start"),
+            ShellElement::NotificationArea => taskbar_children("TrayNotifyWnd"),
+            ShellElement::Widgets => targets::enumerate_all_windows()
+                .into_iter()
+                .filter(|&hwnd| unsafe { IsWindowVisible(hwnd).as_bool() })
+                .filter(|&hwnd| {
+                    get_process_name(hwnd)
+                        .is_some_and(|name| name.eq_ignore_ascii_case("Widgets.exe"))
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(windows)]
+fn wide(text: &str) -> Vec<u16> {
+    format!("{}\0", text).encode_utf16().collect()
+}
+
+/// Find a direct child window by class name
+#[cfg(windows)]
+fn find_child(parent: HWND, class: &str) -> Option<HWND> {
+    let class = wide(class);
+    unsafe { FindWindowExW(parent, None, PCWSTR(class.as_ptr()), PCWSTR::null()) }
+        .ok()
+        .filter(|hwnd| !hwnd.0.is_null())
+}
+
+/// Find a child window of class `class` in every taskbar
+#[cfg(windows)]
+fn taskbar_children(class: &str) -> Vec<HWND> {
+    targets::find_taskbars()
+        .into_iter()
+        .filter_map(|taskbar| find_child(taskbar, class))
+        .collect()
+}
+
+/// The desktop icon view lives under Progman, or a WorkerW once a wallpaper slideshow ran
+#[cfg(windows)]
+fn desktop_icon_view() -> Option<HWND> {
+    unsafe {
+        let progman = wide("Progman");
+        if let Ok(progman) = FindWindowW(PCWSTR(progman.as_ptr()), PCWSTR::null()) {
+            if let Some(view) = find_child(progman, "SHELLDLL_DefView") {
+                return Some(view);
+            }
+        }
+
+        let worker_class = wide("WorkerW");
+        let mut worker = HWND(std::ptr::null_mut());
+        loop {
+            worker = FindWindowExW(
+                HWND(std::ptr::null_mut()),
+                worker,
+                PCWSTR(worker_class.as_ptr()),
+                PCWSTR::null(),
+            )
+            .ok()
+            .filter(|hwnd| !hwnd.0.is_null())?;
+
+            if let Some(view) = find_child(worker, "SHELLDLL_DefView") {
+                return Some(view);
+            }
+        }
+    }
+}
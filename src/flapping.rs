@@ -0,0 +1,298 @@
+use crate::i18n;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::time::{Duration, Instant};
+
+// Re-hides within FLAP_WINDOW that count as a fight with another program
+const FLAP_WINDOW: Duration = Duration::from_secs(10);
+const FLAP_THRESHOLD: usize = 20;
+const BASE_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// A taskbar that keeps reappearing after being hidden
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlapReport {
+    /// Device name of the monitor hosting the taskbar
+    pub monitor: String,
+    /// Re-hides counted when flapping was last detected
+    pub rehides: usize,
+    pub window_secs: u64,
+    /// Process most often in the foreground when the taskbar reappeared
+    pub culprit: Option<String>,
+    /// Current pause between re-hides
+    pub backoff_secs: u64,
+}
+
+impl FlapReport {
+    pub fn message(&self) -> String {
+        let rehides = self.rehides.to_string();
+        let seconds = self.window_secs.to_string();
+        let backoff = self.backoff_secs.to_string();
+        let mut args = vec![
+            ("monitor", self.monitor.as_str()),
+            ("rehides", rehides.as_str()),
+            ("seconds", seconds.as_str()),
+            ("backoff", backoff.as_str()),
+        ];
+
+        let key = match &self.culprit {
+            Some(process) => {
+                args.push(("process", process.as_str()));
+                "flapping-report-culprit"
+            }
+            None => "flapping-report",
+        };
+        i18n::text_with(key, &args)
+    }
+}
+
+#[derive(Debug, Default)]
+struct History {
+    rehides: VecDeque<Instant>,
+    culprits: BTreeMap<String, usize>,
+    /// Number of consecutive flapping detections, doubles the backoff each time
+    level: u32,
+    backoff: Duration,
+    backoff_until: Option<Instant>,
+    last_flap: Option<(Instant, usize)>,
+}
+
+/// Detects taskbars that another program keeps re-showing and backs off exponentially
+///
+/// Time is passed in by the caller, so the detector is deterministic for a given timeline.
+#[derive(Debug)]
+pub struct FlapDetector {
+    window: Duration,
+    threshold: usize,
+    base_backoff: Duration,
+    max_backoff: Duration,
+    taskbars: BTreeMap<String, History>,
+}
+
+impl Default for FlapDetector {
+    fn default() -> Self {
+        Self::new(FLAP_WINDOW, FLAP_THRESHOLD, BASE_BACKOFF, MAX_BACKOFF)
+    }
+}
+
+impl FlapDetector {
+    pub fn new(
+        window: Duration,
+        threshold: usize,
+        base_backoff: Duration,
+        max_backoff: Duration,
+    ) -> Self {
+        Self {
+            window,
+            threshold: threshold.max(1),
+            base_backoff,
+            max_backoff,
+            taskbars: BTreeMap::new(),
+        }
+    }
+
+    /// A quiet period this long after the last detection forgets the flapping
+    fn cooldown(&self) -> Duration {
+        self.window + self.max_backoff
+    }
+
+    /// Whether the taskbar may be re-hidden now, false while backing off
+    pub fn may_rehide(&self, taskbar: &str, now: Instant) -> bool {
+        self.taskbars
+            .get(taskbar)
+            .and_then(|history| history.backoff_until)
+            .is_none_or(|until| now >= until)
+    }
+
+    /// Record a re-hide, returns the backoff to apply if the taskbar is flapping
+    pub fn record_rehide(
+        &mut self,
+        taskbar: &str,
+        culprit: Option<&str>,
+        now: Instant,
+    ) -> Option<Duration> {
+        let (window, threshold, cooldown) = (self.window, self.threshold, self.cooldown());
+        let (base_backoff, max_backoff) = (self.base_backoff, self.max_backoff);
+        let history = self.taskbars.entry(taskbar.to_string()).or_default();
+
+        if history
+            .last_flap
+            .is_some_and(|(at, _)| now.saturating_duration_since(at) >= cooldown)
+        {
+            *history = History::default();
+        }
+
+        history.rehides.push_back(now);
+        while history
+            .rehides
+            .front()
+            .is_some_and(|&at| now.saturating_duration_since(at) > window)
+        {
+            history.rehides.pop_front();
+        }
+        if let Some(culprit) = culprit {
+            *history.culprits.entry(culprit.to_string()).or_default() += 1;
+        }
+
+        if history.rehides.len() < threshold {
+            return None;
+        }
+
+        let backoff = base_backoff
+            .saturating_mul(2u32.saturating_pow(history.level))
+            .min(max_backoff);
+        history.level = history.level.saturating_add(1);
+        history.backoff = backoff;
+        history.backoff_until = Some(now + backoff);
+        history.last_flap = Some((now, history.rehides.len()));
+        history.rehides.clear();
+
+        Some(backoff)
+    }
+
+    /// Taskbars that flapped recently, for the tooltip, log and status
+    pub fn reports(&self, now: Instant) -> Vec<FlapReport> {
+        self.taskbars
+            .iter()
+            .filter_map(|(monitor, history)| {
+                let (at, rehides) = history.last_flap?;
+                if now.saturating_duration_since(at) >= self.cooldown() {
+                    return None;
+                }
+
+                let culprit = history
+                    .culprits
+                    .iter()
+                    .max_by_key(|(_, count)| **count)
+                    .map(|(process, _)| process.clone());
+
+                Some(FlapReport {
+                    monitor: monitor.clone(),
+                    rehides,
+                    window_secs: self.window.as_secs(),
+                    culprit,
+                    backoff_secs: history.backoff.as_secs(),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRIMARY: &str = "\\\\.\\DISPLAY1";
+    const SECONDARY: &str = "\\\\.\\DISPLAY2";
+
+    /// A detector flapping after 5 re-hides in 10s, backing off from 1s up to 8s
+    fn detector() -> FlapDetector {
+        FlapDetector::new(
+            Duration::from_secs(10),
+            5,
+            Duration::from_secs(1),
+            Duration::from_secs(8),
+        )
+    }
+
+    /// Re-hide every `interval_ms` while allowed until `until_ms`, returns the backoffs and when
+    fn timeline(
+        detector: &mut FlapDetector,
+        start: Instant,
+        from_ms: u64,
+        until_ms: u64,
+        interval_ms: u64,
+        culprit: Option<&str>,
+    ) -> Vec<(u64, u64)> {
+        let mut backoffs = Vec::new();
+        for ms in (from_ms..until_ms).step_by(interval_ms as usize) {
+            let now = start + Duration::from_millis(ms);
+            if !detector.may_rehide(PRIMARY, now) {
+                continue;
+            }
+            if let Some(backoff) = detector.record_rehide(PRIMARY, culprit, now) {
+                backoffs.push((ms, backoff.as_secs()));
+            }
+        }
+        backoffs
+    }
+
+    #[test]
+    fn occasional_rehides_are_not_flapping() {
+        let (mut detector, start) = (detector(), Instant::now());
+        // One every 3s never reaches 5 within 10s
+        assert_eq!(timeline(&mut detector, start, 0, 120_000, 3_000, None), []);
+        assert!(detector
+            .reports(start + Duration::from_secs(120))
+            .is_empty());
+    }
+
+    #[test]
+    fn fights_back_off_exponentially_up_to_the_maximum() {
+        let (mut detector, start) = (detector(), Instant::now());
+        let backoffs = timeline(&mut detector, start, 0, 60_000, 100, Some("fighter.exe"));
+
+        let secs: Vec<u64> = backoffs.iter().map(|&(_, secs)| secs).collect();
+        assert_eq!(secs[..5], [1, 2, 4, 8, 8]);
+        // Each detection needs five fresh re-hides once the backoff is over
+        assert_eq!(backoffs[0].0, 400);
+        assert_eq!(backoffs[1].0, 400 + 1_000 + 400);
+    }
+
+    #[test]
+    fn backoff_blocks_rehides_until_it_ends() {
+        let (mut detector, start) = (detector(), Instant::now());
+        timeline(&mut detector, start, 0, 500, 100, None);
+
+        let flapped = start + Duration::from_millis(400);
+        assert!(!detector.may_rehide(PRIMARY, flapped));
+        assert!(!detector.may_rehide(PRIMARY, flapped + Duration::from_millis(999)));
+        assert!(detector.may_rehide(PRIMARY, flapped + Duration::from_secs(1)));
+        assert!(detector.may_rehide(SECONDARY, flapped));
+    }
+
+    #[test]
+    fn quiet_period_forgets_the_flapping() {
+        let (mut detector, start) = (detector(), Instant::now());
+        let first = timeline(&mut detector, start, 0, 5_000, 100, None);
+        assert_eq!(first.last(), Some(&(4_200, 4)));
+
+        // Cooldown is the window plus the maximum backoff, 18s after the last detection
+        let later = 4_200 + 18_000;
+        assert!(detector
+            .reports(start + Duration::from_millis(later))
+            .is_empty());
+        let again = timeline(&mut detector, start, later, later + 500, 100, None);
+        assert_eq!(again, [(later + 400, 1)]);
+    }
+
+    #[test]
+    fn reports_name_the_most_frequent_culprit() {
+        let (mut detector, start) = (detector(), Instant::now());
+        let culprits = [
+            Some("a.exe"),
+            Some("b.exe"),
+            None,
+            Some("a.exe"),
+            Some("a.exe"),
+        ];
+        for (i, culprit) in culprits.into_iter().enumerate() {
+            let now = start + Duration::from_millis(i as u64 * 100);
+            detector.record_rehide(PRIMARY, culprit, now);
+        }
+        detector.record_rehide(SECONDARY, Some("c.exe"), start);
+
+        let reports = detector.reports(start + Duration::from_secs(2));
+        assert_eq!(
+            reports,
+            [FlapReport {
+                monitor: PRIMARY.to_string(),
+                rehides: 5,
+                window_secs: 10,
+                culprit: Some("a.exe".to_string()),
+                backoff_secs: 1,
+            }]
+        );
+        assert!(detector.reports(start + Duration::from_secs(60)).is_empty());
+    }
+}
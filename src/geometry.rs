@@ -0,0 +1,394 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// DPI at 100% scaling, one DIP is one pixel
+pub const DEFAULT_DPI: u32 = 96;
+
+/// Screen rectangle in physical pixels, right and bottom are exclusive
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rect {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+impl Rect {
+    pub fn new(left: i32, top: i32, right: i32, bottom: i32) -> Self {
+        Self {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        (self.right - self.left).max(0)
+    }
+
+    pub fn height(&self) -> i32 {
+        (self.bottom - self.top).max(0)
+    }
+
+    /// Whether `other` lies entirely within the rect
+    pub fn contains(&self, other: Rect) -> bool {
+        other.left >= self.left
+            && other.top >= self.top
+            && other.right <= self.right
+            && other.bottom <= self.bottom
+    }
+
+    /// Whether the point lies within the rect
+    pub fn contains_point(&self, x: i32, y: i32) -> bool {
+        x >= self.left && x < self.right && y >= self.top && y < self.bottom
+    }
+
+    /// The strip of `thickness` pixels along an edge, clamped to the rect
+    pub fn strip(&self, edge: Edge, thickness: i32) -> Rect {
+        let thickness = thickness.clamp(0, self.extent(edge));
+
+        match edge {
+            Edge::Top => Rect::new(self.left, self.top, self.right, self.top + thickness),
+            Edge::Bottom => Rect::new(self.left, self.bottom - thickness, self.right, self.bottom),
+            Edge::Left => Rect::new(self.left, self.top, self.left + thickness, self.bottom),
+            Edge::Right => Rect::new(self.right - thickness, self.top, self.right, self.bottom),
+        }
+    }
+
+    /// What remains after removing the strip of `thickness` pixels along an edge
+    pub fn without_strip(&self, edge: Edge, thickness: i32) -> Rect {
+        let thickness = thickness.clamp(0, self.extent(edge));
+        let mut rect = *self;
+
+        match edge {
+            Edge::Top => rect.top += thickness,
+            Edge::Bottom => rect.bottom -= thickness,
+            Edge::Left => rect.left += thickness,
+            Edge::Right => rect.right -= thickness,
+        }
+
+        rect
+    }
+
+    /// Resize the rect to `thickness` pixels, keeping the side along `edge` in place
+    ///
+    /// Used after ABM_QUERYPOS, which may move the outer side to make room for other AppBars.
+    pub fn anchored(&self, edge: Edge, thickness: i32) -> Rect {
+        let mut rect = *self;
+
+        match edge {
+            Edge::Top => rect.bottom = rect.top + thickness,
+            Edge::Bottom => rect.top = rect.bottom - thickness,
+            Edge::Left => rect.right = rect.left + thickness,
+            Edge::Right => rect.left = rect.right - thickness,
+        }
+
+        rect
+    }
+
+    /// The rect moved `distance` pixels towards an edge, e.g. downwards for the bottom edge
+    pub fn slid(&self, edge: Edge, distance: i32) -> Rect {
+        let (dx, dy) = match edge {
+            Edge::Top => (0, -distance),
+            Edge::Bottom => (0, distance),
+            Edge::Left => (-distance, 0),
+            Edge::Right => (distance, 0),
+        };
+
+        Rect::new(
+            self.left + dx,
+            self.top + dy,
+            self.right + dx,
+            self.bottom + dy,
+        )
+    }
+
+    /// Size of the rect perpendicular to an edge
+    pub fn extent(&self, edge: Edge) -> i32 {
+        match edge {
+            Edge::Top | Edge::Bottom => self.height(),
+            Edge::Left | Edge::Right => self.width(),
+        }
+    }
+}
+
+/// Screen edge of a monitor
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Edge {
+    #[default]
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+/// Convert device independent pixels to physical pixels at the given DPI
+pub fn dips_to_pixels(dips: u32, dpi: u32) -> i32 {
+    let dpi = if dpi == 0 { DEFAULT_DPI } else { dpi };
+    let pixels = (dips as u64 * dpi as u64 + DEFAULT_DPI as u64 / 2) / DEFAULT_DPI as u64;
+    pixels.min(i32::MAX as u64) as i32
+}
+
+/// Whether a monitor selector matches a monitor
+///
+/// Selectors are a 1-based monitor number or a device name, with or without the \\.\ prefix.
+pub fn monitor_matches(selector: &str, name: &str, index: usize) -> bool {
+    let selector = selector.trim();
+    if let Ok(number) = selector.parse::<usize>() {
+        return number == index;
+    }
+
+    name.eq_ignore_ascii_case(selector)
+        || name
+            .trim_start_matches("\\\\.\\")
+            .eq_ignore_ascii_case(selector.trim_start_matches("\\\\.\\"))
+}
+
+/// A strip along a monitor edge kept free for a custom bar
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReservedStrip {
+    pub edge: Edge,
+    /// Thickness in DIPs, scaled by each monitor's DPI
+    pub thickness: u32,
+    /// Monitor numbers or device names, all monitors when empty
+    pub monitors: Vec<String>,
+}
+
+impl ReservedStrip {
+    pub fn applies_to(&self, name: &str, index: usize) -> bool {
+        self.monitors.is_empty()
+            || self
+                .monitors
+                .iter()
+                .any(|selector| monitor_matches(selector, name, index))
+    }
+
+    /// Thickness in physical pixels on a monitor with the given DPI
+    pub fn pixels(&self, dpi: u32) -> i32 {
+        dips_to_pixels(self.thickness, dpi)
+    }
+}
+
+/// Initial AppBar rect proposed to ABM_QUERYPOS for a reserved strip on a monitor
+pub fn appbar_proposal(monitor: Rect, dpi: u32, strip: &ReservedStrip) -> Rect {
+    monitor.strip(strip.edge, strip.pixels(dpi))
+}
+
+/// Final AppBar rect from the one returned by ABM_QUERYPOS
+pub fn appbar_position(queried: Rect, dpi: u32, strip: &ReservedStrip) -> Rect {
+    queried.anchored(strip.edge, strip.pixels(dpi))
+}
+
+/// Edge of the monitor a bar is docked to, judged by its orientation and position
+pub fn docked_edge(bar: Rect, monitor: Rect) -> Edge {
+    // Comparing doubled centers avoids rounding
+    if bar.width() >= bar.height() {
+        if bar.top + bar.bottom >= monitor.top + monitor.bottom {
+            Edge::Bottom
+        } else {
+            Edge::Top
+        }
+    } else if bar.left + bar.right >= monitor.left + monitor.right {
+        Edge::Right
+    } else {
+        Edge::Left
+    }
+}
+
+/// Work area of a monitor whose taskbar is hidden: the full monitor minus reserved strips
+pub fn reclaimed_work_area(
+    monitor: Rect,
+    dpi: u32,
+    name: &str,
+    index: usize,
+    strips: &[ReservedStrip],
+) -> Rect {
+    strips
+        .iter()
+        .filter(|strip| strip.applies_to(name, index))
+        .fold(monitor, |area, strip| {
+            area.without_strip(strip.edge, strip.pixels(dpi))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MONITOR: Rect = Rect {
+        left: 0,
+        top: 0,
+        right: 1920,
+        bottom: 1080,
+    };
+
+    fn strip(edge: Edge, thickness: u32, monitors: &[&str]) -> ReservedStrip {
+        ReservedStrip {
+            edge,
+            thickness,
+            monitors: monitors.iter().map(|m| m.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn strips_follow_their_edge() {
+        assert_eq!(MONITOR.strip(Edge::Top, 30), Rect::new(0, 0, 1920, 30));
+        assert_eq!(
+            MONITOR.strip(Edge::Bottom, 48),
+            Rect::new(0, 1032, 1920, 1080)
+        );
+        assert_eq!(MONITOR.strip(Edge::Left, 10), Rect::new(0, 0, 10, 1080));
+        assert_eq!(
+            MONITOR.strip(Edge::Right, 10),
+            Rect::new(1910, 0, 1920, 1080)
+        );
+    }
+
+    #[test]
+    fn strips_are_clamped_to_the_rect() {
+        assert_eq!(MONITOR.strip(Edge::Top, 5000), MONITOR);
+        assert_eq!(MONITOR.strip(Edge::Left, -5), Rect::new(0, 0, 0, 1080));
+        assert_eq!(
+            MONITOR.without_strip(Edge::Bottom, 5000),
+            Rect::new(0, 0, 1920, 0)
+        );
+    }
+
+    #[test]
+    fn strip_and_remainder_make_up_the_rect() {
+        for edge in [Edge::Top, Edge::Bottom, Edge::Left, Edge::Right] {
+            let strip = MONITOR.strip(edge, 40);
+            let rest = MONITOR.without_strip(edge, 40);
+            assert_eq!(strip.extent(edge) + rest.extent(edge), MONITOR.extent(edge));
+            assert!(MONITOR.contains(strip) && MONITOR.contains(rest));
+        }
+    }
+
+    #[test]
+    fn dips_scale_with_dpi() {
+        assert_eq!(dips_to_pixels(32, 96), 32);
+        assert_eq!(dips_to_pixels(32, 144), 48);
+        assert_eq!(dips_to_pixels(32, 120), 40);
+        // 10 DIPs at 125% is 12.5 pixels, rounded
+        assert_eq!(dips_to_pixels(10, 120), 13);
+        assert_eq!(dips_to_pixels(32, 0), 32);
+        assert_eq!(dips_to_pixels(u32::MAX, 960), i32::MAX);
+    }
+
+    #[test]
+    fn monitors_match_by_number_or_name() {
+        let name = "\\\\.\\DISPLAY2";
+        assert!(monitor_matches("2", name, 2));
+        assert!(monitor_matches(" 2 ", name, 2));
+        assert!(!monitor_matches("1", name, 2));
+        assert!(monitor_matches("\\\\.\\DISPLAY2", name, 2));
+        assert!(monitor_matches("display2", name, 2));
+        assert!(!monitor_matches("DISPLAY1", name, 2));
+    }
+
+    #[test]
+    fn reclaimed_work_area_is_the_monitor_without_reserved_strips() {
+        let name = "\\\\.\\DISPLAY1";
+        assert_eq!(reclaimed_work_area(MONITOR, 96, name, 1, &[]), MONITOR);
+
+        let strips = [
+            strip(Edge::Top, 32, &[]),
+            strip(Edge::Left, 20, &["1"]),
+            strip(Edge::Right, 20, &["2"]),
+        ];
+        assert_eq!(
+            reclaimed_work_area(MONITOR, 96, name, 1, &strips),
+            Rect::new(20, 32, 1920, 1080)
+        );
+        assert_eq!(
+            reclaimed_work_area(MONITOR, 144, name, 1, &strips),
+            Rect::new(30, 48, 1920, 1080)
+        );
+    }
+
+    #[test]
+    fn appbar_proposal_is_the_scaled_strip_along_the_edge() {
+        let top = strip(Edge::Top, 32, &[]);
+        assert_eq!(
+            appbar_proposal(MONITOR, 96, &top),
+            Rect::new(0, 0, 1920, 32)
+        );
+        assert_eq!(
+            appbar_proposal(MONITOR, 192, &top),
+            Rect::new(0, 0, 1920, 64)
+        );
+
+        let second = Rect::new(1920, 0, 3840, 1080);
+        let right = strip(Edge::Right, 40, &[]);
+        assert_eq!(
+            appbar_proposal(second, 120, &right),
+            Rect::new(3790, 0, 3840, 1080)
+        );
+    }
+
+    #[test]
+    fn appbar_position_keeps_the_thickness_after_querypos() {
+        // Another AppBar already takes the top 40 pixels, the shell moves ours below it
+        let top = strip(Edge::Top, 32, &[]);
+        let queried = Rect::new(0, 40, 1920, 32);
+        assert_eq!(
+            appbar_position(queried, 96, &top),
+            Rect::new(0, 40, 1920, 72)
+        );
+
+        // The taskbar at the bottom pushes a bottom strip up and the shell shrinks it
+        let bottom = strip(Edge::Bottom, 32, &[]);
+        let queried = Rect::new(0, 1016, 1920, 1032);
+        assert_eq!(
+            appbar_position(queried, 96, &bottom),
+            Rect::new(0, 1000, 1920, 1032)
+        );
+
+        let left = strip(Edge::Left, 24, &[]);
+        let queried = Rect::new(60, 0, 84, 1080);
+        assert_eq!(
+            appbar_position(queried, 144, &left),
+            Rect::new(60, 0, 96, 1080)
+        );
+    }
+
+    #[test]
+    fn strips_apply_to_the_selected_monitors() {
+        let everywhere = strip(Edge::Top, 32, &[]);
+        assert!(everywhere.applies_to("\\\\.\\DISPLAY3", 3));
+
+        let selected = strip(Edge::Top, 32, &["1", "DISPLAY3"]);
+        assert!(selected.applies_to("\\\\.\\DISPLAY1", 1));
+        assert!(!selected.applies_to("\\\\.\\DISPLAY2", 2));
+        assert!(selected.applies_to("\\\\.\\DISPLAY3", 3));
+    }
+
+    #[test]
+    fn bars_are_docked_to_the_nearest_edge() {
+        assert_eq!(
+            docked_edge(MONITOR.strip(Edge::Top, 48), MONITOR),
+            Edge::Top
+        );
+        assert_eq!(
+            docked_edge(MONITOR.strip(Edge::Bottom, 48), MONITOR),
+            Edge::Bottom
+        );
+        assert_eq!(
+            docked_edge(MONITOR.strip(Edge::Left, 48), MONITOR),
+            Edge::Left
+        );
+        assert_eq!(
+            docked_edge(MONITOR.strip(Edge::Right, 48), MONITOR),
+            Edge::Right
+        );
+
+        let second = Rect::new(1920, 0, 3840, 1080);
+        assert_eq!(
+            docked_edge(second.strip(Edge::Bottom, 48), second),
+            Edge::Bottom
+        );
+    }
+}
@@ -0,0 +1,327 @@
+use crate::config::HooksConfig;
+use crate::controller::{Transition, TransitionKind};
+use std::io::Read;
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+const HOOK_POLL_INTERVAL_MS: u64 = 50;
+
+// Passed to every hook command
+pub const EVENT_ENV_VAR: &str = "THIDE_EVENT";
+pub const STATE_ENV_VAR: &str = "THIDE_STATE";
+pub const PREVIOUS_STATE_ENV_VAR: &str = "THIDE_PREVIOUS_STATE";
+pub const MONITORS_ENV_VAR: &str = "THIDE_MONITORS";
+pub const MONITOR_COUNT_ENV_VAR: &str = "THIDE_MONITOR_COUNT";
+pub const PID_ENV_VAR: &str = "THIDE_PID";
+
+/// Runs a hook command line to completion within the timeout
+type Execute = dyn Fn(&str, &Transition, Duration) + Send + Sync;
+
+/// Hook commands not finished yet
+#[derive(Debug, Default)]
+struct Counts {
+    /// Dispatched, running or waiting for a slot
+    pending: usize,
+    running: usize,
+}
+
+/// Runs user hook commands on controller transitions
+#[derive(Clone)]
+pub struct HookRunner {
+    config: Arc<HooksConfig>,
+    counts: Arc<(Mutex<Counts>, Condvar)>,
+    execute: Arc<Execute>,
+}
+
+impl HookRunner {
+    pub fn new(config: HooksConfig) -> Self {
+        Self::with_executor(config, run)
+    }
+
+    /// Run the hook commands with `execute` instead of the shell
+    fn with_executor(
+        config: HooksConfig,
+        execute: impl Fn(&str, &Transition, Duration) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            config: Arc::new(config),
+            counts: Arc::new((Mutex::new(Counts::default()), Condvar::new())),
+            execute: Arc::new(execute),
+        }
+    }
+
+    /// Hook command lines configured for a kind of transition
+    pub fn commands(&self, kind: TransitionKind) -> &[String] {
+        match kind {
+            TransitionKind::Start => &self.config.on_start,
+            TransitionKind::Hide => &self.config.on_hide,
+            TransitionKind::Show => &self.config.on_show,
+            TransitionKind::Quit => &self.config.on_quit,
+            TransitionKind::ExplorerRestart => &self.config.on_explorer_restart,
+        }
+    }
+
+    /// Spawn the hook commands configured for a transition
+    pub fn dispatch(&self, transition: &Transition) {
+        let timeout = Duration::from_millis(self.config.timeout_ms);
+        for command_line in self.commands(transition.kind) {
+            // Counted before the thread starts, so a wait_idle right after sees it
+            self.counts().pending += 1;
+
+            let runner = self.clone();
+            let command_line = command_line.clone();
+            let transition = transition.clone();
+
+            std::thread::spawn(move || {
+                runner.acquire();
+                (runner.execute)(&command_line, &transition, timeout);
+                runner.release();
+            });
+        }
+    }
+
+    /// Block until all dispatched hooks have finished, or the timeout elapses
+    pub fn wait_idle(&self, timeout: Duration) {
+        let (lock, cvar) = &*self.counts;
+        let guard = lock.lock().unwrap_or_else(PoisonError::into_inner);
+        let _ = cvar.wait_timeout_while(guard, timeout, |counts| counts.pending > 0);
+    }
+
+    fn counts(&self) -> MutexGuard<'_, Counts> {
+        self.counts.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Wait for one of the max_concurrent slots
+    fn acquire(&self) {
+        let (lock, cvar) = &*self.counts;
+        let max_concurrent = self.config.max_concurrent.max(1);
+        let guard = lock.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut counts = cvar
+            .wait_while(guard, |counts| counts.running >= max_concurrent)
+            .unwrap_or_else(PoisonError::into_inner);
+        counts.running += 1;
+    }
+
+    fn release(&self) {
+        let mut counts = self.counts();
+        counts.running = counts.running.saturating_sub(1);
+        counts.pending = counts.pending.saturating_sub(1);
+        self.counts.1.notify_all();
+    }
+}
+
+/// Run a single hook command through the shell, killing it once the timeout elapses
+fn run(command_line: &str, transition: &Transition, timeout: Duration) {
+    let event = transition.kind.as_str();
+
+    let mut child = match shell(command_line)
+        .env(EVENT_ENV_VAR, event)
+        .env(STATE_ENV_VAR, state_name(transition.hidden))
+        .env(PREVIOUS_STATE_ENV_VAR, state_name(transition.was_hidden))
+        .env(MONITORS_ENV_VAR, transition.monitors.join(";"))
+        .env(MONITOR_COUNT_ENV_VAR, transition.monitors.len().to_string())
+        .env(PID_ENV_VAR, std::process::id().to_string())
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            log::error!("Hook {} failed to start `{}`: {}", event, command_line, e);
+            return;
+        }
+    };
+
+    let stdout = child.stdout.take().map(read_pipe);
+    let stderr = child.stderr.take().map(read_pipe);
+
+    let status = wait_with_timeout(&mut child, timeout);
+
+    match status {
+        Some(status) if status.success() => {
+            log::info!("Hook {} `{}` finished", event, command_line)
+        }
+        Some(status) => log::warn!("Hook {} `{}` exited with {}", event, command_line, status),
+        None => {
+            // Don't wait for the pipes, a grandchild process may still hold them open
+            log::warn!(
+                "Hook {} `{}` timed out after {} ms and was killed",
+                event,
+                command_line,
+                timeout.as_millis()
+            );
+            return;
+        }
+    }
+
+    for (name, pipe) in [("stdout", stdout), ("stderr", stderr)] {
+        let output = pipe
+            .and_then(|handle| handle.join().ok())
+            .unwrap_or_default();
+        for line in output.lines().filter(|line| !line.trim().is_empty()) {
+            log::info!("Hook {} {}: {}", event, name, line);
+        }
+    }
+}
+
+/// Command running a hook command line through the shell, without a console window
+#[cfg(windows)]
+fn shell(command_line: &str) -> Command {
+    let mut command = Command::new("cmd");
+    command
+        .arg("/C")
+        .raw_arg(command_line)
+        .creation_flags(CREATE_NO_WINDOW);
+    command
+}
+
+#[cfg(not(windows))]
+fn shell(command_line: &str) -> Command {
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(command_line);
+    command
+}
+
+fn state_name(hidden: bool) -> &'static str {
+    if hidden {
+        "hidden"
+    } else {
+        "visible"
+    }
+}
+
+/// Read a child pipe to the end on a separate thread
+fn read_pipe(mut pipe: impl Read + Send + 'static) -> std::thread::JoinHandle<String> {
+    std::thread::spawn(move || {
+        let mut buffer = Vec::new();
+        let _ = pipe.read_to_end(&mut buffer);
+        String::from_utf8_lossy(&buffer).into_owned()
+    })
+}
+
+/// Wait for the child to exit, killing it if it runs past the timeout
+fn wait_with_timeout(child: &mut Child, timeout: Duration) -> Option<std::process::ExitStatus> {
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return Some(status),
+            Ok(None) if Instant::now() < deadline => {
+                std::thread::sleep(Duration::from_millis(HOOK_POLL_INTERVAL_MS));
+            }
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::TransitionKind;
+
+    fn transition(kind: TransitionKind) -> Transition {
+        Transition {
+            kind,
+            hidden: false,
+            was_hidden: true,
+            monitors: vec!["\\\\.\\DISPLAY1".to_string()],
+        }
+    }
+
+    /// A runner whose hooks take `duration` and are recorded once finished
+    fn fake(config: HooksConfig, duration: Duration) -> (HookRunner, Arc<Mutex<Vec<String>>>) {
+        let finished = Arc::new(Mutex::new(Vec::new()));
+        let record = finished.clone();
+        let runner = HookRunner::with_executor(config, move |command_line, transition, _| {
+            std::thread::sleep(duration);
+            record
+                .lock()
+                .unwrap()
+                .push(format!("{} {}", transition.kind.as_str(), command_line));
+        });
+        (runner, finished)
+    }
+
+    #[test]
+    fn quit_waits_for_the_quit_hooks() {
+        let config = HooksConfig {
+            on_quit: vec!["first".to_string(), "second".to_string()],
+            on_show: vec!["ignored".to_string()],
+            ..HooksConfig::default()
+        };
+        let (runner, finished) = fake(config, Duration::from_millis(100));
+
+        runner.dispatch(&transition(TransitionKind::Quit));
+        runner.wait_idle(Duration::from_secs(10));
+
+        let mut finished = finished.lock().unwrap().clone();
+        finished.sort();
+        assert_eq!(finished, ["quit first", "quit second"]);
+    }
+
+    #[test]
+    fn waiting_hooks_count_as_pending() {
+        let config = HooksConfig {
+            on_quit: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            max_concurrent: 1,
+            ..HooksConfig::default()
+        };
+        let (runner, finished) = fake(config, Duration::from_millis(50));
+
+        runner.dispatch(&transition(TransitionKind::Quit));
+        runner.wait_idle(Duration::from_secs(10));
+
+        assert_eq!(finished.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn max_concurrent_limits_running_hooks() {
+        let config = HooksConfig {
+            on_hide: (0..6).map(|i| i.to_string()).collect(),
+            max_concurrent: 2,
+            ..HooksConfig::default()
+        };
+        let running = Arc::new(Mutex::new((0, 0)));
+        let counter = running.clone();
+        let runner = HookRunner::with_executor(config, move |_, _, _| {
+            {
+                let mut running = counter.lock().unwrap();
+                running.0 += 1;
+                running.1 = running.1.max(running.0);
+            }
+            std::thread::sleep(Duration::from_millis(30));
+            counter.lock().unwrap().0 -= 1;
+        });
+
+        runner.dispatch(&transition(TransitionKind::Hide));
+        runner.wait_idle(Duration::from_secs(10));
+
+        assert_eq!(*running.lock().unwrap(), (0, 2));
+    }
+
+    #[test]
+    fn wait_idle_gives_up_after_the_timeout() {
+        let config = HooksConfig {
+            on_quit: vec!["stuck".to_string()],
+            ..HooksConfig::default()
+        };
+        let (runner, finished) = fake(config, Duration::from_secs(5));
+
+        let started = Instant::now();
+        runner.dispatch(&transition(TransitionKind::Quit));
+        runner.wait_idle(Duration::from_millis(50));
+
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert!(finished.lock().unwrap().is_empty());
+    }
+}
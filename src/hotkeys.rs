@@ -0,0 +1,181 @@
+#[cfg(windows)]
+use std::collections::BTreeMap;
+#[cfg(windows)]
+use std::sync::atomic::{AtomicIsize, Ordering};
+#[cfg(windows)]
+use std::sync::Mutex;
+#[cfg(windows)]
+use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
+#[cfg(windows)]
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    RegisterHotKey, UnregisterHotKey, HOT_KEY_MODIFIERS,
+};
+#[cfg(windows)]
+use windows::Win32::UI::WindowsAndMessaging::{PostMessageW, WM_APP};
+
+/// Posted to the IPC window to re-register the pending hotkey bindings
+#[cfg(windows)]
+pub const WM_THIDE_HOTKEYS: u32 = WM_APP + 10;
+
+// RegisterHotKey modifier flags, kept here so parsing works without Win32
+const MOD_ALT: u32 = 0x0001;
+const MOD_CONTROL: u32 = 0x0002;
+const MOD_SHIFT: u32 = 0x0004;
+const MOD_WIN: u32 = 0x0008;
+const MOD_NOREPEAT: u32 = 0x4000;
+
+/// Action triggered by a global hotkey
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HotkeyAction {
+    Show,
+    Hide,
+    Toggle,
+    Quit,
+    Profile(String),
+}
+
+impl HotkeyAction {
+    /// Parse an action name: show, hide, toggle, quit or profile:<name>
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        if let Some(name) = text.strip_prefix("profile:") {
+            return Ok(HotkeyAction::Profile(name.trim().to_string()));
+        }
+
+        match text.to_lowercase().as_str() {
+            "show" => Ok(HotkeyAction::Show),
+            "hide" => Ok(HotkeyAction::Hide),
+            "toggle" => Ok(HotkeyAction::Toggle),
+            "quit" => Ok(HotkeyAction::Quit),
+            _ => Err(format!("unknown hotkey action `{}`", text)),
+        }
+    }
+}
+
+// Actions indexed by hotkey id, only touched on the IPC window thread
+#[cfg(windows)]
+static REGISTERED: Mutex<Vec<HotkeyAction>> = Mutex::new(Vec::new());
+// Bindings waiting to be registered by the IPC window thread
+#[cfg(windows)]
+static PENDING: Mutex<Option<BTreeMap<String, String>>> = Mutex::new(None);
+#[cfg(windows)]
+static IPC_WINDOW: AtomicIsize = AtomicIsize::new(0);
+
+/// Parse a key combination such as "Ctrl+Alt+T" or "Win+Shift+F9" into modifiers and virtual key
+pub fn parse_hotkey(text: &str) -> Result<(u32, u32), String> {
+    let mut modifiers = MOD_NOREPEAT;
+    let mut key = None;
+
+    for part in text.split('+').map(str::trim) {
+        match part.to_lowercase().as_str() {
+            "ctrl" | "control" => modifiers |= MOD_CONTROL,
+            "alt" => modifiers |= MOD_ALT,
+            "shift" => modifiers |= MOD_SHIFT,
+            "win" | "super" => modifiers |= MOD_WIN,
+            name => {
+                if key.is_some() {
+                    return Err(format!("`{}` has more than one key", text));
+                }
+                key = Some(virtual_key(name).ok_or_else(|| format!("unknown key `{}`", part))?);
+            }
+        }
+    }
+
+    key.map(|vk| (modifiers, vk))
+        .ok_or_else(|| format!("`{}` has no key", text))
+}
+
+/// Map a key name to its virtual-key code
+fn virtual_key(name: &str) -> Option<u32> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        if c.is_ascii_alphanumeric() {
+            return Some(c.to_ascii_uppercase() as u32);
+        }
+    }
+
+    if let Some(n) = name.strip_prefix('f').and_then(|n| n.parse::<u32>().ok()) {
+        if (1..=24).contains(&n) {
+            return Some(0x70 + n - 1);
+        }
+    }
+
+    match name {
+        "space" => Some(0x20),
+        "enter" | "return" => Some(0x0D),
+        "tab" => Some(0x09),
+        "esc" | "escape" => Some(0x1B),
+        "backspace" => Some(0x08),
+        "insert" => Some(0x2D),
+        "delete" => Some(0x2E),
+        "home" => Some(0x24),
+        "end" => Some(0x23),
+        "pageup" => Some(0x21),
+        "pagedown" => Some(0x22),
+        "left" => Some(0x25),
+        "up" => Some(0x26),
+        "right" => Some(0x27),
+        "down" => Some(0x28),
+        _ => None,
+    }
+}
+
+/// Replace the active hotkey bindings, safe to call from any thread
+#[cfg(windows)]
+pub fn request(bindings: BTreeMap<String, String>) {
+    if let Ok(mut pending) = PENDING.lock() {
+        pending.replace(bindings);
+    }
+
+    let hwnd = IPC_WINDOW.load(Ordering::SeqCst);
+    if hwnd != 0 {
+        unsafe {
+            let _ = PostMessageW(HWND(hwnd as *mut _), WM_THIDE_HOTKEYS, WPARAM(0), LPARAM(0));
+        }
+    }
+}
+
+/// Register the pending bindings on the IPC window, must run on its thread
+#[cfg(windows)]
+pub fn register_pending(hwnd: HWND) {
+    IPC_WINDOW.store(hwnd.0 as isize, Ordering::SeqCst);
+
+    let Some(bindings) = PENDING.lock().ok().and_then(|mut pending| pending.take()) else {
+        return;
+    };
+    let Ok(mut registered) = REGISTERED.lock() else {
+        return;
+    };
+
+    unsafe {
+        for id in 0..registered.len() {
+            let _ = UnregisterHotKey(hwnd, id as i32);
+        }
+    }
+    registered.clear();
+
+    for (combination, action) in bindings {
+        let parsed = parse_hotkey(&combination)
+            .and_then(|(modifiers, vk)| Ok((modifiers, vk, HotkeyAction::parse(&action)?)));
+
+        let (modifiers, vk, action) = match parsed {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                log::warn!("Ignoring hotkey {}: {}", combination, e);
+                continue;
+            }
+        };
+
+        let id = registered.len() as i32;
+        match unsafe { RegisterHotKey(hwnd, id, HOT_KEY_MODIFIERS(modifiers), vk) } {
+            Ok(()) => registered.push(action),
+            Err(e) => log::warn!("Failed to register hotkey {}: {}", combination, e),
+        }
+    }
+}
+
+/// Action bound to a WM_HOTKEY id
+#[cfg(windows)]
+pub fn action_for(id: i32) -> Option<HotkeyAction> {
+    REGISTERED.lock().ok()?.get(id as usize).cloned()
+}
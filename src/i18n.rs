@@ -0,0 +1,267 @@
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource};
+use std::sync::OnceLock;
+use unic_langid::LanguageIdentifier;
+#[cfg(windows)]
+use windows::core::PWSTR;
+#[cfg(windows)]
+use windows::Win32::Globalization::{
+    GetUserDefaultUILanguage, GetUserPreferredUILanguages, LCIDToLocaleName, MUI_LANGUAGE_NAME,
+};
+
+/// Message catalogs embedded in the binary, keyed by language, English first
+pub const CATALOGS: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.ftl")),
+    ("de", include_str!("../locales/de.ftl")),
+];
+
+// LOCALE_NAME_MAX_LENGTH, including the terminating NUL
+#[cfg(windows)]
+const LOCALE_NAME_MAX_LENGTH: usize = 85;
+
+struct Localizer {
+    language: &'static str,
+    /// Catalog of the selected language, None for English
+    selected: Option<FluentBundle<FluentResource>>,
+    english: FluentBundle<FluentResource>,
+}
+
+impl Localizer {
+    fn new(language: &'static str) -> Self {
+        let selected = CATALOGS
+            .iter()
+            .find(|(tag, _)| *tag == language && *tag != "en")
+            .map(|(tag, source)| bundle(tag, source));
+
+        Self {
+            language,
+            selected,
+            english: bundle(CATALOGS[0].0, CATALOGS[0].1),
+        }
+    }
+}
+
+static LOCALIZER: OnceLock<Localizer> = OnceLock::new();
+
+/// Select the language, the requested one if there is a catalog for it, otherwise the user's
+///
+/// Messages used before this are English.
+pub fn init(requested: Option<&str>) {
+    let selected = requested.and_then(supported);
+    let language = selected
+        .or_else(|| user_languages().iter().find_map(|tag| supported(tag)))
+        .unwrap_or("en");
+
+    if LOCALIZER.set(Localizer::new(language)).is_err() {
+        log::debug!("Language already selected, ignoring {}", language);
+    }
+
+    if let Some(tag) = requested.filter(|_| selected.is_none()) {
+        let message = text_with(
+            "cli-unknown-language",
+            &[("language", tag), ("available", &available().join(", "))],
+        );
+        log::warn!("{}", message);
+        eprintln!("{}", message);
+    }
+}
+
+/// Language of the messages, e.g. "de"
+pub fn language() -> &'static str {
+    localizer().language
+}
+
+/// Languages with a catalog
+pub fn available() -> Vec<&'static str> {
+    CATALOGS.iter().map(|(tag, _)| *tag).collect()
+}
+
+/// Message in the selected language, English if the catalog lacks it
+pub fn text(key: &str) -> String {
+    text_with(key, &[])
+}
+
+/// Message with `{ $name }` placeables filled in
+pub fn text_with(key: &str, args: &[(&str, &str)]) -> String {
+    let mut fluent_args = FluentArgs::new();
+    for (name, value) in args {
+        fluent_args.set(*name, *value);
+    }
+
+    let localizer = localizer();
+    localizer
+        .selected
+        .iter()
+        .chain([&localizer.english])
+        .find_map(|bundle| format(bundle, key, &fluent_args))
+        .unwrap_or_else(|| {
+            log::warn!("Missing message {}", key);
+            key.to_string()
+        })
+}
+
+fn localizer() -> &'static Localizer {
+    LOCALIZER.get_or_init(|| Localizer::new("en"))
+}
+
+fn bundle(tag: &str, source: &str) -> FluentBundle<FluentResource> {
+    let language: LanguageIdentifier = tag.parse().unwrap_or_default();
+    let mut bundle = FluentBundle::new_concurrent(vec![language]);
+    // Isolation marks show up as garbage in consoles
+    bundle.set_use_isolating(false);
+
+    let resource = FluentResource::try_new(source.to_string()).unwrap_or_else(|(resource, e)| {
+        log::warn!("Errors in the {} catalog: {:?}", tag, e);
+        resource
+    });
+    if let Err(e) = bundle.add_resource(resource) {
+        log::warn!("Errors in the {} catalog: {:?}", tag, e);
+    }
+    bundle
+}
+
+fn format(bundle: &FluentBundle<FluentResource>, key: &str, args: &FluentArgs) -> Option<String> {
+    let pattern = bundle.get_message(key)?.value()?;
+    let mut errors = Vec::new();
+    let text = bundle.format_pattern(pattern, Some(args), &mut errors);
+    if !errors.is_empty() {
+        log::warn!("Failed to format message {}: {:?}", key, errors);
+    }
+    Some(text.into_owned())
+}
+
+/// Catalog language for a tag like "de-AT", matched by its language subtag
+pub fn supported(tag: &str) -> Option<&'static str> {
+    let language: LanguageIdentifier = tag.replace('_', "-").parse().ok()?;
+    CATALOGS
+        .iter()
+        .map(|(catalog, _)| *catalog)
+        .find(|catalog| language.language.as_str() == *catalog)
+}
+
+/// Display languages of the user in order of preference, e.g. ["de-DE", "en-US"]
+///
+/// This is the Windows display language, not the regional format.
+#[cfg(windows)]
+fn user_languages() -> Vec<String> {
+    let mut count = 0u32;
+    let mut length = 0u32;
+    let languages = unsafe {
+        GetUserPreferredUILanguages(MUI_LANGUAGE_NAME, &mut count, PWSTR::null(), &mut length)
+    }
+    .ok()
+    .and_then(|_| {
+        let mut buffer = vec![0u16; length as usize];
+        unsafe {
+            GetUserPreferredUILanguages(
+                MUI_LANGUAGE_NAME,
+                &mut count,
+                PWSTR(buffer.as_mut_ptr()),
+                &mut length,
+            )
+        }
+        .ok()
+        .map(|_| buffer)
+    })
+    .map(|buffer| {
+        // A list of NUL terminated names, ended by an empty one
+        buffer
+            .split(|&c| c == 0)
+            .filter(|name| !name.is_empty())
+            .map(String::from_utf16_lossy)
+            .collect::<Vec<_>>()
+    })
+    .unwrap_or_default();
+    if !languages.is_empty() {
+        return languages;
+    }
+
+    let mut buffer = [0u16; LOCALE_NAME_MAX_LENGTH];
+    let length =
+        unsafe { LCIDToLocaleName(GetUserDefaultUILanguage() as u32, Some(&mut buffer), 0) };
+    if length <= 1 {
+        return Vec::new();
+    }
+    vec![String::from_utf16_lossy(&buffer[..length as usize - 1])]
+}
+
+#[cfg(not(windows))]
+fn user_languages() -> Vec<String> {
+    Vec::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{BTreeMap, BTreeSet};
+
+    /// Message keys of a catalog with the names of their `{ $name }` placeables
+    fn messages(source: &str) -> BTreeMap<String, BTreeSet<String>> {
+        let mut messages = BTreeMap::new();
+        let mut current = None;
+        for line in source.lines() {
+            if line.starts_with(char::is_alphabetic) {
+                let (key, _) = line.split_once(" =").expect("message line without =");
+                current = Some(key.to_string());
+                messages.insert(key.to_string(), BTreeSet::new());
+            } else if line.starts_with('#') || line.trim().is_empty() {
+                continue;
+            }
+
+            let key = current
+                .as_ref()
+                .expect("continuation before the first message");
+            for (at, _) in line.match_indices("{ $") {
+                let name: String = line[at + 3..]
+                    .chars()
+                    .take_while(|c| c.is_alphanumeric() || *c == '-' || *c == '_')
+                    .collect();
+                messages.get_mut(key).unwrap().insert(name);
+            }
+        }
+        messages
+    }
+
+    #[test]
+    fn catalogs_parse_without_errors() {
+        for (tag, source) in CATALOGS {
+            if let Err((_, errors)) = FluentResource::try_new(source.to_string()) {
+                panic!("{} catalog: {:?}", tag, errors);
+            }
+        }
+    }
+
+    #[test]
+    fn catalogs_have_the_same_keys_and_placeables() {
+        let english = messages(CATALOGS[0].1);
+        assert!(english.contains_key("usage"));
+        assert_eq!(english["cli-would-send"].len(), 3);
+
+        for (tag, source) in &CATALOGS[1..] {
+            let translated = messages(source);
+            let keys: BTreeSet<_> = english.keys().collect();
+            assert_eq!(
+                keys,
+                translated.keys().collect(),
+                "keys of the {} catalog",
+                tag
+            );
+
+            for (key, placeables) in &english {
+                assert_eq!(
+                    placeables, &translated[key],
+                    "placeables of {} in the {} catalog",
+                    key, tag
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn languages_are_matched_by_their_language_subtag() {
+        assert_eq!(supported("de-AT"), Some("de"));
+        assert_eq!(supported("en_US"), Some("en"));
+        assert_eq!(supported("fr-FR"), None);
+        assert_eq!(supported("not a tag"), None);
+    }
+}
@@ -0,0 +1,376 @@
+use crate::config::IdleConfig;
+use crate::controller::Command;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+#[cfg(windows)]
+use windows::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+#[cfg(windows)]
+use windows::Win32::UI::WindowsAndMessaging::GetForegroundWindow;
+
+#[cfg(windows)]
+const IDLE_POLL_INTERVAL_MS: u64 = 250;
+
+/// Event that reveals a taskbar hidden by the idle policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RevealTrigger {
+    /// Any keyboard or mouse input
+    Input,
+    /// Another window gaining focus
+    FocusChange,
+}
+
+/// Activity observed by the idle watcher
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Activity {
+    Input,
+    /// A window gained focus, identified by its handle
+    Focus(isize),
+}
+
+/// What the idle policy wants done with the taskbar
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdleAction {
+    Hide,
+    Reveal,
+}
+
+/// Hides the taskbar after inactivity or long focus, reveals it on configured triggers
+///
+/// Time is always passed in, so the policy is deterministic for a given event sequence.
+#[derive(Debug, Clone)]
+pub struct IdlePolicy {
+    idle_after: Option<Duration>,
+    focus_after: Option<Duration>,
+    reveal_on: Vec<RevealTrigger>,
+    last_input: Instant,
+    focused: Option<isize>,
+    focused_since: Instant,
+    hidden: bool,
+}
+
+impl IdlePolicy {
+    pub fn new(config: &IdleConfig, now: Instant) -> Self {
+        let duration = |secs: u64| (secs > 0).then(|| Duration::from_secs(secs));
+
+        Self {
+            idle_after: duration(config.hide_after_idle_secs),
+            focus_after: duration(config.hide_after_focus_secs),
+            reveal_on: config.reveal_on.clone(),
+            last_input: now,
+            focused: None,
+            focused_since: now,
+            hidden: false,
+        }
+    }
+
+    /// Whether any hide condition is configured
+    pub fn is_enabled(&self) -> bool {
+        self.idle_after.is_some() || self.focus_after.is_some()
+    }
+
+    /// Feed an activity event, returns Reveal if it triggers one
+    pub fn observe(&mut self, activity: Activity, now: Instant) -> Option<IdleAction> {
+        let trigger = match activity {
+            Activity::Input => {
+                self.last_input = now;
+                RevealTrigger::Input
+            }
+            Activity::Focus(window) => {
+                if self.focused == Some(window) {
+                    return None;
+                }
+                self.focused = Some(window);
+                self.focused_since = now;
+                RevealTrigger::FocusChange
+            }
+        };
+
+        if self.hidden && self.reveal_on.contains(&trigger) {
+            self.hidden = false;
+            // Start both timers over, otherwise a long focus would hide again right away
+            self.last_input = now;
+            self.focused_since = now;
+            return Some(IdleAction::Reveal);
+        }
+
+        None
+    }
+
+    /// Advance the clock, returns Hide once a hide condition is met
+    pub fn tick(&mut self, now: Instant) -> Option<IdleAction> {
+        if self.hidden {
+            return None;
+        }
+
+        let idle = self
+            .idle_after
+            .is_some_and(|after| now.saturating_duration_since(self.last_input) >= after);
+        let focused = self.focused.is_some()
+            && self
+                .focus_after
+                .is_some_and(|after| now.saturating_duration_since(self.focused_since) >= after);
+
+        if idle || focused {
+            self.hidden = true;
+            return Some(IdleAction::Hide);
+        }
+
+        None
+    }
+}
+
+/// Turns idle actions into commands, returning to the state from before the idle period
+///
+/// A reveal only shows a taskbar the idle policy hid, never one the user hid.
+#[derive(Debug, Clone, Default)]
+pub struct IdleOverride {
+    /// Whether the idle policy hid a visible taskbar and it is still hidden because of that
+    hiding: bool,
+}
+
+impl IdleOverride {
+    /// Command for an idle action, `hidden` being the taskbar state before it
+    pub fn command(&mut self, action: IdleAction, hidden: bool) -> Option<Command> {
+        match action {
+            IdleAction::Hide => {
+                self.hiding = !hidden;
+                self.hiding.then_some(Command::Hide)
+            }
+            IdleAction::Reveal => std::mem::take(&mut self.hiding).then_some(Command::Show),
+        }
+    }
+
+    /// The user chose a state, a later reveal leaves it alone
+    pub fn user_changed(&mut self) {
+        self.hiding = false;
+    }
+
+    /// Whether the taskbar is hidden by the idle policy rather than the user
+    pub fn is_hiding(&self) -> bool {
+        self.hiding
+    }
+}
+
+/// Tick count of the last keyboard or mouse input in the session
+#[cfg(windows)]
+fn last_input_tick() -> Option<u32> {
+    let mut info = LASTINPUTINFO {
+        cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+        dwTime: 0,
+    };
+
+    unsafe { GetLastInputInfo(&mut info).as_bool() }.then_some(info.dwTime)
+}
+
+/// Watch input and focus on a background thread and feed them to the idle policy
+///
+/// `send` receives every action and returns false once the receiver is gone.
+#[cfg(windows)]
+pub fn spawn_idle_thread(config: IdleConfig, send: impl Fn(IdleAction) -> bool + Send + 'static) {
+    std::thread::spawn(move || {
+        let mut policy = IdlePolicy::new(&config, Instant::now());
+        if !policy.is_enabled() {
+            return;
+        }
+
+        let mut last_input = last_input_tick();
+
+        loop {
+            let now = Instant::now();
+
+            let input = last_input_tick();
+            let mut actions = Vec::new();
+            if input != last_input {
+                last_input = input;
+                actions.extend(policy.observe(Activity::Input, now));
+            }
+
+            let foreground = unsafe { GetForegroundWindow() };
+            if !foreground.0.is_null() {
+                actions.extend(policy.observe(Activity::Focus(foreground.0 as isize), now));
+            }
+
+            actions.extend(policy.tick(now));
+
+            for action in actions {
+                log::info!("Idle policy: {:?}", action);
+                if !send(action) {
+                    return;
+                }
+            }
+
+            std::thread::sleep(Duration::from_millis(IDLE_POLL_INTERVAL_MS));
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A policy and a clock that only moves when told to
+    struct Clock {
+        policy: IdlePolicy,
+        start: Instant,
+        now: Instant,
+    }
+
+    impl Clock {
+        fn new(idle_secs: u64, focus_secs: u64, reveal_on: &[RevealTrigger]) -> Self {
+            let config = IdleConfig {
+                hide_after_idle_secs: idle_secs,
+                hide_after_focus_secs: focus_secs,
+                reveal_on: reveal_on.to_vec(),
+            };
+            let start = Instant::now();
+            Self {
+                policy: IdlePolicy::new(&config, start),
+                start,
+                now: start,
+            }
+        }
+
+        /// Move to `secs` after the start and tick
+        fn at(&mut self, secs: u64) -> Option<IdleAction> {
+            self.now = self.start + Duration::from_secs(secs);
+            self.policy.tick(self.now)
+        }
+
+        fn observe(&mut self, activity: Activity) -> Option<IdleAction> {
+            self.policy.observe(activity, self.now)
+        }
+    }
+
+    const ALL: [RevealTrigger; 2] = [RevealTrigger::Input, RevealTrigger::FocusChange];
+
+    #[test]
+    fn disabled_without_timeouts() {
+        let mut clock = Clock::new(0, 0, &ALL);
+        assert!(!clock.policy.is_enabled());
+        assert_eq!(clock.at(100_000), None);
+    }
+
+    #[test]
+    fn hides_once_idle_and_only_once() {
+        let mut clock = Clock::new(60, 0, &ALL);
+        assert_eq!(clock.at(59), None);
+        assert_eq!(clock.at(60), Some(IdleAction::Hide));
+        assert_eq!(clock.at(61), None);
+        assert_eq!(clock.at(600), None);
+    }
+
+    #[test]
+    fn input_restarts_the_idle_timer() {
+        let mut clock = Clock::new(60, 0, &ALL);
+        assert_eq!(clock.at(50), None);
+        assert_eq!(clock.observe(Activity::Input), None);
+        assert_eq!(clock.at(100), None);
+        assert_eq!(clock.at(110), Some(IdleAction::Hide));
+    }
+
+    #[test]
+    fn input_reveals_and_starts_over() {
+        let mut clock = Clock::new(60, 0, &ALL);
+        assert_eq!(clock.at(60), Some(IdleAction::Hide));
+
+        clock.at(90);
+        assert_eq!(clock.observe(Activity::Input), Some(IdleAction::Reveal));
+        assert_eq!(clock.observe(Activity::Input), None);
+        assert_eq!(clock.at(149), None);
+        assert_eq!(clock.at(150), Some(IdleAction::Hide));
+    }
+
+    #[test]
+    fn long_focus_hides_even_with_input() {
+        let mut clock = Clock::new(0, 30, &[RevealTrigger::FocusChange]);
+        clock.observe(Activity::Focus(1));
+        for secs in 1..30 {
+            assert_eq!(clock.at(secs), None);
+            assert_eq!(clock.observe(Activity::Input), None);
+        }
+        assert_eq!(clock.at(30), Some(IdleAction::Hide));
+
+        // Input isn't a reveal trigger here, the same window keeping focus isn't a change
+        clock.at(40);
+        assert_eq!(clock.observe(Activity::Input), None);
+        assert_eq!(clock.observe(Activity::Focus(1)), None);
+        assert_eq!(clock.observe(Activity::Focus(2)), Some(IdleAction::Reveal));
+        assert_eq!(clock.at(69), None);
+        assert_eq!(clock.at(70), Some(IdleAction::Hide));
+    }
+
+    #[test]
+    fn focus_timer_needs_a_focused_window() {
+        let mut clock = Clock::new(0, 30, &ALL);
+        assert_eq!(clock.at(300), None);
+
+        clock.observe(Activity::Focus(7));
+        assert_eq!(clock.at(329), None);
+        assert_eq!(clock.at(330), Some(IdleAction::Hide));
+    }
+
+    #[test]
+    fn focus_change_restarts_the_focus_timer() {
+        let mut clock = Clock::new(0, 30, &ALL);
+        clock.observe(Activity::Focus(1));
+        clock.at(20);
+        assert_eq!(clock.observe(Activity::Focus(2)), None);
+        assert_eq!(clock.at(49), None);
+        assert_eq!(clock.at(50), Some(IdleAction::Hide));
+    }
+
+    #[test]
+    fn no_reveal_without_triggers() {
+        let mut clock = Clock::new(10, 0, &[]);
+        assert_eq!(clock.at(10), Some(IdleAction::Hide));
+        assert_eq!(clock.observe(Activity::Input), None);
+        assert_eq!(clock.observe(Activity::Focus(3)), None);
+    }
+
+    #[test]
+    fn reveal_returns_to_the_state_before_the_idle_period() {
+        let mut idle = IdleOverride::default();
+        assert_eq!(idle.command(IdleAction::Hide, false), Some(Command::Hide));
+        assert!(idle.is_hiding());
+        assert_eq!(idle.command(IdleAction::Reveal, true), Some(Command::Show));
+        assert!(!idle.is_hiding());
+        assert_eq!(idle.command(IdleAction::Reveal, false), None);
+    }
+
+    #[test]
+    fn a_taskbar_the_user_hid_stays_hidden_after_idle_and_input() {
+        let mut clock = Clock::new(60, 0, &ALL);
+        let mut idle = IdleOverride::default();
+        let mut hidden = true;
+
+        // Hidden manually, then idle and input
+        let hide = clock.at(60).unwrap();
+        assert_eq!(idle.command(hide, hidden), None);
+        clock.at(90);
+        let reveal = clock.observe(Activity::Input).unwrap();
+        assert_eq!(idle.command(reveal, hidden), None);
+
+        // Shown manually, idle hides it and input brings it back
+        hidden = false;
+        let hide = clock.at(150).unwrap();
+        assert_eq!(idle.command(hide, hidden), Some(Command::Hide));
+        hidden = true;
+
+        // The user picks a state while idle hid the taskbar, the reveal keeps that choice
+        idle.user_changed();
+        clock.at(160);
+        let reveal = clock.observe(Activity::Input).unwrap();
+        assert_eq!(idle.command(reveal, hidden), None);
+    }
+
+    #[test]
+    fn clock_going_backwards_is_harmless() {
+        let mut clock = Clock::new(60, 0, &ALL);
+        clock.at(30);
+        clock.observe(Activity::Input);
+        assert_eq!(clock.at(10), None);
+        assert_eq!(clock.at(90), Some(IdleAction::Hide));
+    }
+}
@@ -0,0 +1,191 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+#[cfg(windows)]
+use std::time::Duration;
+#[cfg(windows)]
+use windows::Win32::Foundation::{
+    CloseHandle, GetLastError, ERROR_ALREADY_EXISTS, HANDLE, WAIT_ABANDONED, WAIT_OBJECT_0,
+};
+#[cfg(windows)]
+use windows::Win32::System::Threading::{CreateMutexW, ReleaseMutex, WaitForSingleObject};
+
+const MUTEX_BASE_NAME: &str = "TaskbarHideApp_SingleInstance";
+const IPC_WINDOW_BASE_CLASS: &str = "THideIPCWindow";
+
+/// Environment variable selecting a named, isolated instance
+pub const INSTANCE_ENV_VAR: &str = "THIDE_INSTANCE";
+
+/// Exit code of a second launch when `on_conflict = "exit"`
+pub const EXIT_ALREADY_RUNNING: i32 = 3;
+
+/// Namespace of the single-instance lock
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum InstanceScope {
+    /// One instance per user session (Local\ namespace)
+    #[default]
+    Session,
+    /// One instance on the whole machine (Global\ namespace)
+    Global,
+}
+
+/// What a second launch does when an instance is already running
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictAction {
+    /// Pass the launch arguments to the running instance and exit
+    #[default]
+    Forward,
+    /// Exit with EXIT_ALREADY_RUNNING
+    Exit,
+}
+
+/// Names of the kernel objects identifying an instance
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstanceNames {
+    /// Instance name, None for the default instance
+    pub name: Option<String>,
+    pub mutex: String,
+    pub ipc_window_class: String,
+}
+
+impl InstanceNames {
+    /// Build the names for the default instance or a named one
+    pub fn new(instance: Option<&str>, scope: InstanceScope) -> Self {
+        let namespace = match scope {
+            InstanceScope::Session => "Local",
+            InstanceScope::Global => "Global",
+        };
+
+        match instance.filter(|name| !name.is_empty()) {
+            Some(name) => Self {
+                name: Some(name.to_string()),
+                mutex: format!("{}\\{}.{}", namespace, MUTEX_BASE_NAME, name),
+                ipc_window_class: format!("{}.{}", IPC_WINDOW_BASE_CLASS, name),
+            },
+            None => Self {
+                name: None,
+                mutex: format!("{}\\{}", namespace, MUTEX_BASE_NAME),
+                ipc_window_class: IPC_WINDOW_BASE_CLASS.to_string(),
+            },
+        }
+    }
+}
+
+static CURRENT: OnceLock<InstanceNames> = OnceLock::new();
+
+/// Select the instance this process belongs to, must be called before any IPC
+pub fn init(names: InstanceNames) {
+    let _ = CURRENT.set(names);
+}
+
+/// Names of the instance this process belongs to
+pub fn current() -> &'static InstanceNames {
+    CURRENT.get_or_init(|| {
+        let instance = std::env::var(INSTANCE_ENV_VAR).ok();
+        InstanceNames::new(instance.as_deref(), InstanceScope::default())
+    })
+}
+
+/// Named mutex held by the running instance, released on drop
+#[cfg(windows)]
+pub struct InstanceLock {
+    handle: HANDLE,
+    owned: bool,
+}
+
+#[cfg(windows)]
+impl InstanceLock {
+    /// Open the instance mutex, taking ownership if no other instance holds it
+    pub fn acquire(names: &InstanceNames) -> Result<Self, Box<dyn std::error::Error>> {
+        unsafe {
+            let mutex_name: Vec<u16> = format!("{}\0", names.mutex).encode_utf16().collect();
+            let handle = CreateMutexW(None, true, windows::core::PCWSTR(mutex_name.as_ptr()))?;
+            let owned = GetLastError() != ERROR_ALREADY_EXISTS;
+
+            Ok(Self { handle, owned })
+        }
+    }
+
+    /// Whether this process owns the lock
+    pub fn is_owned(&self) -> bool {
+        self.owned
+    }
+
+    /// Wait for the running instance to exit and take over the lock
+    pub fn wait_for_owner(&mut self, timeout: Duration) -> bool {
+        if !self.owned {
+            let result = unsafe { WaitForSingleObject(self.handle, timeout.as_millis() as u32) };
+            // An abandoned mutex still transfers ownership to us
+            self.owned = result == WAIT_OBJECT_0 || result == WAIT_ABANDONED;
+        }
+
+        self.owned
+    }
+}
+
+#[cfg(windows)]
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        unsafe {
+            if self.owned {
+                let _ = ReleaseMutex(self.handle);
+            }
+            let _ = CloseHandle(self.handle);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn named_instances_are_isolated() {
+        let default = InstanceNames::new(None, InstanceScope::Session);
+        let first = InstanceNames::new(Some("first"), InstanceScope::Session);
+        let second = InstanceNames::new(Some("second"), InstanceScope::Session);
+
+        for (a, b) in [(&default, &first), (&default, &second), (&first, &second)] {
+            assert_ne!(a.mutex, b.mutex);
+            assert_ne!(a.ipc_window_class, b.ipc_window_class);
+        }
+        assert_eq!(
+            first,
+            InstanceNames::new(Some("first"), InstanceScope::Session)
+        );
+        assert_eq!(
+            default,
+            InstanceNames::new(Some(""), InstanceScope::Session)
+        );
+    }
+
+    #[test]
+    fn scope_only_changes_the_mutex_namespace() {
+        let session = InstanceNames::new(Some("bar"), InstanceScope::Session);
+        let global = InstanceNames::new(Some("bar"), InstanceScope::Global);
+
+        assert_eq!(session.mutex, "Local\\TaskbarHideApp_SingleInstance.bar");
+        assert_eq!(global.mutex, "Global\\TaskbarHideApp_SingleInstance.bar");
+        assert_eq!(session.ipc_window_class, global.ipc_window_class);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn named_instances_hold_separate_locks() {
+        // Unique names so parallel test runs and a running THide don't interfere
+        let suffix = std::process::id();
+        let first = InstanceNames::new(Some(&format!("test-a-{}", suffix)), InstanceScope::Session);
+        let second =
+            InstanceNames::new(Some(&format!("test-b-{}", suffix)), InstanceScope::Session);
+
+        let first_lock = InstanceLock::acquire(&first).unwrap();
+        let second_lock = InstanceLock::acquire(&second).unwrap();
+        assert!(first_lock.is_owned());
+        assert!(second_lock.is_owned());
+
+        let again = InstanceLock::acquire(&first).unwrap();
+        assert!(!again.is_owned());
+    }
+}
@@ -0,0 +1,419 @@
+use crate::config::Config;
+use crate::hooks;
+use crate::instance::INSTANCE_ENV_VAR;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+use toml::{Table, Value};
+
+/// Prefix of variables overriding config keys, e.g. THIDE_HIDE__STRATEGY=dim
+pub const ENV_PREFIX: &str = "THIDE_";
+
+// Separates nested keys, single underscores belong to the key names
+const ENV_SEPARATOR: &str = "__";
+
+// Set by thide itself, for hook commands and instance selection
+const RESERVED_ENV_VARS: &[&str] = &[
+    INSTANCE_ENV_VAR,
+    hooks::EVENT_ENV_VAR,
+    hooks::STATE_ENV_VAR,
+    hooks::PREVIOUS_STATE_ENV_VAR,
+    hooks::MONITORS_ENV_VAR,
+    hooks::MONITOR_COUNT_ENV_VAR,
+    hooks::PID_ENV_VAR,
+];
+
+/// Where a configuration value came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Default,
+    /// The system-wide or the user's config file
+    File(PathBuf),
+    /// Environment variable
+    Env(String),
+    /// Command line flag
+    Flag(String),
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::File(path) => write!(f, "{}", path.display()),
+            Source::Env(name) => write!(f, "%{}%", name),
+            Source::Flag(flag) => write!(f, "{}", flag),
+        }
+    }
+}
+
+/// A configuration merged from layers, each overriding the ones before it key by key
+///
+/// The layers are applied in this order: built-in defaults, the system-wide file, the user's
+/// file, THIDE_* variables and command line flags.
+#[derive(Debug, Clone)]
+pub struct Layered {
+    table: Table,
+    /// Layer that set each value, keyed by dotted path
+    sources: BTreeMap<String, Source>,
+}
+
+impl Layered {
+    /// The built-in defaults
+    pub fn defaults() -> Self {
+        let table = Table::try_from(Config::default()).unwrap_or_default();
+        let mut sources = BTreeMap::new();
+        for (key, value) in &table {
+            record(&mut sources, &quote(key), value, &Source::Default);
+        }
+
+        Self { table, sources }
+    }
+
+    /// Override values with a layer, tables are merged and everything else replaced
+    pub fn merge(&mut self, layer: Table, source: Source) {
+        merge_into(&mut self.table, layer, "", &mut self.sources, &source);
+    }
+
+    /// The merged configuration
+    pub fn config(&self) -> Result<Config, Box<dyn std::error::Error>> {
+        Ok(Value::Table(self.table.clone()).try_into()?)
+    }
+
+    /// Every value by dotted path, with the layer that set it
+    pub fn entries(&self) -> Vec<(String, &Value, &Source)> {
+        let mut leaves = Vec::new();
+        collect(&self.table, "", &mut leaves);
+
+        leaves
+            .into_iter()
+            .filter_map(|(path, value)| {
+                let source = self.sources.get(&path)?;
+                Some((path, value, source))
+            })
+            .collect()
+    }
+}
+
+/// Layers for the THIDE_* variables, one per variable
+///
+/// Nested keys are separated by two underscores, e.g. THIDE_NOTIFICATIONS__MIN_INTERVAL_SECS=60.
+/// Names are lowercased, so keys with capitals such as profile names can only be set by `--set`.
+/// Values are parsed as TOML and taken as a string if that fails.
+pub fn env(vars: impl IntoIterator<Item = (String, String)>) -> Vec<(Table, Source)> {
+    let mut layers: Vec<_> = vars
+        .into_iter()
+        .filter(|(name, _)| !RESERVED_ENV_VARS.contains(&name.as_str()))
+        .filter_map(|(name, raw)| {
+            let path = name.strip_prefix(ENV_PREFIX)?.to_lowercase();
+            let keys: Vec<&str> = path.split(ENV_SEPARATOR).collect();
+            if keys.iter().any(|key| key.is_empty()) {
+                return None;
+            }
+
+            let table = nested(&keys, parse_value(&raw));
+            Some((table, Source::Env(name)))
+        })
+        .collect();
+
+    // Variables come in no particular order
+    layers.sort_by_key(|(_, source)| source.to_string());
+    layers
+}
+
+/// Layers for command line flags, `--lang` and each `--set KEY=VALUE` in order
+///
+/// Keys are dotted paths, e.g. `--set hide.strategy=dim`, values are parsed like variables. A
+/// malformed `--set` is an error, reported with the other problems of loading the config.
+pub fn flags(lang: Option<&str>, set: &[String]) -> Result<Vec<(Table, Source)>, String> {
+    let lang = lang.map(|lang| {
+        (
+            nested(&["language"], Value::String(lang.to_string())),
+            Source::Flag("--lang".to_string()),
+        )
+    });
+
+    let set = set.iter().map(|assignment| {
+        let Some((path, raw)) = assignment.split_once('=') else {
+            return Err(format!("--set {}: expected KEY=VALUE", assignment));
+        };
+
+        let keys: Vec<&str> = path.trim().split('.').collect();
+        if keys.iter().any(|key| key.is_empty()) {
+            return Err(format!("--set {}: the key is empty", assignment));
+        }
+        Ok((
+            nested(&keys, parse_value(raw.trim())),
+            Source::Flag(format!("--set {}", path.trim())),
+        ))
+    });
+
+    lang.into_iter().map(Ok).chain(set).collect()
+}
+
+/// A table setting the value at a path of keys
+pub fn nested(keys: &[&str], value: Value) -> Table {
+    let mut value = value;
+    for key in keys.iter().rev() {
+        value = Value::Table(Table::from_iter([(key.to_string(), value)]));
+    }
+
+    match value {
+        Value::Table(table) => table,
+        _ => Table::new(),
+    }
+}
+
+fn parse_value(raw: &str) -> Value {
+    toml::from_str::<Table>(&format!("value = {}", raw))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| Value::String(raw.to_string()))
+}
+
+fn merge_into(
+    target: &mut Table,
+    layer: Table,
+    prefix: &str,
+    sources: &mut BTreeMap<String, Source>,
+    source: &Source,
+) {
+    for (key, value) in layer {
+        let path = join(prefix, &key);
+        match (target.get_mut(&key), value) {
+            (Some(Value::Table(existing)), Value::Table(table)) => {
+                merge_into(existing, table, &path, sources, source)
+            }
+            (_, value) => {
+                let nested = format!("{}.", path);
+                sources.retain(|key, _| *key != path && !key.starts_with(&nested));
+                record(sources, &path, &value, source);
+                target.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Attribute a value and everything nested in it to a source
+fn record(sources: &mut BTreeMap<String, Source>, path: &str, value: &Value, source: &Source) {
+    match value {
+        Value::Table(table) => {
+            for (key, value) in table {
+                record(sources, &join(path, key), value, source);
+            }
+        }
+        _ => {
+            sources.insert(path.to_string(), source.clone());
+        }
+    }
+}
+
+fn collect<'a>(table: &'a Table, prefix: &str, leaves: &mut Vec<(String, &'a Value)>) {
+    for (key, value) in table {
+        let path = join(prefix, key);
+        match value {
+            Value::Table(table) => collect(table, &path, leaves),
+            _ => leaves.push((path, value)),
+        }
+    }
+}
+
+fn join(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        quote(key)
+    } else {
+        format!("{}.{}", prefix, quote(key))
+    }
+}
+
+/// A key as written in TOML, quoted unless it is a bare key
+fn quote(key: &str) -> String {
+    let bare = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if bare {
+        key.to_string()
+    } else {
+        Value::String(key.to_string()).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::HideStrategy;
+
+    /// The layers above the defaults in the order they are applied, each setting the strategy
+    const LAYERS: [(&str, HideStrategy); 4] = [
+        ("system", HideStrategy::OffScreen),
+        ("user", HideStrategy::Transparent),
+        ("env", HideStrategy::Strip),
+        ("flag", HideStrategy::Dim),
+    ];
+
+    fn file(name: &str, contents: &str) -> (Table, Source) {
+        let path = PathBuf::from(format!("C:\\{}\\thide\\config.toml", name));
+        (toml::from_str(contents).unwrap(), Source::File(path))
+    }
+
+    fn layer(name: &str, strategy: HideStrategy) -> (Table, Source) {
+        let value = strategy.as_str();
+        match name {
+            "env" => env([("THIDE_HIDE__STRATEGY".to_string(), value.to_string())]).remove(0),
+            "flag" => flags(None, &[format!("hide.strategy={}", value)])
+                .unwrap()
+                .remove(0),
+            _ => file(name, &format!("[hide]\nstrategy = \"{}\"", value)),
+        }
+    }
+
+    fn source<'a>(layered: &'a Layered, path: &str) -> Option<&'a Source> {
+        let entries = layered.entries();
+        let (_, _, source) = entries.into_iter().find(|(key, _, _)| key == path)?;
+        Some(source)
+    }
+
+    #[test]
+    fn later_layers_win_in_every_combination() {
+        for present in 0..1 << LAYERS.len() {
+            let mut layered = Layered::defaults();
+            let mut expected = (HideStrategy::default(), Source::Default);
+
+            for (i, (name, strategy)) in LAYERS.into_iter().enumerate() {
+                if present & 1 << i != 0 {
+                    let (table, source) = layer(name, strategy);
+                    layered.merge(table, source.clone());
+                    expected = (strategy, source);
+                }
+            }
+
+            let config = layered.config().unwrap();
+            assert_eq!(config.hide.strategy, expected.0, "layers {:04b}", present);
+            assert_eq!(
+                source(&layered, "hide.strategy"),
+                Some(&expected.1),
+                "layers {:04b}",
+                present
+            );
+            // Siblings of an overridden key keep their own layer
+            assert_eq!(source(&layered, "hide.dim.opacity"), Some(&Source::Default));
+        }
+    }
+
+    #[test]
+    fn tables_merge_and_lists_are_replaced() {
+        let mut layered = Layered::defaults();
+        let (system, system_source) = file(
+            "system",
+            "[[taskbars]]\nclass = \"Shell_TrayWnd\"\n\n[hide.dim]\nopacity = 0.5\nfade_ms = 300",
+        );
+        layered.merge(system, system_source.clone());
+        let (user, user_source) = file(
+            "user",
+            "[[taskbars]]\nprocess = \"explorer.exe\"\n\n[hide.dim]\nfade_ms = 100",
+        );
+        layered.merge(user, user_source.clone());
+
+        let config = layered.config().unwrap();
+        assert_eq!(config.taskbars.len(), 1);
+        assert_eq!(config.taskbars[0].process.as_deref(), Some("explorer.exe"));
+        assert_eq!(config.hide.dim.opacity, 0.5);
+        assert_eq!(config.hide.dim.fade_ms, 100);
+
+        assert_eq!(source(&layered, "hide.dim.opacity"), Some(&system_source));
+        assert_eq!(source(&layered, "hide.dim.fade_ms"), Some(&user_source));
+    }
+
+    #[test]
+    fn replaced_tables_drop_the_sources_of_their_old_keys() {
+        let mut layered = Layered::defaults();
+        layered.merge(
+            nested(&["hotkeys", "Ctrl+Alt+T"], Value::from("toggle")),
+            Source::Flag("--set hotkeys.Ctrl+Alt+T".to_string()),
+        );
+        assert!(source(&layered, "hotkeys.\"Ctrl+Alt+T\"").is_some());
+
+        layered.merge(
+            nested(&["hotkeys"], Value::from("none")),
+            Source::Env("THIDE_HOTKEYS".to_string()),
+        );
+        assert_eq!(source(&layered, "hotkeys.\"Ctrl+Alt+T\""), None);
+        assert_eq!(
+            source(&layered, "hotkeys"),
+            Some(&Source::Env("THIDE_HOTKEYS".to_string()))
+        );
+    }
+
+    #[test]
+    fn variables_are_lowercased_and_filtered() {
+        let vars = [
+            ("THIDE_NOTIFICATIONS__MIN_INTERVAL_SECS", "60"),
+            ("THIDE_ANIMATION__KIND", "fade"),
+            ("THIDE_INSTANCE", "work"),
+            ("THIDE_PID", "42"),
+            ("THIDE_HIDE____STRATEGY", "dim"),
+            ("PATH", "C:\\Windows"),
+        ];
+        let layers = env(vars.map(|(name, value)| (name.to_string(), value.to_string())));
+
+        let sources: Vec<String> = layers.iter().map(|(_, s)| s.to_string()).collect();
+        assert_eq!(
+            sources,
+            [
+                "%THIDE_ANIMATION__KIND%",
+                "%THIDE_NOTIFICATIONS__MIN_INTERVAL_SECS%"
+            ]
+        );
+        assert_eq!(
+            layers[1].0,
+            nested(&["notifications", "min_interval_secs"], Value::Integer(60))
+        );
+        assert_eq!(
+            layers[0].0,
+            nested(&["animation", "kind"], Value::from("fade"))
+        );
+    }
+
+    #[test]
+    fn flags_keep_their_order_and_reject_malformed_assignments() {
+        let set = [
+            "hide.strategy=dim".to_string(),
+            "hide.strategy = strip".to_string(),
+        ];
+        let layers = flags(Some("de"), &set).unwrap();
+        let sources: Vec<String> = layers.iter().map(|(_, s)| s.to_string()).collect();
+        assert_eq!(
+            sources,
+            ["--lang", "--set hide.strategy", "--set hide.strategy"]
+        );
+
+        let mut layered = Layered::defaults();
+        for (table, source) in layers {
+            layered.merge(table, source);
+        }
+        let config = layered.config().unwrap();
+        assert_eq!(config.hide.strategy, HideStrategy::Strip);
+        assert_eq!(config.language.as_deref(), Some("de"));
+
+        assert_eq!(
+            flags(None, &["hide.strategy".to_string()]).unwrap_err(),
+            "--set hide.strategy: expected KEY=VALUE"
+        );
+        assert_eq!(
+            flags(None, &["hide..strategy=dim".to_string()]).unwrap_err(),
+            "--set hide..strategy=dim: the key is empty"
+        );
+    }
+
+    #[test]
+    fn values_fall_back_to_text() {
+        assert_eq!(parse_value("60"), Value::Integer(60));
+        assert_eq!(parse_value("true"), Value::Boolean(true));
+        assert_eq!(parse_value("\"quoted\""), Value::from("quoted"));
+        assert_eq!(parse_value("dim"), Value::from("dim"));
+        assert_eq!(
+            parse_value("[1, 2]"),
+            Value::Array(vec![Value::Integer(1), Value::Integer(2)])
+        );
+    }
+}
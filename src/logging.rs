@@ -0,0 +1,72 @@
+use log::{LevelFilter, Log, Metadata, Record};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+const LOG_FILE_NAME: &str = "thide.log";
+
+/// Simple file logger, the GUI build has no console to write to
+struct FileLogger {
+    file: Mutex<File>,
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        let now = unsafe { windows::Win32::System::SystemInformation::GetLocalTime() };
+
+        if let Ok(mut file) = self.file.lock() {
+            let _ = writeln!(
+                file,
+                "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:03} [{}] {}",
+                now.wYear,
+                now.wMonth,
+                now.wDay,
+                now.wHour,
+                now.wMinute,
+                now.wSecond,
+                now.wMilliseconds,
+                record.level(),
+                record.args()
+            );
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.flush();
+        }
+    }
+}
+
+/// Get the log file path (%LOCALAPPDATA%\thide\thide.log)
+pub fn log_path() -> Option<PathBuf> {
+    crate::config::data_dir().map(|dir| dir.join(LOG_FILE_NAME))
+}
+
+/// Install the file logger, logging stays disabled if the file can't be opened
+pub fn init() {
+    let Some(path) = log_path() else {
+        return;
+    };
+
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+
+    let Ok(file) = OpenOptions::new().create(true).append(true).open(&path) else {
+        return;
+    };
+
+    let logger = Box::new(FileLogger {
+        file: Mutex::new(file),
+    });
+
+    if log::set_boxed_logger(logger).is_ok() {
+        log::set_max_level(LevelFilter::Info);
+    }
+}
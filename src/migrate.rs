@@ -0,0 +1,416 @@
+use crate::i18n;
+use crate::schema;
+use crate::validate;
+use schemars::schema::{RootSchema, Schema, SchemaObject, SingleOrVec};
+use std::collections::{BTreeMap, BTreeSet};
+use toml_edit::{DocumentMut, Item, TableLike};
+
+/// Version of the config format this thide reads and writes
+pub const CURRENT_VERSION: u32 = 1;
+
+/// Schemas of each version's config file, frozen when the version was released
+///
+/// Steps check against the version they upgrade to, not the types of this thide, so later
+/// changes to the config don't change what an old step does.
+const SCHEMAS: &[(u32, &str)] = &[(1, include_str!("../assets/schemas/config.v1.json"))];
+
+/// A step upgrading a config file to the next version, returns what it changed
+struct Step {
+    from: u32,
+    run: fn(&mut DocumentMut) -> Vec<String>,
+}
+
+// In order, each one taking files from its version to the next
+const STEPS: &[Step] = &[Step {
+    from: 0,
+    run: drop_ignored_keys,
+}];
+
+/// A config file upgraded to the current version
+#[derive(Debug, Clone)]
+pub struct Migration {
+    /// Version the file was written for, 0 if it has none
+    pub from: u32,
+    pub document: DocumentMut,
+    /// What the steps changed, for the user to review
+    pub changes: Vec<String>,
+}
+
+impl Migration {
+    /// Whether the file needs upgrading, older files the steps don't change are current as they are
+    pub fn is_upgrade(&self) -> bool {
+        self.from < CURRENT_VERSION && !self.changes.is_empty()
+    }
+}
+
+/// Upgrade the contents of a config file, refuses files written for a newer thide
+///
+/// Comments and formatting are kept, so the result can be written back.
+pub fn migrate(contents: &str) -> Result<Migration, Box<dyn std::error::Error>> {
+    let mut document: DocumentMut = contents.parse()?;
+    let from = readable_version(&document)?;
+
+    let mut changes = Vec::new();
+    for step in STEPS.iter().filter(|step| step.from >= from) {
+        changes.extend((step.run)(&mut document));
+    }
+    if from < CURRENT_VERSION {
+        document["version"] = toml_edit::value(i64::from(CURRENT_VERSION));
+    }
+
+    Ok(Migration {
+        from,
+        document,
+        changes,
+    })
+}
+
+/// Version a config file was written for, refusing files written for a newer thide
+pub fn file_version(contents: &str) -> Result<u32, Box<dyn std::error::Error>> {
+    Ok(readable_version(&contents.parse()?)?)
+}
+
+fn readable_version(document: &DocumentMut) -> Result<u32, String> {
+    let from = version(document)?;
+    if from > CURRENT_VERSION {
+        return Err(format!(
+            "the file is for config version {}, this thide ({}) reads up to version {}. \
+             Update thide, or set version = {} and remove what this version doesn't know",
+            from,
+            env!("CARGO_PKG_VERSION"),
+            CURRENT_VERSION,
+            CURRENT_VERSION
+        ));
+    }
+    Ok(from)
+}
+
+/// Version a config file was written for, files from before versioning have none and are 0
+fn version(document: &DocumentMut) -> Result<u32, String> {
+    let Some(item) = document.get("version") else {
+        return Ok(0);
+    };
+
+    item.as_integer()
+        .and_then(|version| u32::try_from(version).ok())
+        .ok_or_else(|| format!("version must be a whole number, not {}", item))
+}
+
+/// Version 0 to 1: remove keys that versions before validation silently ignored
+///
+/// A file without a version may also be a current one that left it out, so keys the current
+/// config knows are kept.
+fn drop_ignored_keys(document: &mut DocumentMut) -> Vec<String> {
+    let current: BTreeSet<String> =
+        unknown_keys(&schema::config(), &mut document.clone(), &|_| true)
+            .into_iter()
+            .map(|key| key.path)
+            .collect();
+
+    unknown_keys(&frozen(1), document, &|path| current.contains(path))
+        .into_iter()
+        .map(|key| match key.suggestion {
+            Some(known) => i18n::text_with(
+                "migrate-removed-suggestion",
+                &[("key", &key.path), ("suggestion", &known)],
+            ),
+            None => i18n::text_with("migrate-removed", &[("key", &key.path)]),
+        })
+        .collect()
+}
+
+/// A key a schema doesn't allow
+struct UnknownKey {
+    /// Dotted path, e.g. hide.stratgy
+    path: String,
+    /// The closest key allowed in its place
+    suggestion: Option<String>,
+}
+
+/// Remove the keys a schema doesn't allow and `removable` accepts the path of
+///
+/// Removing an unknown table also drops what it holds, so nested keys aren't listed twice.
+fn unknown_keys(
+    root: &RootSchema,
+    document: &mut DocumentMut,
+    removable: &dyn Fn(&str) -> bool,
+) -> Vec<UnknownKey> {
+    let schema = Schema::Object(root.schema.clone());
+    let mut unknown = Vec::new();
+    prune(
+        root,
+        &schema,
+        document.as_table_mut(),
+        "",
+        removable,
+        &mut unknown,
+    );
+    unknown
+}
+
+/// The frozen schema of a version
+fn frozen(version: u32) -> RootSchema {
+    let (_, schema) = SCHEMAS
+        .iter()
+        .find(|(frozen, _)| *frozen == version)
+        .expect("every version has a frozen schema");
+    serde_json::from_str(schema).expect("frozen config schemas are valid")
+}
+
+/// Remove the keys of a table its schema doesn't allow, recursing into nested tables
+fn prune(
+    root: &RootSchema,
+    schema: &Schema,
+    table: &mut dyn TableLike,
+    path: &str,
+    removable: &dyn Fn(&str) -> bool,
+    unknown: &mut Vec<UnknownKey>,
+) {
+    let objects = objects(root, schema);
+    let validations: Vec<_> = objects.iter().filter_map(|o| o.object.as_deref()).collect();
+    let properties: BTreeMap<&str, &Schema> = validations
+        .iter()
+        .flat_map(|o| o.properties.iter().map(|(key, s)| (key.as_str(), s)))
+        .collect();
+    let additional = validations
+        .iter()
+        .filter_map(|o| o.additional_properties.as_deref())
+        .find(|s| !matches!(s, Schema::Bool(false)));
+    let strict = validations.iter().any(|o| {
+        matches!(
+            o.additional_properties.as_deref(),
+            Some(Schema::Bool(false))
+        )
+    });
+
+    let keys: Vec<String> = table.iter().map(|(key, _)| key.to_string()).collect();
+    for key in keys {
+        let key_path = if path.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", path, key)
+        };
+
+        match properties.get(key.as_str()).copied().or(additional) {
+            Some(schema) => {
+                if let Some(item) = table.get_mut(&key) {
+                    prune_item(root, schema, item, &key_path, removable, unknown);
+                }
+            }
+            None if strict && removable(&key_path) => {
+                table.remove(&key);
+                unknown.push(UnknownKey {
+                    suggestion: validate::closest(&key, properties.keys().copied())
+                        .map(str::to_string),
+                    path: key_path,
+                });
+            }
+            None => {}
+        }
+    }
+}
+
+fn prune_item(
+    root: &RootSchema,
+    schema: &Schema,
+    item: &mut Item,
+    path: &str,
+    removable: &dyn Fn(&str) -> bool,
+    unknown: &mut Vec<UnknownKey>,
+) {
+    if let Some(table) = item.as_table_like_mut() {
+        prune(root, schema, table, path, removable, unknown);
+        return;
+    }
+
+    let element = objects(root, schema).into_iter().find_map(|o| {
+        match o.array.as_deref()?.items.as_ref()? {
+            SingleOrVec::Single(element) => Some(&**element),
+            SingleOrVec::Vec(_) => None,
+        }
+    });
+    let Some(element) = element else {
+        return;
+    };
+
+    if let Some(tables) = item.as_array_of_tables_mut() {
+        for (i, table) in tables.iter_mut().enumerate() {
+            let path = format!("{}[{}]", path, i);
+            prune(root, element, table, &path, removable, unknown);
+        }
+    } else if let Some(array) = item.as_array_mut() {
+        for (i, value) in array.iter_mut().enumerate() {
+            if let Some(table) = value.as_inline_table_mut() {
+                let path = format!("{}[{}]", path, i);
+                prune(root, element, table, &path, removable, unknown);
+            }
+        }
+    }
+}
+
+/// Object schemas a value has to match, following references and combinations
+fn objects<'a>(root: &'a RootSchema, schema: &'a Schema) -> Vec<&'a SchemaObject> {
+    let Schema::Object(object) = schema else {
+        return Vec::new();
+    };
+
+    let mut objects = vec![object];
+    if let Some(definition) = object
+        .reference
+        .as_deref()
+        .and_then(|reference| reference.strip_prefix("#/definitions/"))
+        .and_then(|name| root.definitions.get(name))
+    {
+        objects.extend(self::objects(root, definition));
+    }
+    if let Some(subschemas) = &object.subschemas {
+        let combined = [&subschemas.all_of, &subschemas.any_of, &subschemas.one_of];
+        for schema in combined.into_iter().flatten().flatten() {
+            objects.extend(self::objects(root, schema));
+        }
+    }
+    objects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{self, Config};
+    use std::path::PathBuf;
+
+    /// A config file as written for each version, `v<N>.toml` being `v<N-1>.toml` upgraded
+    fn fixture(version: u32) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/migrations")
+            .join(format!("v{}.toml", version))
+    }
+
+    fn read_fixture(version: u32) -> String {
+        std::fs::read_to_string(fixture(version))
+            .unwrap()
+            .replace("\r\n", "\n")
+    }
+
+    /// Run the single step upgrading a file from `from` to the next version
+    fn step(from: u32, contents: &str) -> (String, Vec<String>) {
+        let mut document: DocumentMut = contents.parse().unwrap();
+        let step = STEPS.iter().find(|step| step.from == from).unwrap();
+        let changes = (step.run)(&mut document);
+        document["version"] = toml_edit::value(i64::from(from + 1));
+        (document.to_string(), changes)
+    }
+
+    #[test]
+    fn each_step_upgrades_its_fixture() {
+        for from in 0..CURRENT_VERSION {
+            let (upgraded, _) = step(from, &read_fixture(from));
+            assert_eq!(
+                upgraded,
+                read_fixture(from + 1),
+                "step from version {}",
+                from
+            );
+        }
+    }
+
+    #[test]
+    fn fixtures_only_use_the_keys_of_their_version() {
+        for version in 1..=CURRENT_VERSION {
+            let mut document: DocumentMut = read_fixture(version).parse().unwrap();
+            let unknown: Vec<_> = unknown_keys(&frozen(version), &mut document, &|_| true)
+                .into_iter()
+                .map(|key| key.path)
+                .collect();
+            assert_eq!(unknown, Vec::<String>::new(), "version {}", version);
+        }
+    }
+
+    #[test]
+    fn ignored_keys_are_listed_with_suggestions() {
+        let (_, changes) = step(0, &read_fixture(0));
+        assert_eq!(
+            changes,
+            [
+                "Removed `hdie_on_start`, which earlier versions ignored",
+                "Removed `hide.stratgy`, which earlier versions ignored, did you mean `strategy`?",
+                "Removed `taskbars[0].procces`, which earlier versions ignored, did you mean \
+                 `process`?",
+                "Removed `profiles.focus.colour`, which earlier versions ignored",
+            ]
+        );
+    }
+
+    #[test]
+    fn old_files_are_upgraded_to_the_current_version() {
+        for from in 0..=CURRENT_VERSION {
+            let migration = migrate(&read_fixture(from)).unwrap();
+            assert_eq!(migration.from, from);
+            assert_eq!(migration.is_upgrade(), from < CURRENT_VERSION);
+
+            let upgraded = migration.document.to_string();
+            assert_eq!(
+                upgraded,
+                read_fixture(CURRENT_VERSION),
+                "from version {}",
+                from
+            );
+            let config: Config = toml::from_str(&upgraded).unwrap();
+            assert_eq!(config.version, Some(CURRENT_VERSION));
+        }
+
+        let current = migrate(&read_fixture(CURRENT_VERSION)).unwrap();
+        assert!(current.changes.is_empty());
+    }
+
+    #[test]
+    fn versionless_files_keep_the_current_keys() {
+        let current =
+            "[hide]\nstrategy = \"dim\"\n\n[profiles.gaming]\nstrategy = \"off_screen\"\n";
+        let migration = migrate(current).unwrap();
+        assert_eq!(migration.from, 0);
+        assert!(!migration.is_upgrade());
+        assert!(migration.changes.is_empty(), "{:?}", migration.changes);
+        assert_eq!(
+            migration.document.to_string(),
+            format!("version = {}\n{}", CURRENT_VERSION, current)
+        );
+
+        let path =
+            std::env::temp_dir().join(format!("thide-versionless-{}.toml", std::process::id()));
+        std::fs::write(&path, current).unwrap();
+        let table = config::read(&path).unwrap().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let config: Config = toml::Value::Table(table).try_into().unwrap();
+        let gaming = config.profiles["gaming"].strategy;
+        assert_eq!(gaming.map(|strategy| strategy.as_str()), Some("off_screen"));
+    }
+
+    #[test]
+    fn newer_and_malformed_versions_are_refused() {
+        let newer = format!("version = {}", CURRENT_VERSION + 1);
+        let error = migrate(&newer).unwrap_err().to_string();
+        assert!(error.contains("reads up to version"), "{}", error);
+        assert!(file_version(&newer).is_err());
+
+        assert!(migrate("version = \"1\"").is_err());
+        assert!(migrate("version = -1").is_err());
+        assert_eq!(file_version("language = \"de\"").unwrap(), 0);
+    }
+
+    #[test]
+    fn loading_never_migrates() {
+        let error = config::read(&fixture(0)).unwrap_err().to_string();
+        assert!(error.contains("hdie_on_start"), "{}", error);
+        assert!(
+            error.contains("`thide config migrate` upgrades it"),
+            "{}",
+            error
+        );
+
+        let table = config::read(&fixture(CURRENT_VERSION)).unwrap().unwrap();
+        let config: Config = toml::Value::Table(table).try_into().unwrap();
+        assert_eq!(config.hide.strategy.as_str(), "off_screen");
+        let focus = config.profiles["focus"].strategy;
+        assert_eq!(focus.map(|strategy| strategy.as_str()), Some("auto_hide"));
+    }
+}
@@ -0,0 +1,401 @@
+#[cfg(windows)]
+use crate::cli;
+use crate::config::NotificationsConfig;
+use crate::controller::{Transition, TransitionKind};
+use crate::flapping::FlapReport;
+use crate::i18n;
+use std::collections::BTreeMap;
+use std::time::Instant;
+#[cfg(windows)]
+use windows::Win32::Foundation::LPARAM;
+#[cfg(windows)]
+use windows::Win32::UI::Shell::{
+    Shell_NotifyIconW, NIF_ICON, NIF_INFO, NIF_MESSAGE, NIF_TIP, NIIF_INFO, NIIF_WARNING, NIM_ADD,
+    NIM_DELETE, NIM_MODIFY, NIN_BALLOONTIMEOUT, NIN_BALLOONUSERCLICK, NOTIFYICONDATAW,
+};
+#[cfg(windows)]
+use windows::Win32::UI::WindowsAndMessaging::{LoadIconW, IDI_INFORMATION, WM_APP};
+
+/// Sent to the IPC window when the balloon of the notification icon is dismissed
+#[cfg(windows)]
+pub const WM_THIDE_NOTIFY: u32 = WM_APP + 11;
+
+// Separate from the tray icon, which tray-icon owns
+#[cfg(windows)]
+const NOTIFY_ICON_ID: u32 = 0x7417;
+
+/// Events that may show a notification
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum NotificationEvent {
+    Hidden,
+    Shown,
+    ExplorerRestarted,
+    /// The config file couldn't be loaded, thide runs with the defaults
+    ConfigFailed,
+    Flapping,
+}
+
+impl NotificationEvent {
+    /// Whether the notification reports a problem
+    pub fn is_warning(&self) -> bool {
+        matches!(
+            self,
+            NotificationEvent::ConfigFailed | NotificationEvent::Flapping
+        )
+    }
+}
+
+/// A notification ready to be shown
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub event: NotificationEvent,
+    pub title: String,
+    pub body: String,
+}
+
+/// Turns the controller's transitions and the app's errors into rate limited notifications
+///
+/// Time is passed in by the caller, so the notifier is deterministic for a given timeline.
+pub struct Notifier {
+    config: NotificationsConfig,
+    /// When each event was last notified
+    last: BTreeMap<NotificationEvent, Instant>,
+}
+
+impl Notifier {
+    pub fn new(config: NotificationsConfig) -> Self {
+        Self {
+            config,
+            last: BTreeMap::new(),
+        }
+    }
+
+    /// Notifications for transitions emitted by the controller
+    pub fn transitions(&mut self, transitions: &[Transition], now: Instant) -> Vec<Notification> {
+        transitions
+            .iter()
+            .filter_map(|transition| {
+                let monitors = &transition.monitors;
+                match transition.kind {
+                    TransitionKind::Hide => self.notify(
+                        NotificationEvent::Hidden,
+                        on_monitors("notify-hidden", monitors),
+                        now,
+                    ),
+                    TransitionKind::Show => self.notify(
+                        NotificationEvent::Shown,
+                        on_monitors("notify-shown", monitors),
+                        now,
+                    ),
+                    TransitionKind::ExplorerRestart => self.notify(
+                        NotificationEvent::ExplorerRestarted,
+                        i18n::text("notify-explorer-restarted"),
+                        now,
+                    ),
+                    TransitionKind::Start | TransitionKind::Quit => None,
+                }
+            })
+            .collect()
+    }
+
+    /// Notifications for taskbars that started flapping, `previous` are the reports already known
+    pub fn flapping(
+        &mut self,
+        reports: &[FlapReport],
+        previous: &[FlapReport],
+        now: Instant,
+    ) -> Vec<Notification> {
+        reports
+            .iter()
+            .filter(|report| !previous.iter().any(|p| p.monitor == report.monitor))
+            .filter_map(|report| self.notify(NotificationEvent::Flapping, report.message(), now))
+            .collect()
+    }
+
+    /// Notification for a config file that couldn't be loaded
+    pub fn config_failed(&mut self, error: &str, now: Instant) -> Option<Notification> {
+        self.notify(
+            NotificationEvent::ConfigFailed,
+            i18n::text_with("notify-config-failed", &[("error", error)]),
+            now,
+        )
+    }
+
+    fn notify(
+        &mut self,
+        event: NotificationEvent,
+        body: String,
+        now: Instant,
+    ) -> Option<Notification> {
+        if !self.config.enabled || !self.config.is_enabled(event) {
+            return None;
+        }
+
+        let interval = self.config.min_interval();
+        if let Some(last) = self.last.get(&event) {
+            if now.saturating_duration_since(*last) < interval {
+                log::debug!("Rate limited notification: {}", body);
+                return None;
+            }
+        }
+        self.last.insert(event, now);
+
+        Some(Notification {
+            event,
+            title: i18n::text("app-name"),
+            body,
+        })
+    }
+}
+
+/// The message `key`, or `key-on` naming the monitors, e.g. "DISPLAY1, DISPLAY2"
+fn on_monitors(key: &str, monitors: &[String]) -> String {
+    if monitors.is_empty() {
+        return i18n::text(key);
+    }
+
+    let names: Vec<&str> = monitors
+        .iter()
+        .map(|name| name.trim_start_matches("\\\\.\\"))
+        .collect();
+    i18n::text_with(&format!("{}-on", key), &[("monitors", &names.join(", "))])
+}
+
+/// Copy text into a fixed-size, NUL-terminated UTF-16 buffer, truncating it if needed
+#[cfg(windows)]
+fn fill(buffer: &mut [u16], text: &str) {
+    let capacity = buffer.len() - 1;
+    for (slot, unit) in buffer.iter_mut().zip(text.encode_utf16().take(capacity)) {
+        *slot = unit;
+    }
+}
+
+/// Show a notification as a balloon of thide's notification icon, a toast on Windows 10 and later
+#[cfg(windows)]
+pub fn show(notification: &Notification) {
+    let Some(hwnd) = cli::find_ipc_window() else {
+        log::warn!(
+            "No IPC window to show a notification: {}",
+            notification.body
+        );
+        return;
+    };
+
+    let mut data = NOTIFYICONDATAW {
+        cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as u32,
+        hWnd: hwnd,
+        uID: NOTIFY_ICON_ID,
+        uFlags: NIF_INFO | NIF_ICON | NIF_MESSAGE | NIF_TIP,
+        uCallbackMessage: WM_THIDE_NOTIFY,
+        hIcon: unsafe { LoadIconW(None, IDI_INFORMATION) }.unwrap_or_default(),
+        dwInfoFlags: if notification.event.is_warning() {
+            NIIF_WARNING
+        } else {
+            NIIF_INFO
+        },
+        ..Default::default()
+    };
+    fill(&mut data.szTip, &notification.title);
+    fill(&mut data.szInfoTitle, &notification.title);
+    fill(&mut data.szInfo, &notification.body);
+
+    unsafe {
+        if !Shell_NotifyIconW(NIM_MODIFY, &data).as_bool()
+            && !Shell_NotifyIconW(NIM_ADD, &data).as_bool()
+        {
+            log::warn!("Failed to show notification: {}", notification.body);
+        }
+    }
+}
+
+/// Remove the notification icon once its balloon was dismissed
+#[cfg(windows)]
+pub fn on_callback(lparam: LPARAM) {
+    let event = lparam.0 as u32;
+    if event == NIN_BALLOONTIMEOUT || event == NIN_BALLOONUSERCLICK {
+        remove();
+    }
+}
+
+/// Remove the notification icon, if one is shown
+#[cfg(windows)]
+pub fn remove() {
+    let Some(hwnd) = cli::find_ipc_window() else {
+        return;
+    };
+
+    let data = NOTIFYICONDATAW {
+        cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as u32,
+        hWnd: hwnd,
+        uID: NOTIFY_ICON_ID,
+        ..Default::default()
+    };
+    unsafe {
+        let _ = Shell_NotifyIconW(NIM_DELETE, &data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AnimationConfig, HideConfig};
+    use crate::controller::{Command, Input};
+    use crate::testing::{self, Session};
+    use std::time::Duration;
+
+    /// A session whose transitions are turned into notifications
+    struct Notified {
+        session: Session,
+        notifier: Notifier,
+        now: Instant,
+    }
+
+    impl Notified {
+        fn start(config: NotificationsConfig) -> Self {
+            let session = Session::new(
+                testing::desktop(),
+                HideConfig::default(),
+                AnimationConfig::default(),
+            );
+            let mut notified = Self {
+                notifier: Notifier::new(config),
+                now: session.now,
+                session,
+            };
+
+            let started = notified.handle(notified.session.start_input());
+            assert_eq!(started, []);
+            notified
+        }
+
+        fn handle(&mut self, input: Input) -> Vec<Notification> {
+            let output = self.session.handle(input);
+            self.notifier.transitions(&output.transitions, self.now)
+        }
+
+        /// Run a command `secs` seconds after the previous one
+        fn command(&mut self, secs: u64, command: Command) -> Vec<NotificationEvent> {
+            self.now += Duration::from_secs(secs);
+            let notifications = self.handle(Input::Command(command));
+            notifications.iter().map(|n| n.event).collect()
+        }
+    }
+
+    fn everything() -> NotificationsConfig {
+        NotificationsConfig {
+            hidden: true,
+            shown: true,
+            explorer_restarted: true,
+            ..NotificationsConfig::default()
+        }
+    }
+
+    fn flapping(monitor: &str) -> FlapReport {
+        FlapReport {
+            monitor: monitor.to_string(),
+            rehides: 12,
+            window_secs: 10,
+            culprit: Some("fighter.exe".to_string()),
+            backoff_secs: 2,
+        }
+    }
+
+    #[test]
+    fn transitions_name_the_monitors() {
+        let mut session = Notified::start(everything());
+        session.now += Duration::from_secs(1);
+        let shown = session.handle(Input::Command(Command::Show));
+        assert_eq!(
+            shown,
+            [Notification {
+                event: NotificationEvent::Shown,
+                title: "Taskbar Hide".to_string(),
+                body: "Taskbar shown on DISPLAY1, DISPLAY2".to_string(),
+            }]
+        );
+
+        assert_eq!(
+            session.command(1, Command::Hide),
+            [NotificationEvent::Hidden]
+        );
+        assert_eq!(session.command(1, Command::Quit), []);
+    }
+
+    #[test]
+    fn each_event_is_rate_limited_on_its_own() {
+        let mut session = Notified::start(everything());
+
+        assert_eq!(
+            session.command(1, Command::Show),
+            [NotificationEvent::Shown]
+        );
+        assert_eq!(
+            session.command(1, Command::Hide),
+            [NotificationEvent::Hidden]
+        );
+        assert_eq!(session.command(10, Command::Show), []);
+        assert_eq!(session.command(10, Command::Hide), []);
+        // 30s after the first show
+        assert_eq!(
+            session.command(9, Command::Show),
+            [NotificationEvent::Shown]
+        );
+        assert_eq!(
+            session.command(1, Command::Hide),
+            [NotificationEvent::Hidden]
+        );
+    }
+
+    #[test]
+    fn routine_changes_are_quiet_by_default() {
+        let mut session = Notified::start(NotificationsConfig::default());
+        assert_eq!(session.command(1, Command::Show), []);
+        assert_eq!(session.command(1, Command::Hide), []);
+
+        let now = session.now;
+        let failed = session.notifier.config_failed("expected `=`", now).unwrap();
+        assert!(failed.event.is_warning());
+        assert_eq!(
+            failed.body,
+            "Failed to load the config, using defaults: expected `=`"
+        );
+    }
+
+    #[test]
+    fn disabled_notifications_stay_silent() {
+        let config = NotificationsConfig {
+            enabled: false,
+            ..everything()
+        };
+        let mut session = Notified::start(config);
+        assert_eq!(session.command(1, Command::Show), []);
+
+        let now = session.now;
+        assert_eq!(session.notifier.config_failed("broken", now), None);
+        let reports = [flapping("\\\\.\\DISPLAY1")];
+        assert_eq!(session.notifier.flapping(&reports, &[], now), []);
+    }
+
+    #[test]
+    fn flapping_is_reported_once_per_monitor() {
+        let mut notifier = Notifier::new(NotificationsConfig {
+            min_interval_secs: 0,
+            ..NotificationsConfig::default()
+        });
+        let now = Instant::now();
+        let first = [flapping("\\\\.\\DISPLAY1")];
+        let both = [flapping("\\\\.\\DISPLAY1"), flapping("\\\\.\\DISPLAY2")];
+
+        let notified = notifier.flapping(&first, &[], now);
+        assert_eq!(notified.len(), 1);
+        assert_eq!(notified[0].body, first[0].message());
+        assert!(notified[0].event.is_warning());
+
+        // Still flapping, already known
+        assert_eq!(notifier.flapping(&first, &first, now), []);
+
+        let notified = notifier.flapping(&both, &first, now);
+        assert_eq!(notified.len(), 1);
+        assert_eq!(notified[0].body, both[1].message());
+    }
+}
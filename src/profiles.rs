@@ -0,0 +1,161 @@
+use crate::config::Config;
+use crate::schema;
+use crate::strategy::HideStrategy;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Profile used when none was persisted, matches the classic thide behavior
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// A named bundle of desired state, hide strategy, rules and hotkeys
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    #[serde(skip)]
+    pub name: String,
+    /// Whether the taskbar is hidden in this profile
+    pub hidden: bool,
+    /// How the taskbar is hidden in this profile, the [hide] strategies when unset
+    pub strategy: Option<HideStrategy>,
+    /// Whether the user script decides per-monitor state
+    pub rules: bool,
+    /// Hotkey bindings, the global [hotkeys] table is used when unset
+    #[schemars(schema_with = "schema::hotkeys")]
+    pub hotkeys: Option<BTreeMap<String, String>>,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            hidden: true,
+            strategy: None,
+            rules: true,
+            hotkeys: None,
+        }
+    }
+}
+
+impl Profile {
+    fn builtin(name: &str, hidden: bool, strategy: Option<HideStrategy>, rules: bool) -> Self {
+        Self {
+            name: name.to_string(),
+            hidden,
+            strategy,
+            rules,
+            hotkeys: None,
+        }
+    }
+}
+
+/// Built-in profiles merged with the ones from the config file
+pub fn resolve(config: &Config) -> BTreeMap<String, Profile> {
+    let mut profiles: BTreeMap<String, Profile> = [
+        Profile::builtin(DEFAULT_PROFILE, true, None, true),
+        Profile::builtin("normal", false, None, true),
+        Profile::builtin("focus", true, Some(HideStrategy::AutoHide), false),
+        // Nothing may reveal the taskbar during a presentation, whatever [hide] picks
+        Profile::builtin("presentation", true, Some(HideStrategy::Hide), false),
+    ]
+    .into_iter()
+    .map(|profile| (profile.name.clone(), profile))
+    .collect();
+
+    for (name, profile) in &config.profiles {
+        profiles.insert(
+            name.clone(),
+            Profile {
+                name: name.clone(),
+                ..profile.clone()
+            },
+        );
+    }
+
+    for profile in profiles.values_mut() {
+        if profile.hotkeys.is_none() {
+            profile.hotkeys = Some(config.hotkeys.clone());
+        }
+    }
+
+    profiles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolved(config: &str) -> BTreeMap<String, Profile> {
+        resolve(&toml::from_str(config).unwrap())
+    }
+
+    #[test]
+    fn builtin_profiles() {
+        let profiles = resolved("");
+        let summary: Vec<_> = profiles
+            .values()
+            .map(|p| (p.name.as_str(), p.hidden, p.strategy, p.rules))
+            .collect();
+
+        assert_eq!(
+            summary,
+            [
+                (DEFAULT_PROFILE, true, None, true),
+                ("focus", true, Some(HideStrategy::AutoHide), false),
+                ("normal", false, None, true),
+                ("presentation", true, Some(HideStrategy::Hide), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn presentation_never_reveals_whatever_the_hide_strategy() {
+        let profiles =
+            resolved("[hide]\nstrategy = \"dim\"\n\n[hide.dim]\nreveal_on_hover = true\n");
+        assert_eq!(profiles["presentation"].strategy, Some(HideStrategy::Hide));
+    }
+
+    #[test]
+    fn user_profiles_replace_builtin_ones_and_add_new_ones() {
+        let profiles = resolved(
+            "[profiles.presentation]\nrules = true\n\n\
+             [profiles.gaming]\nhidden = true\nstrategy = \"off_screen\"\nrules = false\n",
+        );
+
+        // A user profile replaces the built-in one as a whole, unset fields take the defaults
+        let presentation = &profiles["presentation"];
+        assert_eq!(presentation.name, "presentation");
+        assert!(presentation.hidden && presentation.rules);
+        assert_eq!(presentation.strategy, None);
+
+        let gaming = &profiles["gaming"];
+        assert_eq!(gaming.name, "gaming");
+        assert_eq!(gaming.strategy, Some(HideStrategy::OffScreen));
+        assert!(!gaming.rules);
+        assert_eq!(profiles.len(), 5);
+        assert_eq!(profiles["focus"], resolved("")["focus"]);
+    }
+
+    #[test]
+    fn profiles_without_hotkeys_use_the_global_ones() {
+        let profiles = resolved(
+            "[hotkeys]\n\"Ctrl+Alt+T\" = \"toggle\"\n\n\
+             [profiles.focus.hotkeys]\n\"Ctrl+Alt+Q\" = \"quit\"\n",
+        );
+
+        let global = BTreeMap::from([("Ctrl+Alt+T".to_string(), "toggle".to_string())]);
+        assert_eq!(profiles["normal"].hotkeys.as_ref(), Some(&global));
+        assert_eq!(
+            profiles["focus"].hotkeys,
+            Some(BTreeMap::from([(
+                "Ctrl+Alt+Q".to_string(),
+                "quit".to_string()
+            )]))
+        );
+    }
+
+    #[test]
+    fn unknown_profile_keys_are_rejected() {
+        assert!(toml::from_str::<Config>("[profiles.focus]\ncolour = \"blue\"\n").is_err());
+    }
+}
@@ -0,0 +1,170 @@
+use crate::config::Config;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{RootSchema, Schema};
+use schemars::schema_for;
+
+/// JSON Schema of the config file, for completion and validation in editors
+pub fn config() -> RootSchema {
+    let mut schema = schema_for!(Config);
+    schema.schema.metadata().title = Some("THide configuration".to_string());
+    schema
+}
+
+/// The schema as written by `thide config schema`
+pub fn config_json() -> Result<String, serde_json::Error> {
+    Ok(serde_json::to_string_pretty(&config())? + "\n")
+}
+
+/// Hotkey bindings, key combinations like "Ctrl+Alt+T" mapped to the actions of
+/// `HotkeyAction::parse`
+pub fn hotkeys(_: &mut SchemaGenerator) -> Schema {
+    let schema = serde_json::json!({
+        "type": "object",
+        "additionalProperties": {
+            "anyOf": [
+                {
+                    "type": "string",
+                    "enum": ["show", "hide", "toggle", "quit"]
+                },
+                {
+                    "type": "string",
+                    "pattern": "^profile:.+$",
+                    "description": "Switch to a profile, e.g. profile:presentation"
+                }
+            ]
+        }
+    });
+    serde_json::from_value(schema).unwrap_or(Schema::Bool(true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Map, Value};
+
+    const COMMITTED: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/config.schema.json");
+
+    fn schema() -> Value {
+        serde_json::to_value(config()).unwrap()
+    }
+
+    /// A property's own schema, following a reference to its definition
+    fn resolve<'a>(root: &'a Value, node: &'a Value) -> &'a Value {
+        let reference = node["$ref"]
+            .as_str()
+            .or_else(|| node["allOf"][0]["$ref"].as_str());
+        match reference.and_then(|r| r.strip_prefix("#/definitions/")) {
+            Some(name) => &root["definitions"][name],
+            None => node,
+        }
+    }
+
+    fn properties(node: &Value) -> Option<&Map<String, Value>> {
+        node["properties"].as_object()
+    }
+
+    #[test]
+    fn committed_schema_is_up_to_date() {
+        let generated = config_json().unwrap();
+        if std::env::var_os("UPDATE_SCHEMA").is_some() {
+            std::fs::write(COMMITTED, &generated).unwrap();
+        }
+
+        let committed = std::fs::read_to_string(COMMITTED)
+            .unwrap()
+            .replace("\r\n", "\n");
+        assert!(
+            committed == generated,
+            "assets/config.schema.json is out of date, regenerate it with \
+             `UPDATE_SCHEMA=1 cargo test committed_schema_is_up_to_date`"
+        );
+    }
+
+    #[test]
+    fn every_option_is_described() {
+        let root = schema();
+        let mut pending = vec![(String::new(), &root)];
+        let mut visited = Vec::new();
+
+        while let Some((path, node)) = pending.pop() {
+            for (key, property) in properties(node).into_iter().flatten() {
+                let path = format!("{}{}", path, key);
+                let resolved = resolve(&root, property);
+                assert!(
+                    property.get("description").is_some() || resolved.get("description").is_some(),
+                    "{} has no description",
+                    path
+                );
+
+                if !visited.contains(&resolved) {
+                    visited.push(resolved);
+                    pending.push((format!("{}.", path), resolved));
+                }
+            }
+        }
+    }
+
+    /// Check that every key of a serialized config has a matching property
+    fn check_keys(root: &Value, node: &Value, value: &toml::Value, path: &str) {
+        let node = resolve(root, node);
+        match value {
+            toml::Value::Table(table) => {
+                for (key, value) in table {
+                    let child = match properties(node) {
+                        Some(properties) => properties.get(key),
+                        None => node.get("additionalProperties"),
+                    };
+                    let path = format!("{}.{}", path, key);
+                    let child = child.unwrap_or_else(|| panic!("{} is not in the schema", path));
+                    check_keys(root, child, value, &path);
+                }
+            }
+            toml::Value::Array(items) => {
+                for item in items {
+                    check_keys(root, &node["items"], item, path);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn schema_covers_the_config_types() {
+        let root = schema();
+        let defaults = toml::Value::try_from(Config::default()).unwrap();
+        check_keys(&root, &root, &defaults, "config");
+
+        // Unknown keys are rejected by the schema like they are when loading
+        assert_eq!(root["additionalProperties"], Value::Bool(false));
+        let hide = resolve(&root, &root["properties"]["hide"]);
+        assert_eq!(hide["additionalProperties"], Value::Bool(false));
+    }
+
+    #[test]
+    fn choices_are_listed() {
+        let root = schema();
+        let strategy = resolve(&root, &root["properties"]["hide"]);
+        let strategy = resolve(&root, &strategy["properties"]["strategy"]);
+        let values: Vec<&str> = strategy["oneOf"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .flat_map(|choice| choice["enum"].as_array().unwrap())
+            .filter_map(Value::as_str)
+            .collect();
+        assert_eq!(
+            values,
+            [
+                "hide",
+                "auto_hide",
+                "off_screen",
+                "transparent",
+                "strip",
+                "dim"
+            ]
+        );
+
+        let hotkeys = &root["properties"]["hotkeys"]["additionalProperties"]["anyOf"];
+        assert_eq!(hotkeys[0]["enum"][3], "quit");
+    }
+}
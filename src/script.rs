@@ -0,0 +1,573 @@
+use crate::config::ScriptConfig;
+use crate::workarea::MonitorGeometry;
+#[cfg(windows)]
+use crate::{get_process_name, window_monitor};
+use rhai::{Dynamic, Engine, EvalAltResult, Scope, AST};
+use serde::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+#[cfg(windows)]
+use windows::Win32::Foundation::{BOOL, HWND, LPARAM};
+#[cfg(windows)]
+use windows::Win32::UI::WindowsAndMessaging::{
+    EnumWindows, GetClassNameW, GetForegroundWindow, GetWindowTextW, IsWindowVisible,
+};
+
+// Sandbox limits for a single evaluation
+const MAX_OPERATIONS: u64 = 1_000_000;
+const MAX_CALL_LEVELS: usize = 32;
+const MAX_STRING_SIZE: usize = 64 * 1024;
+const MAX_COLLECTION_SIZE: usize = 10_000;
+
+/// A top-level window as seen by the script
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowInfo {
+    pub hwnd: i64,
+    pub title: String,
+    pub class: String,
+    pub process: String,
+    pub monitor: String,
+}
+
+/// A display monitor as seen by the script
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonitorInfo {
+    /// 1-based monitor number
+    pub index: i64,
+    /// Device name, e.g. \\.\DISPLAY1
+    pub name: String,
+    pub primary: bool,
+    pub width: i64,
+    pub height: i64,
+}
+
+impl From<&MonitorGeometry> for MonitorInfo {
+    fn from(monitor: &MonitorGeometry) -> Self {
+        Self {
+            index: monitor.index as i64,
+            name: monitor.name.clone(),
+            primary: monitor.primary,
+            width: monitor.rect.width() as i64,
+            height: monitor.rect.height() as i64,
+        }
+    }
+}
+
+/// Local time when the snapshot was taken
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TimeInfo {
+    pub year: i64,
+    pub month: i64,
+    pub day: i64,
+    pub hour: i64,
+    pub minute: i64,
+    pub second: i64,
+    /// 0 = Sunday
+    pub weekday: i64,
+}
+
+impl TimeInfo {
+    /// The current local time
+    #[cfg(windows)]
+    pub fn now() -> Self {
+        let now = unsafe { windows::Win32::System::SystemInformation::GetLocalTime() };
+
+        Self {
+            year: now.wYear as i64,
+            month: now.wMonth as i64,
+            day: now.wDay as i64,
+            hour: now.wHour as i64,
+            minute: now.wMinute as i64,
+            second: now.wSecond as i64,
+            weekday: now.wDayOfWeek as i64,
+        }
+    }
+
+    /// Without Win32 there's no local time, scenarios and traces bring their own
+    #[cfg(not(windows))]
+    pub fn now() -> Self {
+        Self::default()
+    }
+}
+
+/// Desktop state passed to the script as the `snapshot` constant
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub foreground: Option<WindowInfo>,
+    pub windows: Vec<WindowInfo>,
+    pub monitors: Vec<MonitorInfo>,
+    pub time: TimeInfo,
+}
+
+impl Snapshot {
+    /// Capture the current desktop state
+    #[cfg(windows)]
+    pub fn capture() -> Self {
+        unsafe {
+            let foreground = GetForegroundWindow();
+
+            Self {
+                foreground: (!foreground.0.is_null()).then(|| window_info(foreground)),
+                windows: enumerate_windows().into_iter().map(window_info).collect(),
+                monitors: crate::workarea::monitors()
+                    .iter()
+                    .filter(|monitor| !monitor.name.is_empty())
+                    .map(MonitorInfo::from)
+                    .collect(),
+                time: TimeInfo::now(),
+            }
+        }
+    }
+
+    /// Load a recorded snapshot from a JSON file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    /// Save the snapshot as a JSON file
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// A compiled user script with a sandboxed engine
+pub struct ScriptRunner {
+    engine: Engine,
+    ast: AST,
+    timeout: Duration,
+    /// Process names for get_process_name, filled from the snapshot
+    processes: Rc<RefCell<HashMap<i64, String>>>,
+    started: Rc<Cell<Instant>>,
+}
+
+impl ScriptRunner {
+    /// Compile a script file
+    pub fn load(
+        path: impl AsRef<Path>,
+        timeout: Duration,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let source = std::fs::read_to_string(path)?;
+
+        let mut engine = Engine::new();
+        engine
+            .set_max_operations(MAX_OPERATIONS)
+            .set_max_call_levels(MAX_CALL_LEVELS)
+            .set_max_string_size(MAX_STRING_SIZE)
+            .set_max_array_size(MAX_COLLECTION_SIZE)
+            .set_max_map_size(MAX_COLLECTION_SIZE)
+            .set_module_resolver(rhai::module_resolvers::DummyModuleResolver::new())
+            .on_print(|text| log::info!("Script: {}", text));
+
+        let started = Rc::new(Cell::new(Instant::now()));
+        let progress_started = Rc::clone(&started);
+        engine.on_progress(move |_| {
+            (progress_started.get().elapsed() > timeout).then(|| Dynamic::from("timeout"))
+        });
+
+        let processes = Rc::new(RefCell::new(HashMap::new()));
+        let script_processes = Rc::clone(&processes);
+        engine.register_fn("get_process_name", move |hwnd: i64| -> String {
+            script_processes
+                .borrow()
+                .get(&hwnd)
+                .cloned()
+                .unwrap_or_default()
+        });
+
+        let ast = engine.compile(source)?;
+
+        Ok(Self {
+            engine,
+            ast,
+            timeout,
+            processes,
+            started,
+        })
+    }
+
+    /// Evaluate the script, returning the hidden state per monitor device name
+    ///
+    /// An empty map means the script made no decision.
+    pub fn evaluate(
+        &self,
+        snapshot: &Snapshot,
+    ) -> Result<BTreeMap<String, bool>, Box<dyn std::error::Error>> {
+        *self.processes.borrow_mut() = snapshot
+            .windows
+            .iter()
+            .chain(snapshot.foreground.iter())
+            .map(|window| (window.hwnd, window.process.clone()))
+            .collect();
+
+        let mut scope = Scope::new();
+        scope.push_constant("snapshot", rhai::serde::to_dynamic(snapshot)?);
+
+        self.started.set(Instant::now());
+        let result = match self
+            .engine
+            .eval_ast_with_scope::<Dynamic>(&mut scope, &self.ast)
+        {
+            Ok(result) => result,
+            Err(e) if matches!(*e, EvalAltResult::ErrorTerminated(..)) => {
+                return Err(
+                    format!("script timed out after {} ms", self.timeout.as_millis()).into(),
+                );
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        parse_decision(result, snapshot)
+    }
+}
+
+/// Convert the script result into per-monitor hidden states
+///
+/// Accepted results: `()` for no decision, `"hide"`/`"show"` for every monitor, or a map
+/// from monitor number, device name or `"*"` to `"hide"`/`"show"`.
+fn parse_decision(
+    result: Dynamic,
+    snapshot: &Snapshot,
+) -> Result<BTreeMap<String, bool>, Box<dyn std::error::Error>> {
+    let mut decision = BTreeMap::new();
+
+    if result.is_unit() {
+        return Ok(decision);
+    }
+
+    if result.is_string() {
+        let hidden = parse_visibility(&result)?;
+        for monitor in &snapshot.monitors {
+            decision.insert(monitor.name.clone(), hidden);
+        }
+        return Ok(decision);
+    }
+
+    let type_name = result.type_name();
+    let Some(map) = result.try_cast::<rhai::Map>() else {
+        return Err(format!(
+            "script must return \"hide\", \"show\", a map or (), got {}",
+            type_name
+        )
+        .into());
+    };
+
+    // The wildcard applies first so specific monitors can override it
+    if let Some(value) = map.get("*") {
+        let hidden = parse_visibility(value)?;
+        for monitor in &snapshot.monitors {
+            decision.insert(monitor.name.clone(), hidden);
+        }
+    }
+
+    for (key, value) in map.iter().filter(|(key, _)| key.as_str() != "*") {
+        let name =
+            resolve_monitor(key, snapshot).ok_or_else(|| format!("unknown monitor `{}`", key))?;
+        decision.insert(name, parse_visibility(value)?);
+    }
+
+    Ok(decision)
+}
+
+fn parse_visibility(value: &Dynamic) -> Result<bool, Box<dyn std::error::Error>> {
+    let text = value
+        .clone()
+        .into_string()
+        .map_err(|type_name| format!("expected \"hide\" or \"show\", got {}", type_name))?;
+
+    match text.to_lowercase().as_str() {
+        "hide" | "hidden" => Ok(true),
+        "show" | "visible" => Ok(false),
+        other => Err(format!("expected \"hide\" or \"show\", got \"{}\"", other).into()),
+    }
+}
+
+/// Resolve a monitor number or device name (with or without the \\.\ prefix)
+fn resolve_monitor(key: &str, snapshot: &Snapshot) -> Option<String> {
+    if let Ok(index) = key.parse::<i64>() {
+        return snapshot
+            .monitors
+            .iter()
+            .find(|monitor| monitor.index == index)
+            .map(|monitor| monitor.name.clone());
+    }
+
+    snapshot
+        .monitors
+        .iter()
+        .find(|monitor| {
+            monitor.name.eq_ignore_ascii_case(key)
+                || monitor
+                    .name
+                    .trim_start_matches("\\\\.\\")
+                    .eq_ignore_ascii_case(key)
+        })
+        .map(|monitor| monitor.name.clone())
+}
+
+/// Evaluate the configured script periodically on a background thread
+///
+/// `send` receives every changed decision and returns false once the receiver is gone.
+pub fn spawn_script_thread(
+    config: ScriptConfig,
+    send: impl Fn(BTreeMap<String, bool>) -> bool + Send + 'static,
+) {
+    std::thread::spawn(move || {
+        let Some(path) = config.path else {
+            return;
+        };
+
+        let runner = match ScriptRunner::load(&path, Duration::from_millis(config.timeout_ms)) {
+            Ok(runner) => runner,
+            Err(e) => {
+                log::error!("Failed to load script {}: {}", path.display(), e);
+                return;
+            }
+        };
+        log::info!("Loaded script {}", path.display());
+
+        let mut last_decision = None;
+        let mut last_error = None;
+
+        loop {
+            let snapshot = crate::backend::current().snapshot();
+            let decision = next_decision(&runner, &snapshot, &path, &mut last_error);
+
+            if last_decision.as_ref() != Some(&decision) {
+                if !send(decision.clone()) {
+                    return;
+                }
+                last_decision = Some(decision);
+            }
+
+            std::thread::sleep(Duration::from_millis(config.interval_ms));
+        }
+    });
+}
+
+/// Evaluate the script once, falling back to no decision, and so the tray state, if it fails
+///
+/// Only reports each distinct error once, `last_error` holds the previous one.
+fn next_decision(
+    runner: &ScriptRunner,
+    snapshot: &Snapshot,
+    path: &Path,
+    last_error: &mut Option<String>,
+) -> BTreeMap<String, bool> {
+    match runner.evaluate(snapshot) {
+        Ok(decision) => {
+            *last_error = None;
+            decision
+        }
+        Err(e) => {
+            let message = e.to_string();
+            if last_error.as_ref() != Some(&message) {
+                log::error!("Script {} failed: {}", path.display(), message);
+                *last_error = Some(message);
+            }
+            BTreeMap::new()
+        }
+    }
+}
+
+#[cfg(windows)]
+fn window_info(hwnd: HWND) -> WindowInfo {
+    unsafe {
+        let mut title = [0u16; 512];
+        let title_len = GetWindowTextW(hwnd, &mut title).max(0) as usize;
+        let mut class = [0u16; 256];
+        let class_len = GetClassNameW(hwnd, &mut class).max(0) as usize;
+
+        WindowInfo {
+            hwnd: hwnd.0 as isize as i64,
+            title: String::from_utf16_lossy(&title[..title_len]),
+            class: String::from_utf16_lossy(&class[..class_len]),
+            process: get_process_name(hwnd).unwrap_or_default(),
+            monitor: window_monitor(hwnd).unwrap_or_default(),
+        }
+    }
+}
+
+/// List visible top-level windows that have a title
+#[cfg(windows)]
+fn enumerate_windows() -> Vec<HWND> {
+    unsafe extern "system" fn callback(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        let windows = &mut *(lparam.0 as *mut Vec<HWND>);
+        let mut title = [0u16; 2];
+        if IsWindowVisible(hwnd).as_bool() && GetWindowTextW(hwnd, &mut title) > 0 {
+            windows.push(hwnd);
+        }
+        BOOL(1)
+    }
+
+    let mut windows: Vec<HWND> = Vec::new();
+    unsafe {
+        let _ = EnumWindows(Some(callback), LPARAM(&mut windows as *mut _ as isize));
+    }
+    windows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::Desktop;
+    use crate::testing;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const DISPLAY1: &str = "\\\\.\\DISPLAY1";
+    const DISPLAY2: &str = "\\\\.\\DISPLAY2";
+
+    fn snapshot() -> Snapshot {
+        testing::scenario().snapshot()
+    }
+
+    /// Decision for the result of a script expression
+    fn decide(expression: &str) -> Result<BTreeMap<String, bool>, String> {
+        let result = Engine::new().eval::<Dynamic>(expression).unwrap();
+        parse_decision(result, &snapshot()).map_err(|e| e.to_string())
+    }
+
+    /// Run a script source through the whole runner, as loaded from a file
+    fn run(
+        source: &str,
+        timeout: Duration,
+        snapshots: &[Snapshot],
+    ) -> Vec<(BTreeMap<String, bool>, Option<String>)> {
+        static SCRIPTS: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "thide-{}-script-{}.rhai",
+            std::process::id(),
+            SCRIPTS.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::write(&path, source).unwrap();
+        let runner = ScriptRunner::load(&path, timeout);
+        std::fs::remove_file(&path).unwrap();
+        let runner = runner.unwrap();
+
+        let mut last_error = None;
+        snapshots
+            .iter()
+            .map(|snapshot| {
+                let decision = next_decision(&runner, snapshot, &path, &mut last_error);
+                (decision, last_error.clone())
+            })
+            .collect()
+    }
+
+    fn decision(states: &[(&str, bool)]) -> Result<BTreeMap<String, bool>, String> {
+        Ok(states
+            .iter()
+            .map(|(name, hidden)| (name.to_string(), *hidden))
+            .collect())
+    }
+
+    #[test]
+    fn snapshots_share_the_desktop_monitors() {
+        let monitors = snapshot().monitors;
+
+        assert_eq!(monitors.len(), 2);
+        assert_eq!(
+            (monitors[0].index, monitors[0].name.as_str()),
+            (1, DISPLAY1)
+        );
+        assert!(monitors[0].primary);
+        assert!(!monitors[1].primary);
+        assert_eq!((monitors[1].width, monitors[1].height), (2560, 1440));
+    }
+
+    #[test]
+    fn unit_and_strings_decide_for_every_monitor() {
+        assert_eq!(decide("()"), decision(&[]));
+        assert_eq!(
+            decide(r#""hide""#),
+            decision(&[(DISPLAY1, true), (DISPLAY2, true)])
+        );
+        assert_eq!(
+            decide(r#""Visible""#),
+            decision(&[(DISPLAY1, false), (DISPLAY2, false)])
+        );
+    }
+
+    #[test]
+    fn maps_decide_per_monitor_over_the_wildcard() {
+        assert_eq!(decide(r#"#{ "2": "hide" }"#), decision(&[(DISPLAY2, true)]));
+        assert_eq!(
+            decide(r#"#{ "*": "hide", "display1": "show" }"#),
+            decision(&[(DISPLAY1, false), (DISPLAY2, true)])
+        );
+        assert_eq!(decide("#{}"), decision(&[]));
+    }
+
+    #[test]
+    fn invalid_results_are_errors() {
+        assert!(decide("42").unwrap_err().contains("got i64"));
+        assert!(decide(r#""maybe""#).unwrap_err().contains("\"maybe\""));
+        assert!(decide(r#"#{ "1": true }"#)
+            .unwrap_err()
+            .contains("got bool"));
+        assert_eq!(
+            decide(r#"#{ "3": "hide" }"#).unwrap_err(),
+            "unknown monitor `3`"
+        );
+    }
+
+    #[test]
+    fn monitors_resolve_by_number_or_device_name() {
+        let snapshot = snapshot();
+        let resolve = |key| resolve_monitor(key, &snapshot);
+
+        assert_eq!(resolve("1").as_deref(), Some(DISPLAY1));
+        assert_eq!(resolve("2").as_deref(), Some(DISPLAY2));
+        assert_eq!(resolve(DISPLAY2).as_deref(), Some(DISPLAY2));
+        assert_eq!(resolve("display2").as_deref(), Some(DISPLAY2));
+        assert_eq!(resolve("\\\\.\\display1").as_deref(), Some(DISPLAY1));
+        assert_eq!(resolve("0"), None);
+        assert_eq!(resolve("DISPLAY3"), None);
+        assert_eq!(resolve(""), None);
+    }
+
+    #[test]
+    fn endless_scripts_hit_a_limit_and_make_no_decision() {
+        let results = run("loop {}", Duration::ZERO, &[snapshot()]);
+        assert_eq!(results[0].0, BTreeMap::new());
+        assert_eq!(results[0].1.as_deref(), Some("script timed out after 0 ms"));
+
+        let results = run("loop {}", Duration::from_secs(600), &[snapshot()]);
+        assert_eq!(results[0].0, BTreeMap::new());
+        assert!(results[0]
+            .1
+            .as_ref()
+            .unwrap()
+            .contains("Too many operations"));
+    }
+
+    #[test]
+    fn failing_evaluations_fall_back_until_the_script_decides_again() {
+        let single = Snapshot {
+            monitors: snapshot().monitors[..1].to_vec(),
+            ..snapshot()
+        };
+        let script = r#"
+            if snapshot.monitors.len() > 1 { throw "too many monitors" }
+            "hide"
+        "#;
+
+        let results = run(script, Duration::from_secs(5), &[snapshot(), single]);
+        assert_eq!(results[0].0, BTreeMap::new());
+        assert!(results[0].1.as_ref().unwrap().contains("too many monitors"));
+        assert_eq!(Ok(results[1].0.clone()), decision(&[(DISPLAY1, true)]));
+        assert_eq!(results[1].1, None);
+    }
+
+    #[test]
+    fn wrong_result_types_make_no_decision() {
+        let results = run("[1, 2]", Duration::from_secs(5), &[snapshot(), snapshot()]);
+        for (decision, error) in results {
+            assert_eq!(decision, BTreeMap::new());
+            assert!(error.unwrap().contains("got array"));
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use crate::backend::{Action, Backend, Desktop, Mode, WindowId};
+use crate::elements::ShellElement;
+use crate::geometry::{Edge, Rect, DEFAULT_DPI};
+use crate::script::{MonitorInfo, Snapshot, TimeInfo, WindowInfo};
+use crate::targets::{self, WindowIdentity};
+use crate::workarea::MonitorGeometry;
+use serde::Deserialize;
+use std::path::Path;
+
+/// A monitor of the simulated desktop
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ScenarioMonitor {
+    /// Device name, e.g. \\.\DISPLAY1
+    pub name: String,
+    pub rect: Rect,
+    /// Work area, the whole monitor if not given
+    pub work: Option<Rect>,
+    pub dpi: u32,
+    pub primary: bool,
+}
+
+impl Default for ScenarioMonitor {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            rect: Rect::default(),
+            work: None,
+            dpi: DEFAULT_DPI,
+            primary: false,
+        }
+    }
+}
+
+/// A top-level window of the simulated desktop
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ScenarioWindow {
+    pub hwnd: WindowId,
+    pub class: String,
+    pub process: String,
+    pub title: String,
+    /// Device name of the monitor the window is on
+    pub monitor: String,
+    pub visible: bool,
+    /// Screen rect, the bottom 48 pixels of its monitor if not given
+    pub rect: Option<Rect>,
+    /// Alpha of a layered window
+    pub alpha: Option<u8>,
+    /// Shell element the window belongs to, if any
+    pub element: Option<ShellElement>,
+}
+
+impl Default for ScenarioWindow {
+    fn default() -> Self {
+        Self {
+            hwnd: 0,
+            class: String::new(),
+            process: String::new(),
+            title: String::new(),
+            monitor: String::new(),
+            visible: true,
+            rect: None,
+            alpha: None,
+            element: None,
+        }
+    }
+}
+
+/// A fake desktop loaded from a JSON file, used to reproduce bug reports
+///
+/// The scenario itself never changes, wrap it in an `Overlay` to track thide's actions.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Scenario {
+    pub monitors: Vec<ScenarioMonitor>,
+    pub windows: Vec<ScenarioWindow>,
+    /// Handle of the foreground window
+    pub foreground: Option<WindowId>,
+    /// Mouse cursor position, e.g. [100, 1060]
+    pub cursor: Option<(i32, i32)>,
+    /// Initial ABM_GETSTATE of the taskbar
+    pub appbar_state: u32,
+    /// Local time seen by the script, the real time if not given
+    pub time: Option<TimeInfo>,
+}
+
+impl Scenario {
+    /// Load a scenario from a JSON file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        let scenario: Self = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+
+        for window in &scenario.windows {
+            if !scenario.monitors.iter().any(|m| m.name == window.monitor) {
+                return Err(format!(
+                    "Window {:#x} is on unknown monitor '{}'",
+                    window.hwnd, window.monitor
+                )
+                .into());
+            }
+        }
+
+        Ok(scenario)
+    }
+
+    fn window(&self, window: WindowId) -> Option<&ScenarioWindow> {
+        self.windows.iter().find(|w| w.hwnd == window)
+    }
+
+    fn window_info(window: &ScenarioWindow) -> WindowInfo {
+        WindowInfo {
+            hwnd: window.hwnd as i64,
+            title: window.title.clone(),
+            class: window.class.clone(),
+            process: window.process.clone(),
+            monitor: window.monitor.clone(),
+        }
+    }
+}
+
+impl Desktop for Scenario {
+    fn taskbars(&self) -> Vec<WindowId> {
+        targets::select(self.windows.iter().map(|window| {
+            let identity = WindowIdentity {
+                class: window.class.clone(),
+                process: window.process.clone(),
+                title: window.title.clone(),
+            };
+            (window.hwnd, identity)
+        }))
+    }
+
+    fn element_windows(&self, element: ShellElement) -> Vec<WindowId> {
+        self.windows
+            .iter()
+            .filter(|window| window.element == Some(element))
+            .map(|window| window.hwnd)
+            .collect()
+    }
+
+    fn monitors(&self) -> Vec<MonitorGeometry> {
+        self.monitors
+            .iter()
+            .enumerate()
+            .map(|(i, monitor)| MonitorGeometry {
+                index: i + 1,
+                name: monitor.name.clone(),
+                rect: monitor.rect,
+                work: monitor.work.unwrap_or(monitor.rect),
+                dpi: monitor.dpi,
+                primary: monitor.primary,
+            })
+            .collect()
+    }
+
+    fn window_monitor(&self, window: WindowId) -> Option<String> {
+        self.window(window).map(|window| window.monitor.clone())
+    }
+
+    fn is_visible(&self, window: WindowId) -> Option<bool> {
+        self.window(window).map(|window| window.visible)
+    }
+
+    fn window_rect(&self, window: WindowId) -> Option<Rect> {
+        let window = self.window(window)?;
+        window.rect.or_else(|| {
+            let monitor = self.monitors.iter().find(|m| m.name == window.monitor)?;
+            Some(monitor.rect.strip(Edge::Bottom, 48))
+        })
+    }
+
+    fn window_alpha(&self, window: WindowId) -> Option<u8> {
+        self.window(window)?.alpha
+    }
+
+    fn cursor_position(&self) -> Option<(i32, i32)> {
+        self.cursor
+    }
+
+    fn foreground_process(&self) -> Option<String> {
+        self.window(self.foreground?)
+            .map(|window| window.process.clone())
+    }
+
+    fn appbar_state(&self) -> u32 {
+        self.appbar_state
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        // Scripts see what Snapshot::capture would: visible windows that have a title
+        Snapshot {
+            foreground: self
+                .foreground
+                .and_then(|hwnd| self.window(hwnd))
+                .map(Self::window_info),
+            windows: self
+                .windows
+                .iter()
+                .filter(|window| window.visible && !window.title.is_empty())
+                .map(Self::window_info)
+                .collect(),
+            monitors: self.monitors().iter().map(MonitorInfo::from).collect(),
+            time: self.time.clone().unwrap_or_else(TimeInfo::now),
+        }
+    }
+}
+
+impl Backend for Scenario {
+    fn mode(&self) -> Mode {
+        Mode::Simulated
+    }
+
+    fn perform(&self, action: Action) {
+        log::debug!("Scenario ignores {}", action);
+    }
+}
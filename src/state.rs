@@ -0,0 +1,252 @@
+use crate::config::data_dir;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const STATE_VERSION: u32 = 1;
+
+/// How the taskbar state is chosen when thide starts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupMode {
+    /// Start with the taskbar hidden
+    AlwaysHide,
+    /// Start with the taskbar shown
+    AlwaysShow,
+    /// Restore the state thide had when it last ran
+    #[default]
+    Remember,
+}
+
+/// Runtime state kept across restarts
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuntimeState {
+    pub version: u32,
+    /// Last desired state for monitors without an override
+    pub hidden: Option<bool>,
+    /// Last per-monitor overrides keyed by device name
+    pub monitors: BTreeMap<String, bool>,
+    pub active_profile: Option<String>,
+    /// Unix time at which a snoozed taskbar is hidden again
+    pub snooze_until: Option<u64>,
+}
+
+impl RuntimeState {
+    /// Time left until the snooze ends, None if there's no snooze or it is over
+    pub fn snooze_remaining(&self) -> Option<Duration> {
+        let until = UNIX_EPOCH + Duration::from_secs(self.snooze_until?);
+        until.duration_since(SystemTime::now()).ok()
+    }
+}
+
+/// Seconds since the Unix epoch at `now + after`
+pub fn unix_time_after(after: Duration) -> u64 {
+    (SystemTime::now() + after)
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// State file of the current instance (%LOCALAPPDATA%\thide\state.json)
+fn state_path() -> Option<PathBuf> {
+    let name = match &crate::instance::current().name {
+        Some(instance) => format!("state.{}.json", instance),
+        None => "state.json".to_string(),
+    };
+
+    data_dir().map(|dir| dir.join(name))
+}
+
+/// Exclusive lock serializing writers, released when the file is closed
+fn lock(path: &Path) -> Option<File> {
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path.with_extension("lock"))
+        .ok()?;
+    file.lock().ok()?;
+    Some(file)
+}
+
+/// Read the state file, a corrupt file is moved aside and yields the defaults
+fn read(path: &Path) -> RuntimeState {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return RuntimeState::default(),
+        Err(e) => {
+            log::warn!("Failed to read {}: {}", path.display(), e);
+            return RuntimeState::default();
+        }
+    };
+
+    match serde_json::from_str(&contents) {
+        Ok(state) => state,
+        Err(e) => {
+            log::warn!("Ignoring corrupt state file {}: {}", path.display(), e);
+            let _ = std::fs::rename(path, path.with_extension("json.corrupt"));
+            RuntimeState::default()
+        }
+    }
+}
+
+/// Write the state to a temporary file and move it over the old one
+fn write(path: &Path, state: &RuntimeState) -> Result<(), Box<dyn std::error::Error>> {
+    let temp = path.with_extension(format!("json.{}.tmp", std::process::id()));
+
+    let mut file = File::create(&temp)?;
+    file.write_all(serde_json::to_string_pretty(state)?.as_bytes())?;
+    file.sync_all()?;
+    drop(file);
+
+    std::fs::rename(&temp, path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&temp);
+    })?;
+    Ok(())
+}
+
+/// Load the persisted state, defaults if there is none
+pub fn load() -> RuntimeState {
+    state_path()
+        .map(|path| load_from(&path))
+        .unwrap_or_default()
+}
+
+/// Change the persisted state, re-reading it first so other writers' fields are kept
+pub fn update(change: impl FnOnce(&mut RuntimeState)) {
+    if let Some(path) = state_path() {
+        update_at(&path, change);
+    }
+}
+
+/// Load the state file at `path`
+fn load_from(path: &Path) -> RuntimeState {
+    let _lock = lock(path);
+    read(path)
+}
+
+/// Change the state file at `path` while holding its lock
+fn update_at(path: &Path, change: impl FnOnce(&mut RuntimeState)) {
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+
+    let _lock = lock(path);
+    let mut state = read(path);
+    change(&mut state);
+    state.version = STATE_VERSION;
+
+    if let Err(e) = write(path, &state) {
+        log::warn!("Failed to save state to {}: {}", path.display(), e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An empty directory of its own for each test
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("thide-{}-state-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn files(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| !name.ends_with(".lock"))
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn missing_files_yield_the_defaults() {
+        let dir = temp_dir("missing");
+        assert_eq!(load_from(&dir.join("state.json")), RuntimeState::default());
+    }
+
+    #[test]
+    fn corrupt_files_are_moved_aside() {
+        let dir = temp_dir("corrupt");
+        let path = dir.join("state.json");
+        std::fs::write(&path, "{\"hidden\": tru").unwrap();
+
+        assert_eq!(load_from(&path), RuntimeState::default());
+        assert_eq!(files(&dir), ["state.json.corrupt"]);
+        assert_eq!(
+            std::fs::read_to_string(dir.join("state.json.corrupt")).unwrap(),
+            "{\"hidden\": tru"
+        );
+
+        // The next update starts over from the defaults
+        update_at(&path, |state| state.hidden = Some(true));
+        assert_eq!(load_from(&path).hidden, Some(true));
+    }
+
+    #[test]
+    fn updates_replace_the_file_without_leaving_temporary_ones() {
+        let dir = temp_dir("replace");
+        let path = dir.join("state.json");
+
+        update_at(&path, |state| {
+            state.active_profile = Some("focus".to_string())
+        });
+        update_at(&path, |state| state.hidden = Some(false));
+        assert_eq!(files(&dir), ["state.json"]);
+
+        let state: RuntimeState =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(state.version, STATE_VERSION);
+        assert_eq!(state.active_profile.as_deref(), Some("focus"));
+        assert_eq!(state.hidden, Some(false));
+    }
+
+    #[test]
+    fn failed_writes_keep_the_old_file_and_remove_the_temporary_one() {
+        let dir = temp_dir("failed");
+        // A directory in place of the state file makes the final rename fail
+        let path = dir.join("state.json");
+        std::fs::create_dir(&path).unwrap();
+
+        assert!(write(&path, &RuntimeState::default()).is_err());
+        assert_eq!(files(&dir), ["state.json"]);
+        assert!(path.is_dir());
+    }
+
+    #[test]
+    fn concurrent_writers_keep_each_others_fields() {
+        let dir = temp_dir("concurrent");
+        let path = dir.join("state.json");
+
+        let writers: Vec<_> = (0..4)
+            .map(|writer| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    for i in 0..25 {
+                        update_at(&path, |state| {
+                            state
+                                .monitors
+                                .insert(format!("writer{}-{}", writer, i), true);
+                        });
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        let state = load_from(&path);
+        assert_eq!(state.monitors.len(), 100);
+        assert_eq!(files(&dir), ["state.json"]);
+    }
+}
@@ -0,0 +1,63 @@
+use crate::flapping::FlapReport;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Mutex, PoisonError};
+#[cfg(windows)]
+use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
+#[cfg(windows)]
+use windows::Win32::System::DataExchange::COPYDATASTRUCT;
+#[cfg(windows)]
+use windows::Win32::UI::WindowsAndMessaging::{SendMessageW, WM_COPYDATA};
+
+/// State of the running instance reported by `thide status`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Status {
+    pub pid: u32,
+    /// live, dry-run or simulated
+    pub mode: String,
+    pub profile: String,
+    pub hidden: bool,
+    /// Per-monitor overrides keyed by device name
+    pub monitors: BTreeMap<String, bool>,
+    pub snooze_remaining_secs: Option<u64>,
+    pub flapping: Vec<FlapReport>,
+}
+
+static CURRENT: Mutex<Option<Status>> = Mutex::new(None);
+
+/// Replace the status answered to `thide status`
+pub fn publish(status: Status) {
+    CURRENT
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .replace(status);
+}
+
+/// Send the current status as JSON to the window that asked for it
+#[cfg(windows)]
+pub fn reply(to: HWND, kind: usize) {
+    let status = CURRENT
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+        .unwrap_or_default();
+    let Ok(json) = serde_json::to_string(&status) else {
+        return;
+    };
+
+    unsafe {
+        let copy_data = COPYDATASTRUCT {
+            dwData: kind,
+            cbData: json.len() as u32,
+            lpData: json.as_ptr() as *mut _,
+        };
+
+        SendMessageW(
+            to,
+            WM_COPYDATA,
+            WPARAM(0),
+            LPARAM(&copy_data as *const _ as isize),
+        );
+    }
+}
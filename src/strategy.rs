@@ -0,0 +1,360 @@
+use crate::backend::{Action, Desktop, WindowId};
+use crate::config::DimConfig;
+use crate::geometry::{docked_edge, Rect};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Where off-screen taskbars are moved, the same spot Windows parks minimized windows at
+const OFF_SCREEN_POSITION: i32 = -32000;
+
+/// How a hidden taskbar window is hidden
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HideStrategy {
+    /// ShowWindow(SW_HIDE)
+    #[default]
+    Hide,
+    /// Only force auto-hide with ABM_SETSTATE, the window itself is left alone
+    AutoHide,
+    /// Move the window outside every monitor
+    OffScreen,
+    /// Make the window a fully transparent layered window
+    Transparent,
+    /// Shrink the window to a one-pixel strip along its edge
+    Strip,
+    /// Keep the window visible at a reduced opacity
+    Dim,
+}
+
+impl HideStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HideStrategy::Hide => "hide",
+            HideStrategy::AutoHide => "auto_hide",
+            HideStrategy::OffScreen => "off_screen",
+            HideStrategy::Transparent => "transparent",
+            HideStrategy::Strip => "strip",
+            HideStrategy::Dim => "dim",
+        }
+    }
+
+    /// Whether the strategy changes the taskbar window itself
+    pub fn changes_window(&self) -> bool {
+        *self != HideStrategy::AutoHide
+    }
+
+    /// Whether other windows may use the taskbar's space, a dimmed taskbar still takes it
+    pub fn frees_space(&self) -> bool {
+        *self != HideStrategy::Dim
+    }
+
+    /// Record what the strategy is about to change, None if the window is gone
+    pub fn capture(
+        self,
+        window: WindowId,
+        desktop: &dyn Desktop,
+        dim: &DimConfig,
+    ) -> Option<Applied> {
+        desktop.is_visible(window)?;
+
+        let undo = match self {
+            // Taskbars are never meant to be hidden, so showing is the exact undo
+            HideStrategy::Hide => Undo::Show,
+            HideStrategy::AutoHide => Undo::Nothing,
+            HideStrategy::OffScreen | HideStrategy::Strip => {
+                Undo::Rect(desktop.window_rect(window)?)
+            }
+            HideStrategy::Transparent | HideStrategy::Dim => {
+                Undo::Alpha(desktop.window_alpha(window))
+            }
+        };
+
+        let alpha = match self {
+            HideStrategy::Dim => dim.alpha(),
+            _ => 0,
+        };
+
+        Some(Applied {
+            strategy: self,
+            undo,
+            alpha,
+        })
+    }
+}
+
+/// What a strategy changed on a window
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Undo {
+    Nothing,
+    /// Show the window again
+    Show,
+    /// Move the window back to its original rect
+    Rect(Rect),
+    /// Restore the original alpha, None if the window wasn't layered
+    Alpha(Option<u8>),
+}
+
+/// A strategy applied to a window together with what undoes it exactly
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Applied {
+    pub strategy: HideStrategy,
+    pub undo: Undo,
+    /// Alpha a transparent or dimmed window is set to
+    alpha: u8,
+}
+
+impl Applied {
+    /// Keep the original state recorded by a previous strategy if it changed the same thing
+    ///
+    /// The previous strategy is undone in the same step, so the desktop still shows its changes.
+    pub fn inherit(self, previous: &Applied) -> Applied {
+        let undo = match (&self.undo, &previous.undo) {
+            (Undo::Rect(_), Undo::Rect(_)) | (Undo::Alpha(_), Undo::Alpha(_)) => {
+                previous.undo.clone()
+            }
+            _ => self.undo,
+        };
+
+        Applied { undo, ..self }
+    }
+
+    /// Rect the window is moved to, if the strategy moves it
+    fn target_rect(&self, window: WindowId, desktop: &dyn Desktop) -> Option<Rect> {
+        let Undo::Rect(original) = self.undo else {
+            return None;
+        };
+
+        match self.strategy {
+            HideStrategy::OffScreen => Some(Rect::new(
+                OFF_SCREEN_POSITION,
+                OFF_SCREEN_POSITION,
+                OFF_SCREEN_POSITION + original.width(),
+                OFF_SCREEN_POSITION + original.height(),
+            )),
+            HideStrategy::Strip => {
+                let name = desktop.window_monitor(window)?;
+                let monitor = desktop.monitors().into_iter().find(|m| m.name == name)?;
+                Some(original.strip(docked_edge(original, monitor.rect), 1))
+            }
+            _ => None,
+        }
+    }
+
+    /// Actions putting the window into its hidden state
+    pub fn hide_actions(&self, window: WindowId, desktop: &dyn Desktop) -> Vec<Action> {
+        match self.strategy {
+            HideStrategy::Hide => vec![Action::ShowWindow {
+                window,
+                visible: false,
+            }],
+            HideStrategy::AutoHide => Vec::new(),
+            HideStrategy::OffScreen | HideStrategy::Strip => self
+                .target_rect(window, desktop)
+                .map(|rect| Action::MoveWindow { window, rect })
+                .into_iter()
+                .collect(),
+            HideStrategy::Transparent | HideStrategy::Dim => vec![Action::SetWindowAlpha {
+                window,
+                alpha: Some(self.alpha),
+            }],
+        }
+    }
+
+    /// Whether the window is still in its hidden state
+    pub fn holds(&self, window: WindowId, desktop: &dyn Desktop) -> bool {
+        match self.strategy {
+            HideStrategy::Hide => desktop.is_visible(window) == Some(false),
+            HideStrategy::AutoHide => true,
+            HideStrategy::OffScreen | HideStrategy::Strip => {
+                desktop.window_rect(window) == self.target_rect(window, desktop)
+            }
+            HideStrategy::Transparent => desktop.window_alpha(window) == Some(0),
+            // The alpha of a dimmed window changes on hover, only the layered style must stay
+            HideStrategy::Dim => desktop.window_alpha(window).is_some(),
+        }
+    }
+
+    /// Actions restoring exactly what `hide_actions` changed
+    pub fn undo_actions(&self, window: WindowId) -> Vec<Action> {
+        match self.undo {
+            Undo::Nothing => Vec::new(),
+            Undo::Show => vec![Action::ShowWindow {
+                window,
+                visible: true,
+            }],
+            Undo::Rect(rect) => vec![Action::MoveWindow { window, rect }],
+            Undo::Alpha(alpha) => vec![Action::SetWindowAlpha { window, alpha }],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{Backend, Mode, Overlay};
+    use crate::geometry::Edge;
+    use crate::simulation::Scenario;
+    use crate::testing::{desktop, perform, scenario};
+
+    const PRIMARY: WindowId = 1;
+    const SECONDARY: WindowId = 2;
+
+    const STRATEGIES: [HideStrategy; 6] = [
+        HideStrategy::Hide,
+        HideStrategy::AutoHide,
+        HideStrategy::OffScreen,
+        HideStrategy::Transparent,
+        HideStrategy::Strip,
+        HideStrategy::Dim,
+    ];
+
+    fn observe(
+        desktop: &dyn Desktop,
+        window: WindowId,
+    ) -> (Option<bool>, Option<Rect>, Option<u8>) {
+        (
+            desktop.is_visible(window),
+            desktop.window_rect(window),
+            desktop.window_alpha(window),
+        )
+    }
+
+    fn hide(desktop: &Overlay<Scenario>, strategy: HideStrategy, window: WindowId) -> Applied {
+        let applied = strategy
+            .capture(window, desktop, &DimConfig::default())
+            .unwrap();
+        perform(desktop, applied.hide_actions(window, desktop));
+        applied
+    }
+
+    #[test]
+    fn undo_restores_the_window_exactly() {
+        for strategy in STRATEGIES {
+            for window in [PRIMARY, SECONDARY] {
+                let desktop = desktop();
+                let original = observe(&desktop, window);
+
+                let applied = hide(&desktop, strategy, window);
+                assert!(applied.holds(window, &desktop), "{strategy:?}");
+                assert_eq!(
+                    observe(&desktop, window) != original,
+                    strategy.changes_window(),
+                    "{strategy:?}"
+                );
+
+                perform(&desktop, applied.undo_actions(window));
+                assert_eq!(observe(&desktop, window), original, "{strategy:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn hidden_states() {
+        let desktop = desktop();
+        hide(&desktop, HideStrategy::Hide, PRIMARY);
+        assert_eq!(desktop.is_visible(PRIMARY), Some(false));
+
+        let desktop = self::desktop();
+        hide(&desktop, HideStrategy::OffScreen, SECONDARY);
+        assert_eq!(
+            desktop.window_rect(SECONDARY),
+            Some(Rect::new(-32000, -32000, -32000 + 2560, -32000 + 48))
+        );
+
+        let desktop = self::desktop();
+        hide(&desktop, HideStrategy::Strip, SECONDARY);
+        assert_eq!(
+            desktop.window_rect(SECONDARY),
+            Some(Rect::new(1920, 1439, 4480, 1440))
+        );
+
+        let desktop = self::desktop();
+        hide(&desktop, HideStrategy::Transparent, PRIMARY);
+        assert_eq!(desktop.window_alpha(PRIMARY), Some(0));
+
+        let desktop = self::desktop();
+        hide(&desktop, HideStrategy::Dim, PRIMARY);
+        assert_eq!(desktop.window_alpha(PRIMARY), Some(89));
+    }
+
+    #[test]
+    fn vertical_taskbars_strip_along_their_edge() {
+        let mut scenario = scenario();
+        scenario.windows[0].rect = Some(Rect::new(0, 0, 1920, 1080).strip(Edge::Left, 62));
+        let desktop = Overlay::new(scenario, Mode::Simulated);
+
+        hide(&desktop, HideStrategy::Strip, PRIMARY);
+        assert_eq!(desktop.window_rect(PRIMARY), Some(Rect::new(0, 0, 1, 1080)));
+    }
+
+    #[test]
+    fn holds_notices_windows_taken_back() {
+        let desktop = desktop();
+        let hidden = hide(&desktop, HideStrategy::Hide, PRIMARY);
+        desktop.perform(Action::ShowWindow {
+            window: PRIMARY,
+            visible: true,
+        });
+        assert!(!hidden.holds(PRIMARY, &desktop));
+
+        let desktop = self::desktop();
+        let moved = hide(&desktop, HideStrategy::OffScreen, PRIMARY);
+        desktop.perform(Action::MoveWindow {
+            window: PRIMARY,
+            rect: Rect::new(0, 1032, 1920, 1080),
+        });
+        assert!(!moved.holds(PRIMARY, &desktop));
+
+        // Hovering fades a dimmed taskbar in, that is still dimmed
+        let desktop = self::desktop();
+        let dimmed = hide(&desktop, HideStrategy::Dim, PRIMARY);
+        desktop.perform(Action::SetWindowAlpha {
+            window: PRIMARY,
+            alpha: Some(255),
+        });
+        assert!(dimmed.holds(PRIMARY, &desktop));
+        desktop.perform(Action::SetWindowAlpha {
+            window: PRIMARY,
+            alpha: None,
+        });
+        assert!(!dimmed.holds(PRIMARY, &desktop));
+    }
+
+    #[test]
+    fn switching_strategies_keeps_the_original_state() {
+        let desktop = desktop();
+        let original = observe(&desktop, PRIMARY);
+
+        let off_screen = hide(&desktop, HideStrategy::OffScreen, PRIMARY);
+        let strip = HideStrategy::Strip
+            .capture(PRIMARY, &desktop, &DimConfig::default())
+            .unwrap()
+            .inherit(&off_screen);
+        perform(&desktop, strip.hide_actions(PRIMARY, &desktop));
+        assert_eq!(
+            desktop.window_rect(PRIMARY),
+            Some(Rect::new(0, 1079, 1920, 1080))
+        );
+
+        perform(&desktop, strip.undo_actions(PRIMARY));
+        assert_eq!(observe(&desktop, PRIMARY), original);
+
+        // Different things changed, each strategy undoes its own
+        let transparent = hide(&desktop, HideStrategy::Transparent, PRIMARY);
+        let hidden = HideStrategy::Hide
+            .capture(PRIMARY, &desktop, &DimConfig::default())
+            .unwrap()
+            .inherit(&transparent);
+        assert_eq!(hidden.undo, Undo::Show);
+    }
+
+    #[test]
+    fn missing_windows_are_not_captured() {
+        for strategy in STRATEGIES {
+            assert_eq!(
+                strategy.capture(99, &desktop(), &DimConfig::default()),
+                None
+            );
+        }
+    }
+}
@@ -0,0 +1,472 @@
+#[cfg(windows)]
+use crate::get_process_name;
+use crate::i18n;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+#[cfg(windows)]
+use windows::Win32::Foundation::{BOOL, HWND, LPARAM};
+#[cfg(windows)]
+use windows::Win32::UI::WindowsAndMessaging::{
+    EnumWindows, GetClassNameW, GetWindowTextW, IsWindowVisible,
+};
+
+/// A rule selecting (or excluding) the windows thide treats as taskbars
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct Matcher {
+    /// Window class name, compared case-insensitively
+    pub class: Option<String>,
+    /// Executable name of the owning process, e.g. explorer.exe
+    pub process: Option<String>,
+    /// Window title pattern, `*` and `?` wildcards
+    pub title: Option<String>,
+    /// Windows matching this rule are never treated as taskbars
+    pub exclude: bool,
+}
+
+impl Matcher {
+    fn explorer(class: &str) -> Self {
+        Self {
+            class: Some(class.to_string()),
+            process: Some("explorer.exe".to_string()),
+            ..Self::default()
+        }
+    }
+
+    /// Whether the rule has no criteria at all, it would match every window
+    pub fn is_empty(&self) -> bool {
+        self.class.is_none() && self.process.is_none() && self.title.is_none()
+    }
+
+    fn accepts_class(&self, class: &str) -> bool {
+        self.class
+            .as_ref()
+            .is_none_or(|c| c.eq_ignore_ascii_case(class))
+    }
+
+    /// Whether the window shares the class or process this rule looks for
+    fn is_related(&self, window: &WindowIdentity) -> bool {
+        let class = self.class.as_ref();
+        let process = self.process.as_ref();
+
+        class.is_some_and(|c| c.eq_ignore_ascii_case(&window.class))
+            || process.is_some_and(|p| p.eq_ignore_ascii_case(&window.process))
+    }
+
+    /// Describe the first criterion the window fails, None if it matches
+    fn mismatch(&self, window: &WindowIdentity) -> Option<String> {
+        if let Some(class) = &self.class {
+            if !class.eq_ignore_ascii_case(&window.class) {
+                return Some(i18n::text_with(
+                    "rule-class-mismatch",
+                    &[("class", &window.class), ("expected", class)],
+                ));
+            }
+        }
+
+        if let Some(process) = &self.process {
+            if window.process.is_empty() {
+                return Some(i18n::text_with(
+                    "rule-process-unknown",
+                    &[("expected", process)],
+                ));
+            }
+            if !process.eq_ignore_ascii_case(&window.process) {
+                return Some(i18n::text_with(
+                    "rule-process-mismatch",
+                    &[("process", &window.process), ("expected", process)],
+                ));
+            }
+        }
+
+        if let Some(title) = &self.title {
+            if !glob_match(title, &window.title) {
+                return Some(i18n::text_with(
+                    "rule-title-mismatch",
+                    &[("title", &window.title), ("expected", title)],
+                ));
+            }
+        }
+
+        None
+    }
+
+    fn matches(&self, window: &WindowIdentity) -> bool {
+        self.mismatch(window).is_none()
+    }
+}
+
+/// The matchers used when none are configured: the explorer.exe taskbars
+pub fn default_matchers() -> Vec<Matcher> {
+    vec![
+        Matcher::explorer("Shell_TrayWnd"),
+        Matcher::explorer("Shell_SecondaryTrayWnd"),
+    ]
+}
+
+/// Properties of a window the matchers look at
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WindowIdentity {
+    pub class: String,
+    pub process: String,
+    pub title: String,
+}
+
+/// Outcome of matching a candidate window, rule numbers are 0-based indexes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Included(usize),
+    Excluded(usize),
+    Rejected(String),
+}
+
+impl Verdict {
+    pub fn status(&self) -> &'static str {
+        match self {
+            Verdict::Included(_) => "match",
+            Verdict::Excluded(_) => "exclude",
+            Verdict::Rejected(_) => "reject",
+        }
+    }
+
+    /// Why the window was matched, excluded or rejected
+    pub fn reason(&self) -> String {
+        match self {
+            Verdict::Included(rule) => {
+                i18n::text_with("rule-matched", &[("rule", &(rule + 1).to_string())])
+            }
+            Verdict::Excluded(rule) => {
+                i18n::text_with("rule-excluded", &[("rule", &(rule + 1).to_string())])
+            }
+            Verdict::Rejected(reason) => reason.clone(),
+        }
+    }
+}
+
+/// Match a window against the rules, None if it isn't a candidate at all
+pub fn evaluate(matchers: &[Matcher], window: &WindowIdentity) -> Option<Verdict> {
+    let rules = || matchers.iter().enumerate().filter(|(_, m)| !m.is_empty());
+
+    if let Some((i, _)) = rules().find(|(_, m)| m.exclude && m.matches(window)) {
+        return Some(Verdict::Excluded(i));
+    }
+
+    if let Some((i, _)) = rules().find(|(_, m)| !m.exclude && m.matches(window)) {
+        return Some(Verdict::Included(i));
+    }
+
+    // Near misses are reported so users can see why a bar wasn't picked up
+    rules()
+        .filter(|(_, m)| !m.exclude && m.is_related(window))
+        .find_map(|(_, m)| m.mismatch(window))
+        .map(Verdict::Rejected)
+}
+
+/// Case-insensitive wildcard match supporting `*` and `?`
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            // Let the last `*` swallow one more character
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, t));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+static MATCHERS: OnceLock<Vec<Matcher>> = OnceLock::new();
+
+/// Select the taskbar matchers, an empty list keeps the defaults
+pub fn init(matchers: Vec<Matcher>) {
+    let _ = MATCHERS.set(effective(matchers));
+}
+
+/// The configured matchers, or the defaults when the list is empty
+pub fn effective(matchers: Vec<Matcher>) -> Vec<Matcher> {
+    for (i, matcher) in matchers.iter().enumerate() {
+        if matcher.is_empty() {
+            log::warn!("Ignoring taskbar rule #{} without criteria", i + 1);
+        }
+    }
+
+    if matchers.is_empty() {
+        default_matchers()
+    } else {
+        matchers
+    }
+}
+
+/// The matchers selected by `init`
+pub fn matchers() -> &'static [Matcher] {
+    MATCHERS.get_or_init(default_matchers)
+}
+
+/// List every matching and rejected window for the configured rules
+#[cfg(windows)]
+pub fn configured_candidates() -> Vec<Candidate> {
+    list_candidates(matchers())
+}
+
+/// Keep the windows the configured rules treat as taskbars, ordered by the rule that matched
+pub fn select<T>(windows: impl IntoIterator<Item = (T, WindowIdentity)>) -> Vec<T> {
+    select_by(matchers(), windows)
+}
+
+fn select_by<T>(
+    matchers: &[Matcher],
+    windows: impl IntoIterator<Item = (T, WindowIdentity)>,
+) -> Vec<T> {
+    let mut taskbars: Vec<(usize, T)> = windows
+        .into_iter()
+        .filter_map(|(window, identity)| match evaluate(matchers, &identity) {
+            Some(Verdict::Included(rule)) => Some((rule, window)),
+            _ => None,
+        })
+        .collect();
+
+    taskbars.sort_by_key(|(rule, _)| *rule);
+    taskbars.into_iter().map(|(_, window)| window).collect()
+}
+
+/// Whether a window of this class needs its process looked up, None if no rule can match it
+///
+/// Looking up the process is the costly part of identifying a window, so it's skipped unless
+/// a rule for the class checks it.
+fn needs_process(matchers: &[Matcher], class: &str) -> Option<bool> {
+    let rules: Vec<&Matcher> = matchers
+        .iter()
+        .filter(|m| !m.is_empty() && m.accepts_class(class))
+        .collect();
+
+    rules
+        .iter()
+        .any(|m| !m.exclude)
+        .then(|| rules.iter().any(|m| m.process.is_some()))
+}
+
+/// Find all taskbars, ordered by the rule that matched them
+#[cfg(windows)]
+pub fn find_taskbars() -> Vec<HWND> {
+    let matchers = matchers();
+    select(enumerate_all_windows().into_iter().filter_map(|hwnd| {
+        let class = window_class(hwnd);
+        let process = needs_process(matchers, &class)?;
+        Some((hwnd, identify(hwnd, class, process)))
+    }))
+}
+
+/// A window that matched or nearly matched the rules
+#[cfg(windows)]
+pub struct Candidate {
+    pub hwnd: HWND,
+    pub window: WindowIdentity,
+    pub visible: bool,
+    pub verdict: Verdict,
+}
+
+/// List every matching and rejected window for the given rules
+#[cfg(windows)]
+pub fn list_candidates(matchers: &[Matcher]) -> Vec<Candidate> {
+    enumerate_all_windows()
+        .into_iter()
+        .filter_map(|hwnd| {
+            let window = identify(hwnd, window_class(hwnd), true);
+            let verdict = evaluate(matchers, &window)?;
+
+            Some(Candidate {
+                hwnd,
+                window,
+                visible: unsafe { IsWindowVisible(hwnd).as_bool() },
+                verdict,
+            })
+        })
+        .collect()
+}
+
+#[cfg(windows)]
+fn window_class(hwnd: HWND) -> String {
+    let mut class = [0u16; 256];
+    let len = unsafe { GetClassNameW(hwnd, &mut class) }.max(0) as usize;
+    String::from_utf16_lossy(&class[..len])
+}
+
+/// Read the title and, if `process` is set, the process of a window
+#[cfg(windows)]
+fn identify(hwnd: HWND, class: String, process: bool) -> WindowIdentity {
+    let mut title = [0u16; 512];
+    let len = unsafe { GetWindowTextW(hwnd, &mut title) }.max(0) as usize;
+
+    WindowIdentity {
+        class,
+        process: process
+            .then(|| get_process_name(hwnd))
+            .flatten()
+            .unwrap_or_default(),
+        title: String::from_utf16_lossy(&title[..len]),
+    }
+}
+
+/// List all top-level windows, including hidden ones
+#[cfg(windows)]
+pub fn enumerate_all_windows() -> Vec<HWND> {
+    unsafe extern "system" fn callback(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        let windows = &mut *(lparam.0 as *mut Vec<HWND>);
+        windows.push(hwnd);
+        BOOL(1)
+    }
+
+    let mut windows: Vec<HWND> = Vec::new();
+    unsafe {
+        let _ = EnumWindows(Some(callback), LPARAM(&mut windows as *mut _ as isize));
+    }
+    windows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(class: Option<&str>, process: Option<&str>, title: Option<&str>) -> Matcher {
+        Matcher {
+            class: class.map(str::to_string),
+            process: process.map(str::to_string),
+            title: title.map(str::to_string),
+            exclude: false,
+        }
+    }
+
+    fn exclude(matcher: Matcher) -> Matcher {
+        Matcher {
+            exclude: true,
+            ..matcher
+        }
+    }
+
+    fn window(class: &str, process: &str, title: &str) -> WindowIdentity {
+        WindowIdentity {
+            class: class.to_string(),
+            process: process.to_string(),
+            title: title.to_string(),
+        }
+    }
+
+    #[test]
+    fn globs_ignore_case_and_support_wildcards() {
+        assert!(glob_match("YASB*", "yasb - bar"));
+        assert!(glob_match("*bar", "Status BAR"));
+        assert!(glob_match("ba?", "BAR"));
+        assert!(glob_match("*a*b*", "xxaxxbxx"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("", ""));
+        assert!(!glob_match("ba?", "ba"));
+        assert!(!glob_match("bar", "bars"));
+        assert!(!glob_match("", "bar"));
+        assert!(glob_match("ÄÖ*", "äö Leiste"));
+    }
+
+    #[test]
+    fn class_only_and_process_only_rules() {
+        let by_class = [rule(Some("Shell_TrayWnd"), None, None)];
+        let by_process = [rule(None, Some("YASB.exe"), None)];
+        let tray = window("shell_traywnd", "whatever.exe", "");
+        let bar = window("Qt5QWindow", "yasb.exe", "YASB");
+
+        assert_eq!(evaluate(&by_class, &tray), Some(Verdict::Included(0)));
+        assert_eq!(evaluate(&by_class, &bar), None);
+        assert_eq!(evaluate(&by_process, &bar), Some(Verdict::Included(0)));
+        assert_eq!(evaluate(&by_process, &tray), None);
+    }
+
+    #[test]
+    fn near_misses_are_rejected_with_a_reason() {
+        let rules = default_matchers();
+        let verdict = evaluate(&rules, &window("Shell_TrayWnd", "", "")).unwrap();
+        assert_eq!(
+            verdict,
+            Verdict::Rejected("process is unknown, not explorer.exe".to_string())
+        );
+        assert_eq!(verdict.status(), "reject");
+
+        let titled = [rule(None, Some("yasb.exe"), Some("bar*"))];
+        let verdict = evaluate(&titled, &window("Qt", "yasb.exe", "Settings")).unwrap();
+        assert_eq!(
+            verdict.reason(),
+            "title \"Settings\" does not match \"bar*\""
+        );
+    }
+
+    #[test]
+    fn exclude_beats_include_whatever_the_order() {
+        let yasb = window("Qt5QWindow", "yasb.exe", "Settings");
+        let include = rule(None, Some("yasb.exe"), None);
+        let settings = exclude(rule(None, None, Some("settings")));
+
+        let verdict = evaluate(&[include.clone(), settings.clone()], &yasb);
+        assert_eq!(verdict, Some(Verdict::Excluded(1)));
+        assert_eq!(verdict.unwrap().reason(), "excluded by rule #2");
+        assert_eq!(
+            evaluate(&[settings, include], &yasb),
+            Some(Verdict::Excluded(0))
+        );
+    }
+
+    #[test]
+    fn empty_rules_match_nothing() {
+        let rules = [Matcher::default()];
+        assert_eq!(
+            evaluate(&rules, &window("Shell_TrayWnd", "explorer.exe", "")),
+            None
+        );
+        assert_eq!(needs_process(&rules, "Shell_TrayWnd"), None);
+    }
+
+    #[test]
+    fn selected_windows_are_ordered_by_rule() {
+        let rules = [
+            rule(None, None, Some("secondary*")),
+            rule(Some("Shell_TrayWnd"), None, None),
+        ];
+        let windows = [
+            ("tray", window("Shell_TrayWnd", "", "")),
+            ("other", window("Notepad", "", "Secondary notes")),
+            ("unrelated", window("Notepad", "", "notes")),
+        ];
+
+        assert_eq!(select_by(&rules, windows), ["other", "tray"]);
+    }
+
+    #[test]
+    fn processes_are_only_looked_up_when_a_rule_checks_them() {
+        let titles = [rule(None, None, Some("bar*"))];
+        assert_eq!(needs_process(&titles, "AnyClass"), Some(false));
+
+        let rules = default_matchers();
+        assert_eq!(needs_process(&rules, "Shell_TrayWnd"), Some(true));
+        assert_eq!(needs_process(&rules, "Notepad"), None);
+
+        // An exclude rule alone can't make a window a taskbar, but checks the process if it applies
+        let excluded = [
+            rule(Some("Qt5QWindow"), None, None),
+            exclude(rule(None, Some("yasb.exe"), None)),
+        ];
+        assert_eq!(needs_process(&excluded, "Qt5QWindow"), Some(true));
+        assert_eq!(needs_process(&excluded[1..], "Qt5QWindow"), None);
+    }
+}
@@ -0,0 +1,81 @@
+//! Fixtures shared by the unit tests
+
+use crate::backend::{Action, Backend, Mode, Overlay};
+use crate::config::{AnimationConfig, HideConfig, WorkAreaConfig};
+use crate::controller::{Command, DesiredState, Input, Output, TaskbarController};
+use crate::profiles::Profile;
+use crate::simulation::Scenario;
+use std::time::{Duration, Instant};
+
+/// Two monitors with an explorer taskbar each, the desktop icons and a browser
+pub const SCENARIO: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/scenario.json");
+
+pub fn scenario() -> Scenario {
+    Scenario::load(SCENARIO).unwrap()
+}
+
+/// A simulated desktop over the fixture scenario
+pub fn desktop() -> Overlay<Scenario> {
+    Overlay::new(scenario(), Mode::Simulated)
+}
+
+pub fn perform(desktop: &dyn Backend, actions: impl IntoIterator<Item = Action>) {
+    for action in actions {
+        desktop.perform(action);
+    }
+}
+
+/// A controller driving a fake desktop the way the app drives the real one
+pub struct Session<D: Backend = Overlay<Scenario>> {
+    pub controller: TaskbarController,
+    pub desktop: D,
+    /// Fake clock of ticks and frames
+    pub now: Instant,
+}
+
+impl<D: Backend> Session<D> {
+    /// A session that hasn't handled Start yet
+    pub fn new(desktop: D, hide: HideConfig, animation: AnimationConfig) -> Self {
+        Self {
+            controller: TaskbarController::new(WorkAreaConfig::default(), hide, animation),
+            desktop,
+            now: Instant::now(),
+        }
+    }
+
+    /// Start with the default profile
+    pub fn start_input(&self) -> Input {
+        let profile = Profile::default();
+        let state = DesiredState::for_profile(&profile);
+        Input::Start(profile, state)
+    }
+
+    /// Handle an input and perform its actions on the fake desktop
+    pub fn handle(&mut self, input: Input) -> Output {
+        let output = self.controller.handle(input, &self.desktop);
+        perform(&self.desktop, output.actions.iter().cloned());
+        output
+    }
+
+    pub fn command(&mut self, command: Command) -> Output {
+        self.handle(Input::Command(command))
+    }
+
+    /// Tick 250ms after the previous tick or frame
+    pub fn tick(&mut self) -> Output {
+        self.now += Duration::from_millis(250);
+        self.handle(Input::Tick(self.now))
+    }
+
+    /// Draw frames 50ms apart until the animations end, returning how many were drawn
+    pub fn frames(&mut self) -> usize {
+        let mut frames = 0;
+        while self.controller.is_animating() {
+            self.now += Duration::from_millis(50);
+            self.handle(Input::Frame(self.now));
+            frames += 1;
+            assert!(frames < 100, "the animations never end");
+        }
+        frames
+    }
+}
@@ -0,0 +1,707 @@
+use crate::backend::{Action, Backend, Desktop, Mode, WindowId};
+use crate::config::{AnimationConfig, HideConfig, WorkAreaConfig};
+use crate::controller::{Command, DesiredState, Input, TaskbarController};
+use crate::elements::ShellElement;
+use crate::geometry::Rect;
+use crate::profiles::Profile;
+use crate::script::Snapshot;
+#[cfg(windows)]
+use crate::targets;
+use crate::workarea::MonitorGeometry;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::File;
+use std::io::{LineWriter, Write};
+use std::path::Path;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Format version written into the trace header
+pub const TRACE_VERSION: u32 = 1;
+
+/// A read from the desktop, recorded with its answer
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(tag = "query", rename_all = "snake_case")]
+pub enum Query {
+    Taskbars,
+    ElementWindows {
+        element: ShellElement,
+    },
+    Monitors,
+    WindowMonitor {
+        window: WindowId,
+    },
+    IsVisible {
+        window: WindowId,
+    },
+    WindowRect {
+        window: WindowId,
+    },
+    WindowAlpha {
+        window: WindowId,
+    },
+    CursorPosition,
+    ForegroundProcess,
+    #[serde(rename = "appbar_state")]
+    AppBarState,
+}
+
+/// An input of the controller, ticks take their time from the entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "input", rename_all = "snake_case")]
+pub enum TracedInput {
+    Start {
+        profile: Profile,
+        state: DesiredState,
+    },
+    Command {
+        command: Command,
+    },
+    Tick,
+    Frame,
+}
+
+impl TracedInput {
+    fn from_input(input: &Input) -> Self {
+        match input {
+            Input::Start(profile, state) => TracedInput::Start {
+                profile: profile.clone(),
+                state: state.clone(),
+            },
+            Input::Command(command) => TracedInput::Command {
+                command: command.clone(),
+            },
+            Input::Tick(_) => TracedInput::Tick,
+            Input::Frame(_) => TracedInput::Frame,
+        }
+    }
+
+    fn to_input(&self, at: Instant) -> Input {
+        match self {
+            TracedInput::Start { profile, state } => Input::Start(profile.clone(), state.clone()),
+            TracedInput::Command { command } => Input::Command(command.clone()),
+            TracedInput::Tick => Input::Tick(at),
+            TracedInput::Frame => Input::Frame(at),
+        }
+    }
+
+    /// Short description for the replay report
+    pub fn label(&self) -> String {
+        match self {
+            TracedInput::Start { .. } => "start".to_string(),
+            TracedInput::Tick => "tick".to_string(),
+            TracedInput::Frame => "frame".to_string(),
+            TracedInput::Command { command } => match command {
+                Command::Show => "show".to_string(),
+                Command::Hide => "hide".to_string(),
+                Command::Toggle => "toggle".to_string(),
+                Command::Quit => "quit".to_string(),
+                Command::ApplyProfile(profile) => format!("profile {}", profile.name),
+                Command::SetMonitorOverrides(_) => "script decision".to_string(),
+                Command::SetElement(element, hidden) => format!(
+                    "{} {}",
+                    if *hidden { "hide" } else { "show" },
+                    element.as_str()
+                ),
+            },
+        }
+    }
+}
+
+/// A window the taskbar rules matched or rejected when recording started
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TracedWindow {
+    pub hwnd: WindowId,
+    pub class: String,
+    pub process: String,
+    pub title: String,
+    pub visible: bool,
+    pub verdict: String,
+}
+
+/// First entry of every trace
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Header {
+    pub version: u32,
+    pub mode: String,
+    /// Work area settings the controller ran with, replays use them too
+    pub work_area: WorkAreaConfig,
+    /// Hide strategies, traces recorded before they existed used the default
+    #[serde(default)]
+    pub hide: HideConfig,
+    #[serde(default)]
+    pub animation: AnimationConfig,
+    pub windows: Vec<TracedWindow>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    Header(Header),
+    Input {
+        #[serde(flatten)]
+        input: TracedInput,
+    },
+    Observe {
+        #[serde(flatten)]
+        query: Query,
+        answer: Value,
+    },
+    Perform {
+        #[serde(flatten)]
+        action: Action,
+    },
+}
+
+/// A line of the trace file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    /// Milliseconds since recording started
+    pub at_ms: u64,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+struct Sink {
+    writer: LineWriter<File>,
+    started: Instant,
+}
+
+static SINK: Mutex<Option<Sink>> = Mutex::new(None);
+
+/// Start writing a trace to the file, replacing it
+pub fn start(
+    path: impl AsRef<Path>,
+    mode: Mode,
+    work_area: &WorkAreaConfig,
+    hide: &HideConfig,
+    animation: &AnimationConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let file = File::create(path)?;
+    SINK.lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .replace(Sink {
+            writer: LineWriter::new(file),
+            started: Instant::now(),
+        });
+
+    // A simulated desktop is described by its scenario file already
+    let windows = match mode {
+        Mode::Simulated => Vec::new(),
+        _ => candidates(),
+    };
+
+    record(|| {
+        Event::Header(Header {
+            version: TRACE_VERSION,
+            mode: mode.as_str().to_string(),
+            work_area: work_area.clone(),
+            hide: hide.clone(),
+            animation: animation.clone(),
+            windows,
+        })
+    });
+    Ok(())
+}
+
+/// Windows matching or nearly matching the taskbar rules on the live desktop
+#[cfg(windows)]
+fn candidates() -> Vec<TracedWindow> {
+    targets::configured_candidates()
+        .into_iter()
+        .map(|candidate| TracedWindow {
+            hwnd: candidate.hwnd.0 as WindowId,
+            class: candidate.window.class,
+            process: candidate.window.process,
+            title: candidate.window.title,
+            visible: candidate.visible,
+            verdict: format!(
+                "{}: {}",
+                candidate.verdict.status(),
+                candidate.verdict.reason()
+            ),
+        })
+        .collect()
+}
+
+#[cfg(not(windows))]
+fn candidates() -> Vec<TracedWindow> {
+    Vec::new()
+}
+
+/// Append an event if a trace is being recorded
+fn record(event: impl FnOnce() -> Event) {
+    let mut sink = SINK.lock().unwrap_or_else(PoisonError::into_inner);
+    let Some(sink) = sink.as_mut() else {
+        return;
+    };
+
+    let entry = Entry {
+        at_ms: sink.started.elapsed().as_millis() as u64,
+        event: event(),
+    };
+    if let Ok(line) = serde_json::to_string(&entry) {
+        if let Err(e) = writeln!(sink.writer, "{}", line) {
+            log::warn!("Failed to write trace: {}", e);
+        }
+    }
+}
+
+/// Record an input about to be handled by the controller
+pub fn record_input(input: &Input) {
+    record(|| Event::Input {
+        input: TracedInput::from_input(input),
+    });
+}
+
+/// Records every read and action of the wrapped backend into the trace
+pub struct Recorder {
+    inner: Box<dyn Backend>,
+}
+
+impl Recorder {
+    pub fn new(inner: Box<dyn Backend>) -> Self {
+        Self { inner }
+    }
+
+    fn observe<T: Serialize>(&self, query: Query, answer: T) -> T {
+        record(|| Event::Observe {
+            query,
+            answer: serde_json::to_value(&answer).unwrap_or_default(),
+        });
+        answer
+    }
+}
+
+impl Desktop for Recorder {
+    fn taskbars(&self) -> Vec<WindowId> {
+        self.observe(Query::Taskbars, self.inner.taskbars())
+    }
+
+    fn element_windows(&self, element: ShellElement) -> Vec<WindowId> {
+        self.observe(
+            Query::ElementWindows { element },
+            self.inner.element_windows(element),
+        )
+    }
+
+    fn monitors(&self) -> Vec<MonitorGeometry> {
+        self.observe(Query::Monitors, self.inner.monitors())
+    }
+
+    fn window_monitor(&self, window: WindowId) -> Option<String> {
+        self.observe(
+            Query::WindowMonitor { window },
+            self.inner.window_monitor(window),
+        )
+    }
+
+    fn is_visible(&self, window: WindowId) -> Option<bool> {
+        self.observe(Query::IsVisible { window }, self.inner.is_visible(window))
+    }
+
+    fn window_rect(&self, window: WindowId) -> Option<Rect> {
+        self.observe(Query::WindowRect { window }, self.inner.window_rect(window))
+    }
+
+    fn window_alpha(&self, window: WindowId) -> Option<u8> {
+        self.observe(
+            Query::WindowAlpha { window },
+            self.inner.window_alpha(window),
+        )
+    }
+
+    fn cursor_position(&self) -> Option<(i32, i32)> {
+        self.observe(Query::CursorPosition, self.inner.cursor_position())
+    }
+
+    fn foreground_process(&self) -> Option<String> {
+        self.observe(Query::ForegroundProcess, self.inner.foreground_process())
+    }
+
+    fn appbar_state(&self) -> u32 {
+        self.observe(Query::AppBarState, self.inner.appbar_state())
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        // Only the script sees snapshots, the controller never does
+        self.inner.snapshot()
+    }
+}
+
+impl Backend for Recorder {
+    fn mode(&self) -> Mode {
+        self.inner.mode()
+    }
+
+    fn perform(&self, action: Action) {
+        record(|| Event::Perform {
+            action: action.clone(),
+        });
+        self.inner.perform(action);
+    }
+}
+
+/// An input with what the desktop answered and what was performed while handling it
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub at_ms: u64,
+    pub input: TracedInput,
+    pub observations: Vec<(Query, Value)>,
+    pub actions: Vec<Action>,
+}
+
+/// A recorded trace, split into one step per input
+#[derive(Debug, Clone)]
+pub struct Trace {
+    pub header: Header,
+    pub steps: Vec<Step>,
+}
+
+impl Trace {
+    /// Load a trace file written by `--record`
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        let text = std::fs::read_to_string(path)?;
+        let mut header = None;
+        let mut steps: Vec<Step> = Vec::new();
+
+        for (number, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let entry: Entry =
+                serde_json::from_str(line).map_err(|e| format!("Line {}: {}", number + 1, e))?;
+
+            match entry.event {
+                Event::Header(h) => {
+                    if h.version > TRACE_VERSION {
+                        return Err(format!(
+                            "Trace version {} is newer than the supported version {}",
+                            h.version, TRACE_VERSION
+                        )
+                        .into());
+                    }
+                    header = Some(h);
+                }
+                Event::Input { input } => steps.push(Step {
+                    at_ms: entry.at_ms,
+                    input,
+                    observations: Vec::new(),
+                    actions: Vec::new(),
+                }),
+                // Reads and actions before the first input don't belong to the controller
+                Event::Observe { query, answer } => {
+                    if let Some(step) = steps.last_mut() {
+                        step.observations.push((query, answer));
+                    }
+                }
+                Event::Perform { action } => {
+                    if let Some(step) = steps.last_mut() {
+                        step.actions.push(action);
+                    }
+                }
+            }
+        }
+
+        let header = header.ok_or("The trace has no header")?;
+        Ok(Self { header, steps })
+    }
+
+    /// Run a fresh controller through the recorded inputs and report where its actions differ
+    pub fn replay(&self) -> Vec<Divergence> {
+        let desktop = ReplayDesktop::default();
+        let mut controller = TaskbarController::new(
+            self.header.work_area.clone(),
+            self.header.hide.clone(),
+            self.header.animation.clone(),
+        );
+        let started = Instant::now();
+
+        self.steps
+            .iter()
+            .enumerate()
+            .filter_map(|(index, step)| {
+                desktop.queue(&step.observations);
+                let at = started + Duration::from_millis(step.at_ms);
+                let actual = controller.handle(step.input.to_input(at), &desktop).actions;
+
+                (actual != step.actions).then(|| Divergence {
+                    step: index,
+                    at_ms: step.at_ms,
+                    input: step.input.clone(),
+                    expected: step.actions.clone(),
+                    actual,
+                })
+            })
+            .collect()
+    }
+}
+
+/// A step whose replayed actions differ from the recorded ones
+#[derive(Debug, Clone)]
+pub struct Divergence {
+    /// 0-based index of the step
+    pub step: usize,
+    pub at_ms: u64,
+    pub input: TracedInput,
+    pub expected: Vec<Action>,
+    pub actual: Vec<Action>,
+}
+
+#[derive(Default)]
+struct Answers {
+    /// Answers recorded during the current step, in order
+    queued: BTreeMap<Query, VecDeque<Value>>,
+    /// Last answer given to each query, reused once the queue runs dry
+    latest: BTreeMap<Query, Value>,
+}
+
+/// Answers the controller's reads from a trace
+#[derive(Default)]
+struct ReplayDesktop {
+    answers: Mutex<Answers>,
+}
+
+impl ReplayDesktop {
+    fn queue(&self, observations: &[(Query, Value)]) {
+        let mut answers = self.answers.lock().unwrap_or_else(PoisonError::into_inner);
+        answers.queued.clear();
+        for (query, answer) in observations {
+            answers
+                .queued
+                .entry(query.clone())
+                .or_default()
+                .push_back(answer.clone());
+        }
+    }
+
+    fn answer<T: DeserializeOwned + Default>(&self, query: Query) -> T {
+        let mut answers = self.answers.lock().unwrap_or_else(PoisonError::into_inner);
+        let answer = match answers.queued.get_mut(&query).and_then(VecDeque::pop_front) {
+            Some(answer) => {
+                answers.latest.insert(query, answer.clone());
+                answer
+            }
+            // The replayed controller asked something the recorded one didn't
+            None => answers.latest.get(&query).cloned().unwrap_or_default(),
+        };
+
+        serde_json::from_value(answer).unwrap_or_default()
+    }
+}
+
+impl Desktop for ReplayDesktop {
+    fn taskbars(&self) -> Vec<WindowId> {
+        self.answer(Query::Taskbars)
+    }
+
+    fn element_windows(&self, element: ShellElement) -> Vec<WindowId> {
+        self.answer(Query::ElementWindows { element })
+    }
+
+    fn monitors(&self) -> Vec<MonitorGeometry> {
+        self.answer(Query::Monitors)
+    }
+
+    fn window_monitor(&self, window: WindowId) -> Option<String> {
+        self.answer(Query::WindowMonitor { window })
+    }
+
+    fn is_visible(&self, window: WindowId) -> Option<bool> {
+        self.answer(Query::IsVisible { window })
+    }
+
+    fn window_rect(&self, window: WindowId) -> Option<Rect> {
+        self.answer(Query::WindowRect { window })
+    }
+
+    fn window_alpha(&self, window: WindowId) -> Option<u8> {
+        self.answer(Query::WindowAlpha { window })
+    }
+
+    fn cursor_position(&self) -> Option<(i32, i32)> {
+        self.answer(Query::CursorPosition)
+    }
+
+    fn foreground_process(&self) -> Option<String> {
+        self.answer(Query::ForegroundProcess)
+    }
+
+    fn appbar_state(&self) -> u32 {
+        self.answer(Query::AppBarState)
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::HideStrategy;
+    use crate::testing::{self, Session};
+    use std::path::PathBuf;
+
+    fn temp_file(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("thide-{}-{}", std::process::id(), name))
+    }
+
+    fn header(version: u32) -> String {
+        let header = Entry {
+            at_ms: 0,
+            event: Event::Header(Header {
+                version,
+                mode: "simulated".to_string(),
+                work_area: WorkAreaConfig::default(),
+                hide: HideConfig::default(),
+                animation: AnimationConfig::default(),
+                windows: Vec::new(),
+            }),
+        };
+        serde_json::to_string(&header).unwrap()
+    }
+
+    /// Held while a test records, the sink is shared by the whole process
+    static RECORDING: Mutex<()> = Mutex::new(());
+
+    /// Drive a controller over the scenario like the app does, recording into a trace file
+    fn record_session(path: &Path, hide: &HideConfig) {
+        let _recording = RECORDING.lock().unwrap_or_else(PoisonError::into_inner);
+        let work_area = WorkAreaConfig::default();
+        let animation = AnimationConfig::default();
+        start(path, Mode::Simulated, &work_area, hide, &animation).unwrap();
+
+        let recorder = Recorder::new(Box::new(testing::desktop()));
+        let mut session = Session::new(recorder, hide.clone(), animation);
+        let started = session.now;
+
+        let inputs = [
+            session.start_input(),
+            Input::Tick(started + Duration::from_millis(250)),
+            Input::Command(Command::Show),
+            Input::Command(Command::SetElement(ShellElement::DesktopIcons, true)),
+            Input::Command(Command::Hide),
+            Input::Tick(started + Duration::from_millis(500)),
+            Input::Command(Command::Quit),
+        ];
+        for input in inputs {
+            record_input(&input);
+            session.handle(input);
+        }
+
+        // Close the trace, so nothing another test records ends up in it
+        SINK.lock().unwrap_or_else(PoisonError::into_inner).take();
+    }
+
+    #[test]
+    fn recorded_sessions_replay_without_divergence() {
+        let path = temp_file("session.jsonl");
+        let hide = HideConfig {
+            strategy: HideStrategy::OffScreen,
+            ..HideConfig::default()
+        };
+        record_session(&path, &hide);
+
+        let trace = Trace::load(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(trace.header.version, TRACE_VERSION);
+        assert_eq!(trace.header.hide.strategy, HideStrategy::OffScreen);
+        let labels: Vec<String> = trace.steps.iter().map(|step| step.input.label()).collect();
+        assert_eq!(
+            labels,
+            [
+                "start",
+                "tick",
+                "show",
+                "hide desktop-icons",
+                "hide",
+                "tick",
+                "quit"
+            ]
+        );
+        assert!(trace.steps.iter().all(|step| !step.observations.is_empty()));
+        assert!(!trace.steps[0].actions.is_empty());
+
+        let divergences = trace.replay();
+        assert!(divergences.is_empty(), "{:?}", divergences);
+    }
+
+    #[test]
+    fn changed_behavior_is_reported_per_step() {
+        let path = temp_file("changed.jsonl");
+        record_session(&path, &HideConfig::default());
+        let mut trace = Trace::load(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        // Pretend the recorded thide didn't hide the desktop icons
+        let recorded = std::mem::take(&mut trace.steps[3].actions);
+        let divergences = trace.replay();
+
+        assert_eq!(divergences.len(), 1);
+        let divergence = &divergences[0];
+        assert_eq!(divergence.step, 3);
+        assert!(divergence.expected.is_empty());
+        assert_eq!(divergence.actual, recorded);
+        assert!(divergence.actual.contains(&Action::ShowWindow {
+            window: 3,
+            visible: false
+        }));
+    }
+
+    #[test]
+    fn reads_before_the_first_input_are_skipped() {
+        let show = Entry {
+            at_ms: 10,
+            event: Event::Input {
+                input: TracedInput::Command {
+                    command: Command::Show,
+                },
+            },
+        };
+        let read = Entry {
+            at_ms: 5,
+            event: Event::Observe {
+                query: Query::Taskbars,
+                answer: serde_json::json!([1]),
+            },
+        };
+        // Reads before the first input and blank lines are skipped
+        let lines = [
+            header(TRACE_VERSION),
+            serde_json::to_string(&read).unwrap(),
+            String::new(),
+            serde_json::to_string(&show).unwrap(),
+        ];
+        let path = temp_file("manual.jsonl");
+        std::fs::write(&path, lines.join("\n")).unwrap();
+        let trace = Trace::load(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        // Show before start changes nothing, whatever the desktop looks like
+        assert_eq!(trace.steps.len(), 1);
+        assert!(trace.steps[0].observations.is_empty());
+        assert!(trace.replay().is_empty());
+    }
+
+    #[test]
+    fn newer_and_headerless_traces_are_refused() {
+        let path = temp_file("newer.jsonl");
+        std::fs::write(&path, header(TRACE_VERSION + 1)).unwrap();
+        let newer = Trace::load(&path).unwrap_err().to_string();
+        assert!(newer.contains("newer"), "{}", newer);
+
+        std::fs::write(&path, "").unwrap();
+        let headerless = Trace::load(&path).unwrap_err().to_string();
+        assert!(headerless.contains("no header"), "{}", headerless);
+
+        std::fs::write(&path, "{not json").unwrap();
+        let broken = Trace::load(&path).unwrap_err().to_string();
+        assert!(broken.starts_with("Line 1:"), "{}", broken);
+        let _ = std::fs::remove_file(&path);
+    }
+}
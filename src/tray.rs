@@ -0,0 +1,190 @@
+use crate::app::{self, App, EventSender, IPCMessage};
+use crate::backend::Mode;
+use crate::cli::LaunchOptions;
+use crate::controller::Command;
+use crate::i18n;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Instant;
+use tray_icon::{
+    menu::{CheckMenuItem, Menu, MenuEvent, MenuItem, Submenu},
+    TrayIconBuilder,
+};
+use winit::event_loop::{ControlFlow, EventLoopBuilder};
+
+/// Load the application icon from embedded resources, with a badge in dry-run or simulation mode
+fn load_icon(badge: bool) -> tray_icon::Icon {
+    const ICON_DATA: &[u8] = include_bytes!("../assets/icon.ico");
+    load_icon_file(ICON_DATA, badge).expect("Failed to load icon")
+}
+
+/// Paint an amber dot into the bottom-right corner of an RGBA image
+fn paint_badge(rgba: &mut [u8], width: u32, height: u32) {
+    let radius = (width.min(height) / 4) as i64;
+    let (center_x, center_y) = (width as i64 - radius, height as i64 - radius);
+
+    for y in 0..height as i64 {
+        for x in 0..width as i64 {
+            let (dx, dy) = (x - center_x, y - center_y);
+            if dx * dx + dy * dy <= radius * radius {
+                let i = (y * width as i64 + x) as usize * 4;
+                rgba[i..i + 4].copy_from_slice(&[0xFF, 0xA0, 0x00, 0xFF]);
+            }
+        }
+    }
+}
+
+/// Load an icon from raw ICO file data
+fn load_icon_file(data: &[u8], badge: bool) -> Result<tray_icon::Icon, Box<dyn std::error::Error>> {
+    let icon_dir = ico::IconDir::read(std::io::Cursor::new(data))?;
+
+    let entry = icon_dir
+        .entries()
+        .iter()
+        .max_by_key(|e| e.width() as u32 * e.height() as u32)
+        .ok_or("No icon entries found")?;
+
+    let image = entry.decode()?;
+    let mut rgba = image.rgba_data().to_vec();
+    let width = image.width();
+    let height = image.height();
+    if badge {
+        paint_badge(&mut rgba, width, height);
+    }
+
+    Ok(tray_icon::Icon::from_rgba(rgba, width, height)?)
+}
+
+/// Run the app with a tray icon, driven by the winit event loop
+pub fn run(
+    launch: &LaunchOptions,
+    instance_name: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some((config, config_error, instance_lock)) = app::prepare(launch, instance_name)? else {
+        return Ok(());
+    };
+
+    let event_loop = EventLoopBuilder::<IPCMessage>::with_user_event().build()?;
+    let proxy = Mutex::new(event_loop.create_proxy());
+    let send: EventSender = Arc::new(move |message| {
+        proxy
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .send_event(message)
+            .is_ok()
+    });
+    let mut app = App::start(config, config_error, launch, instance_lock, send);
+    let mode = app.mode();
+
+    // Build tray menu
+    let tray_menu = Menu::new();
+    let show_item = MenuItem::new(i18n::text("tray-show"), true, None);
+    let hide_item = MenuItem::new(i18n::text("tray-hide"), true, None);
+    let quit_item = MenuItem::new(i18n::text("tray-quit"), true, None);
+
+    // Profiles behave as radio items, exactly one is checked
+    let mut profile_name = app.profile_name().to_string();
+    let mut flapping = Vec::new();
+    let profile_menu = Submenu::new(i18n::text("tray-profile"), true);
+    let profile_items: Vec<(String, CheckMenuItem)> = app
+        .profile_names()
+        .into_iter()
+        .map(|name| {
+            let item = CheckMenuItem::new(&name, true, name == profile_name, None);
+            (name, item)
+        })
+        .collect();
+    for (_, item) in &profile_items {
+        profile_menu.append(item)?;
+    }
+
+    if mode != Mode::Live {
+        let mode_item = MenuItem::new(
+            i18n::text_with("tray-simulation", &[("mode", mode.as_str())]),
+            false,
+            None,
+        );
+        tray_menu.append(&mode_item)?;
+    }
+    tray_menu.append(&show_item)?;
+    tray_menu.append(&hide_item)?;
+    tray_menu.append(&profile_menu)?;
+    tray_menu.append(&quit_item)?;
+
+    // Create tray icon
+    let tooltip = match mode {
+        Mode::Live => i18n::text("app-name"),
+        mode => i18n::text_with("tray-tooltip-mode", &[("mode", mode.as_str())]),
+    };
+    let tray_icon = TrayIconBuilder::new()
+        .with_menu(Box::new(tray_menu))
+        .with_tooltip(&tooltip)
+        .with_icon(load_icon(mode != Mode::Live))
+        .build()?;
+
+    let menu_channel = MenuEvent::receiver();
+
+    event_loop.run(move |event, elwt| {
+        elwt.set_control_flow(ControlFlow::Wait);
+
+        // Handle IPC messages from CLI and the background threads
+        let ipc_command = match event {
+            winit::event::Event::UserEvent(message) => app.on_message(message),
+            _ => None,
+        };
+
+        // Handle tray menu events
+        let mut profile_clicked = false;
+        let menu_command = menu_channel.try_recv().ok().and_then(|menu_event| {
+            let event_id = menu_event.id;
+
+            if event_id == show_item.id() {
+                Some(Command::Show)
+            } else if event_id == hide_item.id() {
+                Some(Command::Hide)
+            } else if event_id == quit_item.id() {
+                Some(Command::Quit)
+            } else {
+                let (name, _) = profile_items
+                    .iter()
+                    .find(|(_, item)| event_id == item.id())?;
+                profile_clicked = true;
+                app.profile_command(name)
+            }
+        });
+
+        let tick_command = app.on_tick(Instant::now());
+        if let Some(deadline) = app.deadline() {
+            elwt.set_control_flow(ControlFlow::WaitUntil(deadline));
+        }
+
+        for command in ipc_command
+            .into_iter()
+            .chain(menu_command)
+            .chain(tick_command)
+        {
+            if !app.execute(command) {
+                elwt.exit();
+                return;
+            }
+        }
+
+        // A check item toggles itself when clicked, even the active one or one that failed to apply
+        if profile_clicked || app.profile_name() != profile_name {
+            profile_name = app.profile_name().to_string();
+            for (name, item) in &profile_items {
+                item.set_checked(*name == profile_name);
+            }
+        }
+
+        if app.flapping() != flapping {
+            flapping = app.flapping().to_vec();
+            let text = match flapping.first() {
+                Some(report) => format!("{} - {}", tooltip, report.message()),
+                None => tooltip.clone(),
+            };
+            let _ = tray_icon.set_tooltip(Some(text));
+        }
+    })?;
+
+    Ok(())
+}
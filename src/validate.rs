@@ -0,0 +1,166 @@
+use crate::config::Config;
+use crate::hotkeys::{self, HotkeyAction};
+use crate::i18n;
+
+/// Describe a config parse error, suggesting the closest known key or value for a misspelled one
+pub fn describe(error: &toml::de::Error) -> String {
+    let description = error.to_string();
+    match suggestion(error.message()) {
+        Some(known) => format!(
+            "{}{}",
+            description,
+            i18n::text_with("validate-suggestion", &[("name", &known)])
+        ),
+        None => description,
+    }
+}
+
+/// Closest expected name for an "unknown field" or "unknown variant" message
+fn suggestion(message: &str) -> Option<String> {
+    let rest = message
+        .strip_prefix("unknown field `")
+        .or_else(|| message.strip_prefix("unknown variant `"))?;
+    let (unknown, expected) = rest.split_once('`')?;
+
+    closest(unknown, expected.split('`').skip(1).step_by(2)).map(str::to_string)
+}
+
+/// The known name closest to a misspelled one, None if none is close
+pub fn closest<'a>(unknown: &str, known: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    known
+        .into_iter()
+        .map(|known| (distance(&unknown.to_lowercase(), known), known))
+        .filter(|(distance, _)| *distance <= (unknown.chars().count() / 3).max(1))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, known)| known)
+}
+
+/// Levenshtein distance between two strings
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                diagonal
+            } else {
+                1 + diagonal.min(above).min(row[j])
+            };
+            diagonal = above;
+        }
+    }
+
+    row[b.len()]
+}
+
+/// Problems in a config that parsed, but won't work as intended
+pub fn problems(config: &Config) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    for (i, matcher) in config.taskbars.iter().enumerate() {
+        if matcher.is_empty() {
+            problems.push(i18n::text_with(
+                "validate-empty-rule",
+                &[("rule", &(i + 1).to_string())],
+            ));
+        }
+    }
+
+    let profile_hotkeys = config.profiles.iter().filter_map(|(name, profile)| {
+        let scope = i18n::text_with("validate-hotkey-profile", &[("name", name)]);
+        Some((scope, profile.hotkeys.as_ref()?))
+    });
+    for (scope, bindings) in
+        std::iter::once(("[hotkeys]".to_string(), &config.hotkeys)).chain(profile_hotkeys)
+    {
+        for (keys, action) in bindings {
+            if let Err(e) = hotkeys::parse_hotkey(keys).and(HotkeyAction::parse(action)) {
+                problems.push(i18n::text_with(
+                    "validate-hotkey",
+                    &[("keys", keys), ("scope", &scope), ("error", &e.to_string())],
+                ));
+            }
+        }
+    }
+
+    if let Some(script) = &config.script.path {
+        if !script.exists() {
+            problems.push(i18n::text_with(
+                "validate-script-missing",
+                &[("path", &script.display().to_string())],
+            ));
+        }
+    }
+
+    if let Some(language) = &config.language {
+        if i18n::supported(language).is_none() {
+            problems.push(i18n::text_with(
+                "validate-unknown-language",
+                &[
+                    ("language", language),
+                    ("available", &i18n::available().join(", ")),
+                ],
+            ));
+        }
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(toml: &str) -> String {
+        describe(&toml::from_str::<Config>(toml).unwrap_err())
+    }
+
+    #[test]
+    fn misspelled_keys_suggest_the_closest_one() {
+        let description = error("[hide]\nstratgy = \"dim\"\n");
+        assert!(description.contains("line 2, column 1"), "{}", description);
+        assert!(description.ends_with("help: did you mean `strategy`?"));
+    }
+
+    #[test]
+    fn misspelled_values_suggest_the_closest_variant() {
+        let description = error("[hide]\nstrategy = \"dimm\"\n");
+        assert!(description.contains("unknown variant `dimm`"));
+        assert!(description.ends_with("help: did you mean `dim`?"));
+    }
+
+    #[test]
+    fn distant_keys_get_no_suggestion() {
+        let description = error("[hide]\nxyzzyplugh = 1\n");
+        assert!(description.contains("unknown field `xyzzyplugh`"));
+        assert!(!description.contains("help:"));
+
+        assert_eq!(closest("colour", ["strategy", "monitors"]), None);
+    }
+
+    #[test]
+    fn nested_tables_and_arrays_point_at_the_offending_key() {
+        let description = error("[hide.dim]\nopacity = 0.5\nrevel_on_hover = true\n");
+        assert!(description.contains("line 3, column 1"), "{}", description);
+        assert!(description.ends_with("help: did you mean `reveal_on_hover`?"));
+
+        // Only the second rule is misspelled
+        let description = error("[[taskbars]]\nclass = \"a\"\n\n[[taskbars]]\nproces = \"b\"\n");
+        assert!(description.contains("line 5, column 1"), "{}", description);
+        assert!(description.ends_with("help: did you mean `process`?"));
+    }
+
+    #[test]
+    fn closest_ignores_case_and_prefers_the_nearest() {
+        assert_eq!(
+            closest("Stratgy", ["strategy", "monitors"]),
+            Some("strategy")
+        );
+        assert_eq!(closest("dimm", ["hide", "dim", "strip"]), Some("dim"));
+        assert_eq!(closest("x", ["hide"]), None);
+    }
+}
@@ -0,0 +1,103 @@
+use crate::geometry::Rect;
+#[cfg(windows)]
+use crate::geometry::DEFAULT_DPI;
+#[cfg(windows)]
+use crate::monitor_name;
+use serde::{Deserialize, Serialize};
+#[cfg(windows)]
+use std::mem;
+#[cfg(windows)]
+use windows::Win32::Foundation::{BOOL, LPARAM, RECT};
+#[cfg(windows)]
+use windows::Win32::Graphics::Gdi::{
+    EnumDisplayMonitors, GetMonitorInfoW, HDC, HMONITOR, MONITORINFO,
+};
+#[cfg(windows)]
+use windows::Win32::UI::HiDpi::{GetDpiForMonitor, MDT_EFFECTIVE_DPI};
+#[cfg(windows)]
+use windows::Win32::UI::WindowsAndMessaging::MONITORINFOF_PRIMARY;
+
+/// Position, work area and DPI of a monitor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonitorGeometry {
+    /// 1-based monitor number
+    pub index: usize,
+    /// Device name, e.g. \\.\DISPLAY1
+    pub name: String,
+    pub rect: Rect,
+    pub work: Rect,
+    pub dpi: u32,
+    /// Whether this is the primary monitor, missing in older traces
+    #[serde(default)]
+    pub primary: bool,
+}
+
+#[cfg(windows)]
+impl From<RECT> for Rect {
+    fn from(rect: RECT) -> Self {
+        Rect::new(rect.left, rect.top, rect.right, rect.bottom)
+    }
+}
+
+#[cfg(windows)]
+impl From<Rect> for RECT {
+    fn from(rect: Rect) -> Self {
+        RECT {
+            left: rect.left,
+            top: rect.top,
+            right: rect.right,
+            bottom: rect.bottom,
+        }
+    }
+}
+
+/// List all monitors with their geometry
+#[cfg(windows)]
+pub fn monitors() -> Vec<MonitorGeometry> {
+    unsafe extern "system" fn callback(
+        monitor: HMONITOR,
+        _: HDC,
+        _: *mut RECT,
+        lparam: LPARAM,
+    ) -> BOOL {
+        let monitors = &mut *(lparam.0 as *mut Vec<HMONITOR>);
+        monitors.push(monitor);
+        BOOL(1)
+    }
+
+    let mut handles: Vec<HMONITOR> = Vec::new();
+    unsafe {
+        let _ = EnumDisplayMonitors(
+            HDC::default(),
+            None,
+            Some(callback),
+            LPARAM(&mut handles as *mut _ as isize),
+        );
+    }
+
+    handles
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, monitor)| unsafe {
+            let mut info: MONITORINFO = mem::zeroed();
+            info.cbSize = mem::size_of::<MONITORINFO>() as u32;
+            if !GetMonitorInfoW(monitor, &mut info).as_bool() {
+                return None;
+            }
+
+            let (mut dpi_x, mut dpi_y) = (0, 0);
+            if GetDpiForMonitor(monitor, MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y).is_err() {
+                dpi_x = DEFAULT_DPI;
+            }
+
+            Some(MonitorGeometry {
+                index: i + 1,
+                name: monitor_name(monitor).unwrap_or_default(),
+                rect: info.rcMonitor.into(),
+                work: info.rcWork.into(),
+                dpi: dpi_x,
+                primary: info.dwFlags & MONITORINFOF_PRIMARY != 0,
+            })
+        })
+        .collect()
+}